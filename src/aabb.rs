@@ -2,6 +2,7 @@ use super::ray::Ray;
 use super::vec3::{Point3, Vec3};
 
 #[derive(Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct AABB {
     minimum: Point3,
     maximum: Point3,
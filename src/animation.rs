@@ -0,0 +1,296 @@
+use super::camera::Camera;
+use super::exposure::Exposure;
+use super::vec3::{Point3, Vec3};
+
+use std::path::PathBuf;
+
+#[derive(Clone, Copy)]
+pub struct CameraKey {
+    pub frame: f32,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vfov: f32,
+    pub focus_dist: f32,
+    pub aperture: f32,
+}
+
+impl CameraKey {
+    fn lerp(&self, other: &Self, frame: f32) -> Self {
+        let t = (frame - self.frame) / (other.frame - self.frame);
+        Self {
+            frame,
+            lookfrom: (1.0 - t) * self.lookfrom + t * other.lookfrom,
+            lookat: (1.0 - t) * self.lookat + t * other.lookat,
+            vfov: (1.0 - t) * self.vfov + t * other.vfov,
+            focus_dist: (1.0 - t) * self.focus_dist + t * other.focus_dist,
+            aperture: (1.0 - t) * self.aperture + t * other.aperture,
+        }
+    }
+
+    // Like `lerp`, but swings `lookfrom` around `lookat` by angle about `up`, so the
+    // camera keeps its distance instead of cutting the corner between keys.
+    fn orbit_lerp(&self, other: &Self, frame: f32, up: Vec3) -> Self {
+        let mut key = self.lerp(other, frame);
+        let up = up.normalized();
+        let split = |k: &Self| {
+            let offset = k.lookfrom - k.lookat;
+            let height = offset.dot(up);
+            (offset - height * up, height)
+        };
+        let ((a, height_a), (b, height_b)) = (split(self), split(other));
+        if a.near_zero() || b.near_zero() {
+            return key;
+        }
+        let t = (frame - self.frame) / (other.frame - self.frame);
+        let angle = t * up.dot(a.cross(b)).atan2(a.dot(b));
+        let radius = (1.0 - t) * a.length() + t * b.length();
+        let a = a.normalized();
+        let around = angle.cos() * a + angle.sin() * up.cross(a);
+        let height = (1.0 - t) * height_a + t * height_b;
+        key.lookfrom = key.lookat + radius * around + height * up;
+        key
+    }
+}
+
+pub struct CameraPath {
+    keys: Vec<CameraKey>,
+    vup: Vec3,
+    orbit: bool,
+}
+
+impl CameraPath {
+    pub fn new(mut keys: Vec<CameraKey>, vup: Vec3) -> Self {
+        assert!(!keys.is_empty(), "camera path needs a key");
+        assert!(
+            keys.iter().all(|k| k.frame.is_finite()),
+            "camera key frames must be finite"
+        );
+        keys.sort_by(|a, b| a.frame.total_cmp(&b.frame));
+        Self {
+            keys,
+            vup,
+            orbit: false,
+        }
+    }
+
+    // Moves the camera between keys around its `lookat`, taking the shorter way about
+    // `vup`, rather than in a straight line.
+    pub fn with_orbit(mut self) -> Self {
+        self.orbit = true;
+        self
+    }
+
+    // Circles `lookat` at a fixed radius and height, one revolution over the frame range.
+    pub fn turntable(
+        lookat: Point3,
+        radius: f32,
+        height: f32,
+        frames: (u32, u32),
+        vfov: f32,
+    ) -> Self {
+        let (start, end) = frames;
+        assert!(end > start, "turntable needs an end frame after its start");
+        // Quarter turns, so each step's shorter way around goes the same direction.
+        let steps = 4;
+        let keys = (0..=steps)
+            .map(|i| {
+                let angle = 2.0 * std::f32::consts::PI * i as f32 / steps as f32;
+                let offset = Vec3::new(radius * angle.sin(), height, radius * angle.cos());
+                CameraKey {
+                    frame: start as f32 + (end - start) as f32 * i as f32 / steps as f32,
+                    lookfrom: lookat + offset,
+                    lookat,
+                    vfov,
+                    focus_dist: offset.length(),
+                    aperture: 0.0,
+                }
+            })
+            .collect();
+        Self::new(keys, Vec3::new(0.0, 1.0, 0.0)).with_orbit()
+    }
+
+    pub fn key(&self, frame: f32) -> CameraKey {
+        let first = &self.keys[0];
+        let last = &self.keys[self.keys.len() - 1];
+        if frame <= first.frame {
+            return *first;
+        }
+        if frame >= last.frame {
+            return *last;
+        }
+        let i = self.keys.partition_point(|k| k.frame <= frame);
+        if self.orbit {
+            self.keys[i - 1].orbit_lerp(&self.keys[i], frame, self.vup)
+        } else {
+            self.keys[i - 1].lerp(&self.keys[i], frame)
+        }
+    }
+}
+
+pub struct Frame {
+    pub number: u32,
+    pub path: PathBuf,
+    pub camera: Camera,
+}
+
+// Object motion is keyframed in seconds (see `AnimatedTransform`), so frame `n` exposes
+// the scene over [n / fps, (n + shutter) / fps].
+pub struct Animation {
+    pub camera: CameraPath,
+    pub start_frame: u32,
+    pub end_frame: u32,
+    pub fps: f32,
+    pub shutter: f32,
+    pub exposure: Exposure,
+    pub aspect_ratio: f32,
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub skip_existing: bool,
+}
+
+impl Animation {
+    pub fn new(camera: CameraPath, start_frame: u32, end_frame: u32, aspect_ratio: f32) -> Self {
+        Self {
+            camera,
+            start_frame,
+            end_frame,
+            fps: 24.0,
+            shutter: 0.5,
+            exposure: Exposure::default(),
+            aspect_ratio,
+            directory: PathBuf::from("frames"),
+            prefix: "frame_".to_string(),
+            skip_existing: false,
+        }
+    }
+
+    pub fn time(&self, frame: u32) -> f32 {
+        frame as f32 / self.fps
+    }
+
+    pub fn duration(&self) -> (f32, f32) {
+        (
+            self.time(self.start_frame),
+            self.time(self.end_frame) + self.shutter / self.fps,
+        )
+    }
+
+    pub fn frame_path(&self, frame: u32) -> PathBuf {
+        self.directory
+            .join(format!("{}{:04}.png", self.prefix, frame))
+    }
+
+    pub fn frame(&self, frame: u32) -> Frame {
+        let time0 = self.time(frame);
+        let time1 = time0 + self.shutter / self.fps;
+        // Pose the camera at the middle of the exposure.
+        let key = self.camera.key(frame as f32 + 0.5 * self.shutter);
+        Frame {
+            number: frame,
+            path: self.frame_path(frame),
+            camera: Camera::new(
+                key.lookfrom,
+                key.lookat,
+                self.camera.vup,
+                key.vfov,
+                self.aspect_ratio,
+                key.aperture,
+                key.focus_dist,
+                time0,
+                time1,
+            ),
+        }
+    }
+
+    // Frames still to render, leaving out ones already on disk when `skip_existing` is set.
+    pub fn frames(&self) -> impl Iterator<Item = Frame> + '_ {
+        (self.start_frame..=self.end_frame)
+            .filter(move |&n| !(self.skip_existing && self.frame_path(n).exists()))
+            .map(move |n| self.frame(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(frame: f32, x: f32, vfov: f32) -> CameraKey {
+        CameraKey {
+            frame,
+            lookfrom: Point3::new(x, 0.0, 10.0),
+            lookat: Point3::new(0.0, 0.0, 0.0),
+            vfov,
+            focus_dist: 10.0,
+            aperture: 0.0,
+        }
+    }
+
+    #[test]
+    fn keys_interpolate_in_frame_order() {
+        let path = CameraPath::new(
+            vec![key(10.0, 4.0, 60.0), key(0.0, 0.0, 40.0)],
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let mid = path.key(5.0);
+        assert!((mid.lookfrom.x() - 2.0).abs() < 1e-5);
+        assert!((mid.vfov - 50.0).abs() < 1e-5);
+        assert!((path.key(-3.0).vfov - 40.0).abs() < 1e-5);
+        assert!((path.key(99.0).vfov - 60.0).abs() < 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "camera key frames must be finite")]
+    fn nan_key_frames_are_rejected() {
+        CameraPath::new(
+            vec![key(0.0, 0.0, 40.0), key(f32::NAN, 1.0, 40.0)],
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn turntable_starts_in_front_of_the_target() {
+        let lookat = Point3::new(1.0, 2.0, 3.0);
+        let path = CameraPath::turntable(lookat, 5.0, 1.0, (0, 48), 40.0);
+        let start = path.key(0.0);
+        assert!((start.lookfrom - Point3::new(1.0, 3.0, 8.0)).length() < 1e-5);
+        assert!((path.key(48.0).lookfrom - start.lookfrom).length() < 1e-4);
+    }
+
+    #[test]
+    fn turntable_keeps_its_radius_between_keys() {
+        let lookat = Point3::new(1.0, 2.0, 3.0);
+        let path = CameraPath::turntable(lookat, 5.0, 1.0, (10, 58), 40.0);
+        for frame in 10..=58 {
+            let key = path.key(frame as f32 + 0.3);
+            let offset = key.lookfrom - lookat;
+            let across = Vec3::new(offset.x(), 0.0, offset.z()).length();
+            assert!((across - 5.0).abs() < 1e-3 && (offset.y() - 1.0).abs() < 1e-4);
+        }
+        // An eighth of the way round, turning from +z toward +x.
+        let eighth = path.key(16.0).lookfrom - lookat;
+        let diagonal = 5.0 * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((eighth - Vec3::new(diagonal, 1.0, diagonal)).length() < 1e-3);
+    }
+
+    #[test]
+    #[should_panic(expected = "end frame after its start")]
+    fn turntable_frames_must_run_forward() {
+        CameraPath::turntable(Point3::new(0.0, 0.0, 0.0), 5.0, 1.0, (48, 0), 40.0);
+    }
+
+    #[test]
+    fn frames_expose_their_slice_of_time() {
+        let path = CameraPath::new(vec![key(0.0, 0.0, 40.0)], Vec3::new(0.0, 1.0, 0.0));
+        let mut animation = Animation::new(path, 12, 14, 1.0);
+        animation.directory = PathBuf::from("out");
+        assert!((animation.time(12) - 0.5).abs() < 1e-6);
+        let (start, end) = animation.duration();
+        assert!((start - 0.5).abs() < 1e-6);
+        assert!((end - (14.0 + 0.5) / 24.0).abs() < 1e-6);
+
+        let frames: Vec<_> = animation.frames().collect();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].number, 12);
+        assert_eq!(frames[2].path, PathBuf::from("out/frame_0014.png"));
+    }
+}
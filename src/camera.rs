@@ -1,8 +1,61 @@
 use rand::Rng;
 
+use super::hittable::Hittable;
+use super::lens::Aperture;
+use super::matrix::Mat4;
 use super::ray::Ray;
+use super::shutter::Shutter;
 use super::vec3::{Point3, Vec3};
 
+// Maps normalized image coordinates (u to the right, v up, both in [0, 1]) to a primary
+// ray; `None` where the projection covers no directions, e.g. outside a fisheye circle.
+pub trait CameraModel: Send + Sync {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray>;
+}
+
+// Right, up and backward unit vectors of a camera looking from `lookfrom` to `lookat`.
+pub fn look_at_basis(lookfrom: Point3, lookat: Point3, vup: Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = (lookfrom - lookat).normalized();
+    let u = vup.cross(w).normalized();
+    let v = w.cross(u);
+    (u, v, w)
+}
+
+pub fn sample_time(time0: f32, time1: f32) -> f32 {
+    // A zero-length shutter freezes the scene at `time0`.
+    if time1 > time0 {
+        rand::thread_rng().gen_range(time0..time1)
+    } else {
+        time0
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+pub enum FovAxis {
+    Horizontal,
+    Vertical,
+    Diagonal,
+}
+
+// Vertical field of view in degrees, as `Camera::new` takes it, from a field of view
+// measured along another axis of an image with the given aspect ratio.
+pub fn vertical_fov(fov: f32, axis: FovAxis, aspect_ratio: f32) -> f32 {
+    let half_tan = (0.5 * fov.to_radians()).tan();
+    let half_tan = match axis {
+        FovAxis::Horizontal => half_tan / aspect_ratio,
+        FovAxis::Vertical => half_tan,
+        FovAxis::Diagonal => half_tan / (1.0 + aspect_ratio * aspect_ratio).sqrt(),
+    };
+    2.0 * half_tan.atan().to_degrees()
+}
+
+// Field of view in degrees spanned by a sensor dimension behind a lens focused at
+// infinity, e.g. `fov_from_focal_length(50.0, 36.0)` for a full-frame width.
+pub fn fov_from_focal_length(focal_length: f32, sensor_size: f32) -> f32 {
+    2.0 * (sensor_size / (2.0 * focal_length)).atan().to_degrees()
+}
+
 pub struct Camera {
     origin: Point3,
     lower_left_corner: Point3,
@@ -10,13 +63,19 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
-    // w: Vec3,
+    w: Vec3,
+    focus_dist: f32,
+    focal_plane_normal: Option<Vec3>,
     lens_radius: f32,
+    aperture: Aperture,
+    cat_eye: f32,
+    shutter: Shutter,
     time0: f32,
     time1: f32,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Point3,
         lookat: Point3,
@@ -32,9 +91,7 @@ impl Camera {
         let viewport_height = 2.0 * (theta / 2.0).tan();
         let viewport_width = aspect_ratio * viewport_height;
 
-        let cw = (lookfrom - lookat).normalized();
-        let cu = vup.cross(cw).normalized();
-        let cv = cw.cross(cu);
+        let (cu, cv, cw) = look_at_basis(lookfrom, lookat, vup);
 
         let horizontal = focus_dist * viewport_width * cu;
         let vertical = focus_dist * viewport_height * cv;
@@ -42,26 +99,212 @@ impl Camera {
 
         Self {
             origin: lookfrom,
-            lower_left_corner: lower_left_corner,
-            horizontal: horizontal,
-            vertical: vertical,
+            lower_left_corner,
+            horizontal,
+            vertical,
             u: cu,
             v: cv,
-            // w: cw,
+            w: cw,
+            focus_dist,
+            focal_plane_normal: None,
             lens_radius: aperture / 2.0,
+            aperture: Aperture::Circular,
+            cat_eye: 0.0,
+            shutter: Shutter::default(),
             time0,
             time1,
         }
     }
 
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
-        let offset = self.u * rd.x() + self.v * rd.y();
-        let mut rng = rand::thread_rng();
-        Ray::new(
+    // Off-axis projection: slides the image window by fractions of its width and height
+    // without turning the camera, keeping verticals parallel in architectural shots.
+    pub fn with_shift(mut self, x: f32, y: f32) -> Self {
+        self.lower_left_corner += x * self.horizontal + y * self.vertical;
+        self
+    }
+
+    // Scheimpflug focus: turns the plane of focus by `tilt` degrees about the horizontal
+    // axis (positive brings its top toward the camera) and `swing` degrees about the
+    // vertical axis (positive brings its right side toward the camera).
+    pub fn with_tilt(mut self, tilt: f32, swing: f32) -> Self {
+        let forward = -1.0 * self.w;
+        let normal = Mat4::rotate(-swing, self.v)
+            .transform_vector(Mat4::rotate(tilt, self.u).transform_vector(forward));
+        self.focal_plane_normal = if tilt == 0.0 && swing == 0.0 {
+            None
+        } else {
+            Some(normal)
+        };
+        self
+    }
+
+    pub fn focus_dist(&self) -> f32 {
+        self.focus_dist
+    }
+
+    // Moves the plane of focus to `focus_dist` along the view axis, keeping the framing.
+    pub fn with_focus_dist(mut self, focus_dist: f32) -> Self {
+        let scale = focus_dist / self.focus_dist;
+        self.horizontal = scale * self.horizontal;
+        self.vertical = scale * self.vertical;
+        self.lower_left_corner = self.origin + scale * (self.lower_left_corner - self.origin);
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    // Depth along the view axis of the first surface seen through image point (u, v),
+    // which is the focus distance that makes it sharp.
+    pub fn measure_focus(&self, world: &dyn Hittable, u: f32, v: f32) -> Option<f32> {
+        let target = self.lower_left_corner + u * self.horizontal + v * self.vertical;
+        let ray = Ray::new(self.origin, target - self.origin, self.time0);
+        let rec = world.hit(&ray, 0.001, f32::INFINITY)?;
+        Some((self.origin - rec.p).dot(self.w))
+    }
+
+    // Focuses on whatever lies under image point (u, v), e.g. (0.5, 0.5) for the center;
+    // the focus distance is left alone if the ray escapes the scene.
+    pub fn with_autofocus(self, world: &dyn Hittable, u: f32, v: f32) -> Self {
+        match self.measure_focus(world, u, v) {
+            Some(focus_dist) => self.with_focus_dist(focus_dist),
+            None => self,
+        }
+    }
+
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    // Off-axis points see the lens opening clipped by the barrel, giving the cat's-eye
+    // bokeh of real lenses; 0 disables it and larger values clip harder toward the edges.
+    pub fn with_cat_eye(mut self, strength: f32) -> Self {
+        self.cat_eye = strength;
+        self
+    }
+
+    pub fn with_shutter(mut self, shutter: Shutter) -> Self {
+        self.shutter = shutter;
+        self
+    }
+}
+
+impl CameraModel for Camera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let (x, y) = self.aperture.sample();
+        if self.cat_eye > 0.0 {
+            // The barrel's exit circle shifts outward with the image point.
+            let (dx, dy) = (
+                x - 2.0 * self.cat_eye * (u - 0.5),
+                y - 2.0 * self.cat_eye * (v - 0.5),
+            );
+            if dx * dx + dy * dy > 1.0 {
+                return None;
+            }
+        }
+        let offset = self.lens_radius * (self.u * x + self.v * y);
+        let mut target = self.lower_left_corner + u * self.horizontal + v * self.vertical;
+        if let Some(normal) = self.focal_plane_normal {
+            // Refocus on where the chief ray meets the tilted plane through the focus point.
+            let direction = target - self.origin;
+            let denominator = direction.dot(normal);
+            if denominator.abs() > 1.0e-6 {
+                let t = (-self.focus_dist * self.w).dot(normal) / denominator;
+                target = self.origin + t * direction;
+            }
+        }
+        Some(Ray::new(
             self.origin + offset,
-            self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset,
-            rng.gen_range(self.time0..self.time1),
+            target - self.origin - offset,
+            self.shutter.sample(self.time0, self.time1, v),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use crate::testing::{first_hit, gray};
+
+    // A camera at the origin looking down -z with a wide aperture focused 2 units out.
+    fn camera() -> Camera {
+        Camera::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+            0.5,
+            2.0,
+            0.0,
+            0.0,
         )
     }
+
+    // Whether every lens sample for image point (u, v) passes through `p`, i.e. `p` is
+    // in focus there.
+    fn focuses_at(camera: &Camera, u: f32, v: f32, p: Point3) -> bool {
+        (0..50).all(|_| {
+            let ray = camera.ray(u, v).unwrap();
+            let d = ray.direction().normalized();
+            let to_p = p - ray.origin();
+            (to_p - to_p.dot(d) * d).length() < 1e-4
+        })
+    }
+
+    #[test]
+    fn fields_of_view_convert_between_axes() {
+        assert!((fov_from_focal_length(18.0, 36.0) - 90.0).abs() < 1e-4);
+        assert!(
+            (vertical_fov(90.0, FovAxis::Horizontal, 2.0) - 2.0 * 0.5f32.atan().to_degrees()).abs()
+                < 1e-4
+        );
+        assert!((vertical_fov(40.0, FovAxis::Vertical, 2.0) - 40.0).abs() < 1e-4);
+        // A 3:4 diagonal of 5 units has a vertical side of 3.
+        let diagonal = 2.0 * 2.5f32.atan().to_degrees();
+        assert!(
+            (vertical_fov(diagonal, FovAxis::Diagonal, 4.0 / 3.0)
+                - 2.0 * 1.5f32.atan().to_degrees())
+            .abs()
+                < 1e-3
+        );
+    }
+
+    #[test]
+    fn shift_slides_the_image_without_turning() {
+        let shifted = camera().with_shift(0.25, 0.0);
+        // The window is 4 units wide at the focus distance.
+        assert!(focuses_at(&shifted, 0.5, 0.5, Point3::new(1.0, 0.0, -2.0)));
+        assert!(focuses_at(&shifted, 0.25, 0.5, Point3::new(0.0, 0.0, -2.0)));
+    }
+
+    #[test]
+    fn tilt_turns_the_plane_of_focus() {
+        let plain = camera();
+        assert!(focuses_at(&plain, 0.5, 1.0, Point3::new(0.0, 2.0, -2.0)));
+
+        let tilted = camera().with_tilt(30.0, 0.0);
+        assert!(focuses_at(&tilted, 0.5, 0.5, Point3::new(0.0, 0.0, -2.0)));
+        // The top of the frame meets the plane 0.5 y - cos(30) (z + 2) = 0 closer in.
+        let t = 2.0 * 30f32.to_radians().cos() / (0.5 + 30f32.to_radians().cos());
+        assert!(t < 2.0);
+        assert!(focuses_at(&tilted, 0.5, 1.0, Point3::new(0.0, t, -t)));
+    }
+
+    #[test]
+    fn autofocus_measures_depth_not_range() {
+        let ball = Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, gray());
+        let camera = camera();
+        assert!((camera.measure_focus(&ball, 0.5, 0.5).unwrap() - 4.0).abs() < 1e-4);
+        // Off center the hit is further away than its depth along the view axis.
+        let hit = first_hit(&ball, Point3::new(0.0, 0.0, 0.0), Vec3::new(0.1, 0.0, -2.0)).unwrap();
+        let depth = camera.measure_focus(&ball, 0.525, 0.5).unwrap();
+        assert!((depth + hit.z()).abs() < 1e-4 && depth < hit.length());
+
+        let focused = camera.with_autofocus(&ball, 0.5, 0.5);
+        assert!(focuses_at(&focused, 0.5, 0.5, Point3::new(0.0, 0.0, -4.0)));
+        // Rays into empty space keep the old focus.
+        let unchanged = focused.with_autofocus(&ball, 0.0, 0.0);
+        assert!((unchanged.focus_dist() - 4.0).abs() < 1e-4);
+    }
 }
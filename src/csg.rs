@@ -0,0 +1,270 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::ray::Ray;
+
+use std::sync::Arc;
+
+#[derive(Clone, Copy)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    // Everything in the first shape that isn't in the second.
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Union => in_a || in_b,
+            CsgOp::Intersection => in_a && in_b,
+            CsgOp::Difference => in_a && !in_b,
+        }
+    }
+}
+
+// A boolean combination of two closed shapes. Both must have outward-facing normals so
+// that `front_face` marks where a ray enters them; CSG nodes can be nested.
+pub struct Csg {
+    op: CsgOp,
+    a: Arc<dyn Hittable>,
+    b: Arc<dyn Hittable>,
+    bbox: Option<AABB>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        let bbox = combined_box(op, a.bounding_box(0.0, 1.0), b.bounding_box(0.0, 1.0));
+        Self { op, a, b, bbox }
+    }
+
+    pub fn union(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOp::Union, a, b)
+    }
+
+    pub fn intersection(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOp::Intersection, a, b)
+    }
+
+    pub fn difference(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOp::Difference, a, b)
+    }
+
+    // Sweeps the crossings of both children in order, keeping those where the combined
+    // shape switches between inside and outside, until `done` accepts a kept crossing.
+    fn crossings(&self, r: &Ray, done: impl Fn(&HitRecord) -> bool) -> Vec<HitRecord> {
+        let mut events: Vec<(bool, HitRecord)> = self
+            .a
+            .all_hits(r)
+            .into_iter()
+            .map(|rec| (true, rec))
+            .chain(self.b.all_hits(r).into_iter().map(|rec| (false, rec)))
+            .collect();
+        events.sort_by(|x, y| x.1.t.total_cmp(&y.1.t));
+
+        // Depths rather than flags so children made of overlapping parts still work.
+        let (mut depth_a, mut depth_b) = (0i32, 0i32);
+        let mut inside = false;
+        let mut hits = Vec::new();
+        for (from_a, mut rec) in events {
+            let step = if rec.front_face { 1 } else { -1 };
+            if from_a {
+                depth_a += step;
+            } else {
+                depth_b += step;
+            }
+            let now_inside = self.op.inside(depth_a > 0, depth_b > 0);
+            if now_inside != inside {
+                // `normal` already faces the ray; only the side being entered can change,
+                // e.g. where leaving the subtracted shape enters the difference.
+                rec.front_face = now_inside;
+                inside = now_inside;
+                let stop = done(&rec);
+                hits.push(rec);
+                if stop {
+                    break;
+                }
+            }
+        }
+        hits
+    }
+}
+
+fn combined_box(op: CsgOp, a: Option<AABB>, b: Option<AABB>) -> Option<AABB> {
+    match (op, a, b) {
+        (CsgOp::Union, Some(a), Some(b)) => Some(AABB::surrounding_box(&a, &b)),
+        (CsgOp::Union, _, _) => None,
+        (CsgOp::Intersection, Some(a), Some(b)) => {
+            let min = a.min().max(b.min());
+            // Disjoint boxes leave an empty intersection; keep it a valid point.
+            Some(AABB::new(min, a.max().min(b.max()).max(min)))
+        }
+        (CsgOp::Intersection, Some(only), None) | (CsgOp::Intersection, None, Some(only)) => {
+            Some(only)
+        }
+        (CsgOp::Intersection, None, None) => None,
+        (CsgOp::Difference, a, _) => a,
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        if self.bbox.is_some_and(|b| !b.hit(r, t_min, t_max)) {
+            return None;
+        }
+        let in_range = |rec: &HitRecord| rec.t >= t_min && rec.t <= t_max;
+        self.crossings(r, in_range).pop().filter(in_range)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
+        combined_box(
+            self.op,
+            self.a.bounding_box(time0, time1),
+            self.b.bounding_box(time0, time1),
+        )
+    }
+
+    fn all_hits(&self, r: &Ray) -> Vec<HitRecord> {
+        self.crossings(r, |_| false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::rotate::{Axis, Rotate};
+    use crate::sphere::Sphere;
+    use crate::testing::gray;
+    use crate::vec3::{Point3, Vec3};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn ball(x: f32) -> Arc<dyn Hittable> {
+        Arc::new(Sphere::new(Point3::new(x, 0.0, 0.0), 1.0, gray()))
+    }
+
+    fn along_x(shape: &dyn Hittable, x: f32) -> Option<HitRecord> {
+        let ray = Ray::new(Point3::new(x, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        shape.hit(&ray, 0.001, f32::INFINITY)
+    }
+
+    #[test]
+    fn difference_starts_where_the_cutter_ends() {
+        // Unit balls at x = 0 and x = -1; the difference spans x in [0, 1].
+        let bitten = Csg::difference(ball(0.0), ball(-1.0));
+        let rec = along_x(&bitten, -5.0).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-4);
+        assert!(rec.front_face);
+        // The surface there belongs to the cutter but still faces the incoming ray.
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-4);
+        let exit = along_x(&bitten, 0.5).unwrap();
+        assert!((exit.t - 0.5).abs() < 1e-4 && !exit.front_face);
+    }
+
+    #[test]
+    fn union_and_intersection_pick_the_right_crossings() {
+        let union = Csg::union(ball(0.0), ball(-1.0));
+        let hits: Vec<f32> = union
+            .all_hits(&Ray::new(
+                Point3::new(-5.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                0.0,
+            ))
+            .iter()
+            .map(|rec| rec.t)
+            .collect();
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0] - 3.0).abs() < 1e-4 && (hits[1] - 6.0).abs() < 1e-4);
+
+        let lens = Csg::intersection(ball(0.0), ball(-1.0));
+        assert!((along_x(&lens, -5.0).unwrap().t - 4.0).abs() < 1e-4);
+        let bbox = lens.bounding_box(0.0, 0.0).unwrap();
+        assert!((bbox.min().x() + 1.0).abs() < 1e-4 && bbox.max().x().abs() < 1e-4);
+    }
+
+    #[test]
+    fn nested_nodes_combine() {
+        // A ball hollowed out by the lens where two smaller balls overlap.
+        let hole = Arc::new(Csg::intersection(ball(-0.6), ball(0.6)));
+        let ring = Csg::difference(ball(0.0), hole);
+        let hits: Vec<f32> = ring
+            .all_hits(&Ray::new(
+                Point3::new(-5.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                0.0,
+            ))
+            .iter()
+            .map(|rec| rec.t)
+            .collect();
+        assert_eq!(hits.len(), 4);
+        for (t, expected) in hits.iter().zip([4.0, 4.6, 5.4, 6.0]) {
+            assert!((t - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn rotated_children_keep_their_sides() {
+        // A cube turned 45 degrees about y with a ball cut out of its middle.
+        let cube = Arc::new(Cube::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            gray(),
+        ));
+        let turned = Arc::new(Rotate::new(Axis::Y, cube, 45.0));
+        let hollow = Csg::difference(
+            turned,
+            Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5, gray())),
+        );
+        let hits = hollow.all_hits(&Ray::new(
+            Point3::new(-5.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            0.0,
+        ));
+        assert_eq!(hits.len(), 4);
+        let corner = 5.0 - 2.0f32.sqrt();
+        for (rec, (t, entering)) in hits.iter().zip([
+            (corner, true),
+            (4.5, false),
+            (5.5, true),
+            (10.0 - corner, false),
+        ]) {
+            assert!((rec.t - t).abs() < 1e-3 && rec.front_face == entering);
+            // Normals face the incoming ray whichever side it is on.
+            assert!(rec.normal.x() < 0.0);
+        }
+    }
+
+    // Counts how often the CSG asks it for crossings.
+    struct Counted(Arc<dyn Hittable>, AtomicUsize);
+
+    impl Hittable for Counted {
+        fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+            self.0.hit(r, t_min, t_max)
+        }
+
+        fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
+            self.0.bounding_box(time0, time1)
+        }
+
+        fn all_hits(&self, r: &Ray) -> Vec<HitRecord> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.all_hits(r)
+        }
+    }
+
+    #[test]
+    fn hits_respect_the_range_and_skip_missed_boxes() {
+        let counted = Arc::new(Counted(ball(0.0), AtomicUsize::new(0)));
+        let union = Csg::union(counted.clone(), ball(3.0));
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let t = |t_min, t_max| union.hit(&ray, t_min, t_max).map(|rec| rec.t);
+        assert!((t(0.0, 100.0).unwrap() - 4.0).abs() < 1e-4);
+        assert!((t(4.5, 100.0).unwrap() - 6.0).abs() < 1e-4);
+        assert!((t(6.5, 100.0).unwrap() - 7.0).abs() < 1e-4);
+        assert!(t(6.5, 6.9).is_none());
+        let asked = counted.1.load(Ordering::Relaxed);
+
+        let above = Ray::new(Point3::new(-5.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(union.hit(&above, 0.0, 100.0).is_none());
+        assert_eq!(counted.1.load(Ordering::Relaxed), asked);
+    }
+}
@@ -3,7 +3,7 @@ use crate::material::Scatter;
 
 use super::aabb::AABB;
 use super::ray::Ray;
-use super::rect::{Plane, Rect};
+use super::rect::{FlipFace, Plane, Rect};
 use super::vec3::Vec3;
 use super::world::HitableList;
 use std::sync::Arc;
@@ -15,7 +15,10 @@ pub struct Cube {
 }
 
 impl Cube {
+    #[allow(clippy::vec_init_then_push)]
     pub fn new(p0: Vec3, p1: Vec3, mat: Arc<dyn Scatter>) -> Self {
+        // Faces on the low side of each axis are flipped so every normal points out, and a
+        // hit from outside is a front face on all six sides. Dielectric boxes depend on it.
         let mut sides = HitableList::new();
 
         sides.push(Box::new(Rect::new(
@@ -27,7 +30,7 @@ impl Cube {
             p1.z(),
             mat.clone(),
         )));
-        sides.push(Box::new(Rect::new(
+        sides.push(Box::new(FlipFace::new(Rect::new(
             Plane::XY,
             p0.x(),
             p1.x(),
@@ -35,7 +38,7 @@ impl Cube {
             p1.y(),
             p0.z(),
             mat.clone(),
-        )));
+        ))));
 
         sides.push(Box::new(Rect::new(
             Plane::ZX,
@@ -46,7 +49,7 @@ impl Cube {
             p1.y(),
             mat.clone(),
         )));
-        sides.push(Box::new(Rect::new(
+        sides.push(Box::new(FlipFace::new(Rect::new(
             Plane::ZX,
             p0.z(),
             p1.z(),
@@ -54,7 +57,7 @@ impl Cube {
             p1.x(),
             p0.y(),
             mat.clone(),
-        )));
+        ))));
 
         sides.push(Box::new(Rect::new(
             Plane::YZ,
@@ -65,7 +68,7 @@ impl Cube {
             p1.x(),
             mat.clone(),
         )));
-        sides.push(Box::new(Rect::new(
+        sides.push(Box::new(FlipFace::new(Rect::new(
             Plane::YZ,
             p0.y(),
             p1.y(),
@@ -73,7 +76,7 @@ impl Cube {
             p1.z(),
             p0.x(),
             mat,
-        )));
+        ))));
 
         Self {
             box_min: p0,
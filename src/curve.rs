@@ -0,0 +1,388 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::material::Scatter;
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+use super::world::HitableList;
+
+use std::sync::Arc;
+
+// Deepest the intersection test splits a segment before treating it as straight.
+const MAX_DEPTH: u32 = 10;
+
+#[derive(Clone, Copy)]
+pub enum CurveShape {
+    // A thin round fiber: a strip that always faces the ray, shaded with the normals of a
+    // tube. Suits hair and fur.
+    Tube,
+    // A flat strip whose face turns from the first normal to the second along the curve.
+    // Suits grass blades and leaves.
+    Ribbon(Vec3, Vec3),
+}
+
+fn lerp(t: f32, a: Point3, b: Point3) -> Point3 {
+    (1.0 - t) * a + t * b
+}
+
+// Point and derivative of a cubic Bezier curve.
+fn eval_bezier(cp: &[Point3; 4], u: f32) -> (Point3, Vec3) {
+    let a = [
+        lerp(u, cp[0], cp[1]),
+        lerp(u, cp[1], cp[2]),
+        lerp(u, cp[2], cp[3]),
+    ];
+    let b = [lerp(u, a[0], a[1]), lerp(u, a[1], a[2])];
+    let derivative = if (b[1] - b[0]).near_zero() {
+        // Repeated end points give no tangent there; fall back to the chord.
+        cp[3] - cp[0]
+    } else {
+        3.0 * (b[1] - b[0])
+    };
+    (lerp(u, b[0], b[1]), derivative)
+}
+
+// Splits a curve at its middle into two that share the middle point.
+fn subdivide_bezier(cp: &[Point3; 4]) -> [[Point3; 4]; 2] {
+    let mid = |a: Point3, b: Point3| 0.5 * (a + b);
+    let (p01, p12, p23) = (mid(cp[0], cp[1]), mid(cp[1], cp[2]), mid(cp[2], cp[3]));
+    let (p012, p123) = (mid(p01, p12), mid(p12, p23));
+    let center = mid(p012, p123);
+    [[cp[0], p01, p012, center], [center, p123, p23, cp[3]]]
+}
+
+// Control points of the part of the curve between u0 and u1.
+fn blossom_segment(cp: &[Point3; 4], u0: f32, u1: f32) -> [Point3; 4] {
+    let blossom = |a: f32, b: f32, c: f32| {
+        let level1 = [
+            lerp(a, cp[0], cp[1]),
+            lerp(a, cp[1], cp[2]),
+            lerp(a, cp[2], cp[3]),
+        ];
+        let level2 = [lerp(b, level1[0], level1[1]), lerp(b, level1[1], level1[2])];
+        lerp(c, level2[0], level2[1])
+    };
+    [
+        blossom(u0, u0, u0),
+        blossom(u0, u0, u1),
+        blossom(u0, u1, u1),
+        blossom(u1, u1, u1),
+    ]
+}
+
+struct CurveCommon {
+    points: [Point3; 4],
+    width: (f32, f32),
+    shape: CurveShape,
+    mat: Arc<dyn Scatter>,
+}
+
+impl CurveCommon {
+    fn width(&self, u: f32) -> f32 {
+        (1.0 - u) * self.width.0 + u * self.width.1
+    }
+}
+
+// A cubic Bezier curve with a width that changes linearly from one end to the other,
+// intersected by recursive splitting as in pbrt. Hits report u along the curve, v across
+// its width and the curve's direction as `tangent`.
+pub struct Curve {
+    common: Arc<CurveCommon>,
+    // This piece's range of the whole curve and its control points.
+    u0: f32,
+    u1: f32,
+    points: [Point3; 4],
+}
+
+impl Curve {
+    pub fn new(
+        points: [Point3; 4],
+        width0: f32,
+        width1: f32,
+        shape: CurveShape,
+        mat: Arc<dyn Scatter>,
+    ) -> Self {
+        let common = Arc::new(CurveCommon {
+            points,
+            width: (width0, width1),
+            shape,
+            mat,
+        });
+        Self {
+            common,
+            u0: 0.0,
+            u1: 1.0,
+            points,
+        }
+    }
+
+    // The curve cut into `count` pieces that share their data, so a `BVH` can give each
+    // a tight box instead of one loose box around a long bent curve.
+    pub fn segments(
+        points: [Point3; 4],
+        width0: f32,
+        width1: f32,
+        shape: CurveShape,
+        count: usize,
+        mat: Arc<dyn Scatter>,
+    ) -> HitableList {
+        let count = count.max(1);
+        let whole = Self::new(points, width0, width1, shape, mat);
+        let mut list = HitableList::new();
+        for i in 0..count {
+            let (u0, u1) = (i as f32 / count as f32, (i + 1) as f32 / count as f32);
+            list.push(Box::new(Self {
+                common: whole.common.clone(),
+                u0,
+                u1,
+                points: blossom_segment(&points, u0, u1),
+            }));
+        }
+        list
+    }
+
+    // Nearest crossing of piece `cp` (in ray space, where the ray runs down +z from the
+    // origin) spanning [u0, u1] of the whole curve, as (distance along the ray, u).
+    #[allow(clippy::too_many_arguments)]
+    fn recursive_hit(
+        &self,
+        cp: &[Point3; 4],
+        u0: f32,
+        u1: f32,
+        depth: u32,
+        z_min: f32,
+        z_max: f32,
+        ray_dir: Vec3,
+    ) -> Option<(f32, f32)> {
+        let half_width = 0.5 * self.common.width(u0).max(self.common.width(u1));
+        let (min, max) = cp[1..]
+            .iter()
+            .fold((cp[0], cp[0]), |(min, max), &p| (min.min(p), max.max(p)));
+        if max.x() + half_width < 0.0
+            || min.x() - half_width > 0.0
+            || max.y() + half_width < 0.0
+            || min.y() - half_width > 0.0
+            || max.z() + half_width < z_min
+            || min.z() - half_width > z_max
+        {
+            return None;
+        }
+
+        if depth > 0 {
+            let halves = subdivide_bezier(cp);
+            let u_mid = 0.5 * (u0 + u1);
+            let near = self.recursive_hit(&halves[0], u0, u_mid, depth - 1, z_min, z_max, ray_dir);
+            let z_max = near.map_or(z_max, |(z, _)| z);
+            let far = self.recursive_hit(&halves[1], u_mid, u1, depth - 1, z_min, z_max, ray_dir);
+            return far.or(near);
+        }
+
+        // The ray must pass between the perpendiculars at both ends of the piece.
+        let edge = (cp[1].y() - cp[0].y()) * -cp[0].y() + cp[0].x() * (cp[0].x() - cp[1].x());
+        if edge < 0.0 {
+            return None;
+        }
+        let edge = (cp[2].y() - cp[3].y()) * -cp[3].y() + cp[3].x() * (cp[3].x() - cp[2].x());
+        if edge < 0.0 {
+            return None;
+        }
+
+        // Treat the piece as a line and find where it passes closest to the ray.
+        let (dx, dy) = (cp[3].x() - cp[0].x(), cp[3].y() - cp[0].y());
+        let denominator = dx * dx + dy * dy;
+        if denominator == 0.0 {
+            return None;
+        }
+        let w = ((-cp[0].x() * dx - cp[0].y() * dy) / denominator).clamp(0.0, 1.0);
+        let u = (u0 + w * (u1 - u0)).clamp(u0, u1);
+        let width = self.common.width(u);
+        let mut hit_width = width;
+        if let CurveShape::Ribbon(n0, n1) = self.common.shape {
+            // Seen edge on, a ribbon narrows to nothing.
+            let n = lerp(u, n0, n1).normalized();
+            hit_width *= n.dot(ray_dir).abs();
+        }
+        let (pc, _) = eval_bezier(cp, w);
+        let distance2 = pc.x() * pc.x() + pc.y() * pc.y();
+        if distance2 > 0.25 * hit_width * hit_width || pc.z() < z_min || pc.z() > z_max {
+            return None;
+        }
+        // A ray leaving the fiber starts inside it and shouldn't hit it again.
+        if distance2 + pc.z() * pc.z() < 0.25 * width * width {
+            return None;
+        }
+        Some((pc.z(), u))
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let length = r.direction().length();
+        let dir = r.direction() / length;
+        // Any frame with z along the ray will do.
+        let helper = if dir.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let x_axis = helper.cross(dir).normalized();
+        let y_axis = dir.cross(x_axis);
+        let to_ray = |p: Point3| {
+            let p = p - r.origin();
+            Point3::new(p.dot(x_axis), p.dot(y_axis), p.dot(dir))
+        };
+        let cp = self.points.map(to_ray);
+
+        // Split finely enough that the pieces are close to straight at the curve's width.
+        let mut l0 = 0.0f32;
+        for i in 0..2 {
+            let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            l0 = l0.max(d.x().abs()).max(d.y().abs()).max(d.z().abs());
+        }
+        let eps = 0.05 * self.common.width.0.max(self.common.width.1);
+        let depth = if l0 > 0.0 && eps > 0.0 {
+            ((std::f32::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps)).log2() * 0.5)
+                .clamp(0.0, MAX_DEPTH as f32) as u32
+        } else {
+            0
+        };
+
+        let (z, u) = self.recursive_hit(
+            &cp,
+            self.u0,
+            self.u1,
+            depth,
+            t_min * length,
+            t_max * length,
+            dir,
+        )?;
+
+        let t = z / length;
+        let p = r.at(t);
+        let (center, dpdu) = eval_bezier(&self.common.points, u);
+        let tangent = dpdu.normalized();
+        // Across the curve and facing the ray; v runs from 0 to 1 along `side`.
+        let side = dir.cross(tangent).normalized();
+        let facing = side.cross(tangent);
+        let width = self.common.width(u);
+        let (h, outward_normal) = match self.common.shape {
+            CurveShape::Tube => {
+                let h = ((p - center).dot(side) / (0.5 * width)).clamp(-1.0, 1.0);
+                (h, ((1.0 - h * h).sqrt() * facing + h * side).normalized())
+            }
+            CurveShape::Ribbon(n0, n1) => {
+                let n = lerp(u, n0, n1).normalized();
+                let hit_width = width * n.dot(dir).abs();
+                let h = ((p - center).dot(side) / (0.5 * hit_width)).clamp(-1.0, 1.0);
+                (h, n)
+            }
+        };
+
+        let mut rec = HitRecord {
+            t,
+            p,
+            normal: outward_normal,
+            u,
+            v: 0.5 * (h + 1.0),
+            mat: self.common.mat.clone(),
+            front_face: false,
+            color: None,
+            tangent: Some(tangent),
+        };
+        rec.set_face_normal(r, outward_normal);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        let (min, max) = self.points[1..]
+            .iter()
+            .fold((self.points[0], self.points[0]), |(min, max), &p| {
+                (min.min(p), max.max(p))
+            });
+        let half_width = 0.5 * self.common.width(self.u0).max(self.common.width(self.u1));
+        let padding = Vec3::new(half_width, half_width, half_width);
+        Some(AABB::new(min - padding, max + padding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::gray;
+
+    // Straight along x from -1 to 1 with control points evenly spaced.
+    fn straight() -> [Point3; 4] {
+        [
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(-1.0 / 3.0, 0.0, 0.0),
+            Point3::new(1.0 / 3.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        ]
+    }
+
+    fn down_from(curve: &dyn Hittable, x: f32, y: f32) -> Option<HitRecord> {
+        let ray = Ray::new(Point3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -2.0), 0.0);
+        curve.hit(&ray, 0.001, f32::INFINITY)
+    }
+
+    #[test]
+    fn straight_tube_is_hit_across_its_width() {
+        let curve = Curve::new(straight(), 0.2, 0.2, CurveShape::Tube, gray());
+        let rec = down_from(&curve, 0.5, 0.0).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-4);
+        assert!((rec.u - 0.75).abs() < 1e-3 && (rec.v - 0.5).abs() < 1e-3);
+        assert!((rec.tangent.unwrap() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-4);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-4);
+
+        // Halfway to the edge the tube normal leans out to the side.
+        let rec = down_from(&curve, 0.0, 0.05).unwrap();
+        assert!((rec.v - 0.75).abs() < 1e-3 || (rec.v - 0.25).abs() < 1e-3);
+        assert!((rec.normal.y().abs() - 0.5).abs() < 1e-3);
+        assert!(down_from(&curve, 0.0, 0.15).is_none());
+        assert!(down_from(&curve, 1.2, 0.0).is_none());
+    }
+
+    #[test]
+    fn width_tapers_along_the_curve() {
+        let curve = Curve::new(straight(), 0.4, 0.0, CurveShape::Tube, gray());
+        assert!(down_from(&curve, -0.9, 0.15).is_some());
+        assert!(down_from(&curve, 0.9, 0.15).is_none());
+    }
+
+    #[test]
+    fn ribbons_vanish_edge_on() {
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let ribbon = Curve::new(straight(), 0.2, 0.2, CurveShape::Ribbon(up, up), gray());
+        let rec = down_from(&ribbon, 0.0, 0.05).unwrap();
+        assert!((rec.normal - up).length() < 1e-4);
+        // Just off the center line, where a tube of the same width is still hit.
+        let sideways = Ray::new(Point3::new(0.0, 5.0, 0.02), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        assert!(ribbon.hit(&sideways, 0.001, f32::INFINITY).is_none());
+        let tube = Curve::new(straight(), 0.2, 0.2, CurveShape::Tube, gray());
+        assert!(tube.hit(&sideways, 0.001, f32::INFINITY).is_some());
+    }
+
+    #[test]
+    fn segments_cover_the_whole_curve() {
+        let bent = [
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(-0.5, 1.0, 0.0),
+            Point3::new(0.5, -1.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        ];
+        let whole = Curve::new(bent, 0.1, 0.1, CurveShape::Tube, gray());
+        let pieces = Curve::segments(bent, 0.1, 0.1, CurveShape::Tube, 4, gray());
+        for i in 0..=20 {
+            let (p, _) = eval_bezier(&bent, i as f32 / 20.0);
+            let a = down_from(&whole, p.x(), p.y()).unwrap();
+            let b = down_from(&pieces, p.x(), p.y()).unwrap();
+            assert!((a.t - b.t).abs() < 1e-3 && (a.u - b.u).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn zero_segments_still_make_the_whole_curve() {
+        let pieces = Curve::segments(straight(), 0.2, 0.2, CurveShape::Tube, 0, gray());
+        let rec = down_from(&pieces, 0.5, 0.0).unwrap();
+        assert!((rec.u - 0.75).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,304 @@
+use super::aabb::AABB;
+use super::mesh::TriangleMesh;
+use super::texture::Texture;
+use super::vec3::{Point3, Vec3};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Tessellation stops short of the edge length rather than grow a mesh past this size.
+const MAX_TRIANGLES: usize = 4_000_000;
+
+// Area-weighted normals of the faces around each vertex.
+fn vertex_normals(positions: &[Point3], indices: &[[usize; 3]]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::new(0.0, 0.0, 0.0); positions.len()];
+    for &[a, b, c] in indices.iter() {
+        let n = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        normals[a] += n;
+        normals[b] += n;
+        normals[c] += n;
+    }
+    normals
+        .into_iter()
+        .map(|n| {
+            if n.near_zero() {
+                Vec3::new(0.0, 1.0, 0.0)
+            } else {
+                n.normalized()
+            }
+        })
+        .collect()
+}
+
+// Adds the midpoint of edge (a, b) with every vertex attribute blended, returning its index.
+fn push_midpoint(mesh: &mut TriangleMesh, a: usize, b: usize) -> usize {
+    mesh.positions
+        .push(0.5 * (mesh.positions[a] + mesh.positions[b]));
+    if let Some(normals) = mesh.normals.as_mut() {
+        let n = normals[a] + normals[b];
+        normals.push(if n.near_zero() {
+            normals[a]
+        } else {
+            n.normalized()
+        });
+    }
+    if let Some(uvs) = mesh.uvs.as_mut() {
+        uvs.push((0.5 * (uvs[a].0 + uvs[b].0), 0.5 * (uvs[a].1 + uvs[b].1)));
+    }
+    if let Some(colors) = mesh.colors.as_mut() {
+        colors.push(0.5 * (colors[a] + colors[b]));
+    }
+    for (_, positions) in mesh.motion.iter_mut() {
+        positions.push(0.5 * (positions[a] + positions[b]));
+    }
+    mesh.positions.len() - 1
+}
+
+// Splits every edge longer than `edge_length` at its midpoint, repeating until none are
+// left. Whether an edge splits depends only on the edge's length, so neighbors agree
+// and no T-junctions open between them, even across seams where they don't share
+// vertices.
+fn tessellate(mesh: &mut TriangleMesh, edge_length: f32) {
+    loop {
+        let long =
+            |a: usize, b: usize| (mesh.positions[a] - mesh.positions[b]).length() > edge_length;
+        // Each split edge adds one triangle on either side of it.
+        let mut added = 0;
+        for tri in mesh.indices.iter() {
+            added += (0..3).filter(|&k| long(tri[k], tri[(k + 1) % 3])).count();
+        }
+        if added == 0 || mesh.indices.len() + added > MAX_TRIANGLES {
+            return;
+        }
+
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        for index in 0..mesh.indices.len() {
+            let tri = mesh.indices[index];
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                let key = (a.min(b), a.max(b));
+                if !midpoints.contains_key(&key)
+                    && (mesh.positions[a] - mesh.positions[b]).length() > edge_length
+                {
+                    let m = push_midpoint(mesh, a, b);
+                    midpoints.insert(key, m);
+                }
+            }
+        }
+
+        let mut indices = Vec::with_capacity(4 * mesh.indices.len());
+        for &tri in mesh.indices.iter() {
+            let split = |k: usize| {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                midpoints.get(&(a.min(b), a.max(b))).copied()
+            };
+            match (split(0), split(1), split(2)) {
+                (None, None, None) => indices.push(tri),
+                (Some(ab), Some(bc), Some(ca)) => {
+                    indices.push([tri[0], ab, ca]);
+                    indices.push([ab, tri[1], bc]);
+                    indices.push([ca, bc, tri[2]]);
+                    indices.push([ab, bc, ca]);
+                }
+                _ => {
+                    // Walk the outline with the new midpoints and fan it from one of them.
+                    let mut outline = Vec::with_capacity(5);
+                    let mut start = 0;
+                    for (k, &v) in tri.iter().enumerate() {
+                        outline.push(v);
+                        if let Some(m) = split(k) {
+                            start = outline.len();
+                            outline.push(m);
+                        }
+                    }
+                    outline.rotate_left(start);
+                    for i in 1..outline.len() - 1 {
+                        indices.push([outline[0], outline[i], outline[i + 1]]);
+                    }
+                }
+            }
+        }
+        mesh.indices = indices;
+    }
+}
+
+// Pushes a mesh out along its normals by a scalar texture, after tessellating it finely
+// enough for the detail to show. The texture's brightness, clamped to [0, 1], gives the
+// height; `scale` is how far a height of 1 reaches beyond the midlevel.
+pub struct Displacement {
+    texture: Arc<dyn Texture>,
+    scale: f32,
+    midlevel: f32,
+    edge_length: f32,
+}
+
+impl Displacement {
+    // No edge of the tessellated mesh is longer than `edge_length`, measured before
+    // displacement, unless that would take more than a few million triangles.
+    pub fn new(texture: Arc<dyn Texture>, scale: f32, edge_length: f32) -> Self {
+        assert!(
+            edge_length > 0.0,
+            "displacement edge length must be positive"
+        );
+        Self {
+            texture,
+            scale,
+            midlevel: 0.0,
+            edge_length,
+        }
+    }
+
+    // Height that stays in place; 0.5 pushes brighter areas out and darker ones in.
+    pub fn with_midlevel(mut self, midlevel: f32) -> Self {
+        self.midlevel = midlevel;
+        self
+    }
+
+    fn offset(&self, height: f32) -> f32 {
+        self.scale * (height.clamp(0.0, 1.0) - self.midlevel)
+    }
+
+    // A box that will hold the displaced mesh, known without tessellating it: the base
+    // mesh's box grown by the largest offset the displacement can make.
+    pub fn bounds(&self, mesh: &TriangleMesh) -> Option<AABB> {
+        let first = *mesh.positions.first()?;
+        let (min, max) = mesh
+            .positions
+            .iter()
+            .chain(
+                mesh.motion
+                    .iter()
+                    .flat_map(|(_, positions)| positions.iter()),
+            )
+            .fold((first, first), |(min, max), &p| (min.min(p), max.max(p)));
+        let reach = self.offset(0.0).abs().max(self.offset(1.0).abs());
+        let padding = Vec3::new(reach, reach, reach);
+        Some(AABB::new(min - padding, max + padding))
+    }
+
+    // The tessellated and displaced mesh, with normals of the new surface. Meshes without
+    // uvs should use a texture that only looks at positions, such as `NoiseTexture`.
+    // Deforming meshes move every pose by the same offsets.
+    pub fn apply(&self, mesh: &TriangleMesh) -> TriangleMesh {
+        let mut mesh = mesh.clone();
+        if mesh.normals.is_none() {
+            mesh.normals = Some(vertex_normals(&mesh.positions, &mesh.indices));
+        }
+        tessellate(&mut mesh, self.edge_length);
+
+        // Vertices split along uv or normal seams share a position. Each position moves
+        // once, by the mean height of its copies along their mean normal, so the seams
+        // stay closed.
+        let normals = mesh.normals.take().unwrap();
+        let key = |p: Point3| {
+            // Adding zero folds -0.0 into 0.0.
+            [p.x() + 0.0, p.y() + 0.0, p.z() + 0.0].map(f32::to_bits)
+        };
+        let mut welded: HashMap<[u32; 3], (Vec3, f32, usize)> = HashMap::new();
+        for (i, n) in normals.iter().enumerate() {
+            let (u, v) = mesh.uvs.as_ref().map_or((0.0, 0.0), |uvs| uvs[i]);
+            let value = self.texture.value(u, v, mesh.positions[i]);
+            let height = (value.x() + value.y() + value.z()) / 3.0;
+            let entry =
+                welded
+                    .entry(key(mesh.positions[i]))
+                    .or_insert((Vec3::new(0.0, 0.0, 0.0), 0.0, 0));
+            *entry = (entry.0 + *n, entry.1 + height, entry.2 + 1);
+        }
+        for (i, n) in normals.iter().enumerate() {
+            let (normal_sum, height_sum, count) = welded[&key(mesh.positions[i])];
+            let direction = if normal_sum.near_zero() {
+                *n
+            } else {
+                normal_sum.normalized()
+            };
+            let offset = self.offset(height_sum / count as f32) * direction;
+            mesh.positions[i] += offset;
+            for (_, positions) in mesh.motion.iter_mut() {
+                positions[i] += offset;
+            }
+        }
+        // Like `set_motion`, static normals would not follow a deforming mesh.
+        if mesh.motion.is_empty() {
+            mesh.normals = Some(vertex_normals(&mesh.positions, &mesh.indices));
+        }
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{ConstantTexture, NoiseTexture};
+
+    fn gray(level: f32) -> Arc<dyn Texture> {
+        Arc::new(ConstantTexture::new(Vec3::new(level, level, level)))
+    }
+
+    #[test]
+    fn flat_height_lifts_the_plane() {
+        let displaced = Displacement::new(gray(0.75), 2.0, 0.5)
+            .with_midlevel(0.5)
+            .apply(&TriangleMesh::rectangle());
+        for (p, n) in displaced.positions.iter().zip(displaced.normals.unwrap()) {
+            assert!((p.z() - 0.5).abs() < 1e-5);
+            assert!((n - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn tessellation_leaves_no_long_edges_or_t_junctions() {
+        let mesh = Displacement::new(gray(0.0), 0.0, 0.3).apply(&TriangleMesh::rectangle());
+        let mut uses: HashMap<(usize, usize), usize> = HashMap::new();
+        for tri in mesh.indices.iter() {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                assert!((mesh.positions[a] - mesh.positions[b]).length() <= 0.3);
+                *uses.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        // Edges inside the square are shared by exactly two triangles.
+        for (&(a, b), &count) in uses.iter() {
+            let mid = 0.5 * (mesh.positions[a] + mesh.positions[b]);
+            let border = mid.x().abs() > 0.999 || mid.y().abs() > 0.999;
+            assert_eq!(count, if border { 1 } else { 2 });
+        }
+    }
+
+    #[test]
+    fn bounds_hold_the_displaced_mesh() {
+        let displacement = Displacement::new(Arc::new(NoiseTexture::new(4.0)), 0.3, 0.2);
+        let base = TriangleMesh::rectangle();
+        let bounds = displacement.bounds(&base).unwrap();
+        let displaced = displacement.apply(&base);
+        assert!(displaced.indices.len() > 100);
+        for p in displaced.positions.iter() {
+            for k in 0..3 {
+                assert!(p[k] >= bounds.min()[k] && p[k] <= bounds.max()[k]);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "edge length must be positive")]
+    fn zero_edge_length_is_rejected() {
+        Displacement::new(gray(0.5), 1.0, 0.0);
+    }
+
+    #[test]
+    fn seams_stay_closed() {
+        // The cube's faces don't share vertices, so without welding every edge would split.
+        let displaced = Displacement::new(Arc::new(NoiseTexture::new(3.0)), 0.3, 0.4)
+            .apply(&TriangleMesh::cube());
+        let key = |p: Point3| [p.x(), p.y(), p.z()].map(f32::to_bits);
+        let mut uses: HashMap<([u32; 3], [u32; 3]), usize> = HashMap::new();
+        for tri in displaced.indices.iter() {
+            for k in 0..3 {
+                let a = key(displaced.positions[tri[k]]);
+                let b = key(displaced.positions[tri[(k + 1) % 3]]);
+                *uses.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        assert!(uses.values().all(|&count| count == 2));
+    }
+}
@@ -0,0 +1,168 @@
+use rand::Rng;
+
+use super::vec3::{Color, Vec3};
+
+// Normalized color of a blackbody emitter, sampled at representative R, G and B wavelengths.
+pub fn blackbody(temperature: f32) -> Color {
+    let planck = |lambda_nm: f32| {
+        let l = lambda_nm as f64 * 1.0e-9;
+        let c = 299792458.0f64;
+        let h = 6.62606957e-34f64;
+        let kb = 1.3806488e-23f64;
+        (2.0 * h * c * c / (l.powi(5) * ((h * c / (l * kb * temperature as f64)).exp() - 1.0)))
+            as f32
+    };
+    let c = Color::new(planck(610.0), planck(550.0), planck(465.0));
+    let max = c.x().max(c.y()).max(c.z());
+    if max > 0.0 {
+        c / max
+    } else {
+        c
+    }
+}
+
+// Converts accumulated radiance into 8-bit pixels the way a camera sensor would. The
+// default is a unit gain with no color correction or noise.
+#[derive(Clone, Copy)]
+pub struct Exposure {
+    pub iso: f32,
+    pub shutter: f32,
+    pub f_number: f32,
+    pub gain: f32,
+    pub white_balance: Color,
+    pub noise: f32,
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self {
+            iso: 100.0,
+            shutter: 1.0,
+            f_number: f32::INFINITY,
+            gain: 1.0,
+            white_balance: Color::new(1.0, 1.0, 1.0),
+            noise: 0.0,
+        }
+    }
+}
+
+impl Exposure {
+    // Shutter speed in seconds. Scene radiance is read as luminance in cd/m^2 and the
+    // gain follows saturation-based ISO sensitivity, so "sunny 16" (f/16, 1/100 s at
+    // ISO 100) clips at about 30000 cd/m^2.
+    pub fn photographic(iso: f32, shutter: f32, f_number: f32) -> Self {
+        Self {
+            iso,
+            shutter,
+            f_number,
+            gain: shutter * iso / (120.0 * f_number * f_number),
+            ..Self::default()
+        }
+    }
+
+    // Exposure compensation in stops.
+    pub fn with_compensation(mut self, ev: f32) -> Self {
+        self.gain *= ev.exp2();
+        self
+    }
+
+    // Neutralizes an illuminant of the given color temperature in kelvin, relative to
+    // a D65-like 6500 K white.
+    pub fn with_white_balance(mut self, temperature: f32) -> Self {
+        let reference = blackbody(6500.0);
+        let illuminant = blackbody(temperature);
+        let gains = Vec3::new(
+            reference.x() / illuminant.x(),
+            reference.y() / illuminant.y(),
+            reference.z() / illuminant.z(),
+        );
+        // Keep the green channel fixed as cameras do.
+        self.white_balance = gains / gains.y();
+        self
+    }
+
+    // Scales shot and read noise; both grow with ISO like on a real sensor.
+    pub fn with_noise(mut self, amount: f32) -> Self {
+        self.noise = amount;
+        self
+    }
+
+    // Diameter of the entrance pupil for a lens of this focal length, as `Camera` expects.
+    pub fn lens_aperture(&self, focal_length: f32) -> f32 {
+        focal_length / self.f_number
+    }
+
+    // Shutter interval for a frame whose exposure starts at `open`.
+    pub fn shutter_interval(&self, open: f32) -> (f32, f32) {
+        (open, open + self.shutter)
+    }
+
+    fn sensor_noise(&self, signal: f32) -> f32 {
+        if self.noise <= 0.0 {
+            return 0.0;
+        }
+        // Fewer photons reach full scale at high ISO, so shot noise rises with it.
+        let iso = self.iso / 100.0;
+        let shot = (signal.max(0.0) * iso * 1.0e-4).sqrt();
+        let read = 0.002 * iso;
+        let mut rng = rand::thread_rng();
+        let (u1, u2): (f32, f32) = (rng.gen_range(f32::EPSILON..1.0), rng.gen());
+        let gaussian = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos();
+        self.noise * (shot + read) * gaussian
+    }
+
+    pub fn develop(&self, color: &Color, samples_per_pixel: u32) -> [u8; 3] {
+        let exposed = (self.gain / samples_per_pixel as f32) * (self.white_balance * *color);
+        let channel = |c: f32| {
+            let c = c + self.sensor_noise(c);
+            (255.999 * c.max(0.0).sqrt().clamp(0.0, 0.999)) as u8
+        };
+        [
+            channel(exposed[0]),
+            channel(exposed[1]),
+            channel(exposed[2]),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_exposure_is_plain_gamma() {
+        let exposure = Exposure::default();
+        assert_eq!(
+            exposure.develop(&Color::new(1.0, 4.0, 0.0), 4),
+            [127, 255, 0]
+        );
+    }
+
+    #[test]
+    fn sunny_sixteen_clips_near_thirty_thousand_nits() {
+        let exposure = Exposure::photographic(100.0, 0.01, 16.0);
+        let gray = |nits: f32| exposure.develop(&Color::new(nits, nits, nits), 1)[0];
+        assert!(gray(29000.0) < 255);
+        assert_eq!(gray(32000.0), 255);
+        // One stop more halves the light needed for the same pixel.
+        let brighter = exposure.with_compensation(1.0);
+        assert_eq!(
+            brighter.develop(&Color::new(7000.0, 7000.0, 7000.0), 1),
+            exposure.develop(&Color::new(14000.0, 14000.0, 14000.0), 1)
+        );
+        assert!((exposure.lens_aperture(0.05) - 0.05 / 16.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn white_balance_neutralizes_the_illuminant() {
+        let daylight = Exposure::default().with_white_balance(6500.0);
+        assert!((daylight.white_balance - Color::new(1.0, 1.0, 1.0)).length() < 1e-4);
+
+        // Tungsten light is orange, so balancing for it cools the image.
+        let tungsten = Exposure::default().with_white_balance(3200.0);
+        assert!(tungsten.white_balance.x() < 1.0 && tungsten.white_balance.z() > 1.0);
+        let light = tungsten.white_balance * blackbody(3200.0);
+        let reference = blackbody(6500.0);
+        assert!((light / light.y() - reference / reference.y()).length() < 1e-4);
+    }
+}
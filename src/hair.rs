@@ -0,0 +1,406 @@
+use super::hittable::HitRecord;
+use super::material::Scatter;
+use super::ray::Ray;
+use super::vec3::{Color, Vec3, VectorConst};
+
+use rand::Rng;
+use std::f32::consts::PI;
+
+// Paths with more internal bounces than this are lumped into one lobe.
+const P_MAX: usize = 3;
+
+fn safe_sqrt(x: f32) -> f32 {
+    x.max(0.0).sqrt()
+}
+
+fn safe_asin(x: f32) -> f32 {
+    x.clamp(-1.0, 1.0).asin()
+}
+
+fn luminance(c: Color) -> f32 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+// Unpolarized Fresnel reflectance going from air into index `eta`.
+fn fr_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t = safe_sqrt(1.0 - cos_i * cos_i) / eta;
+    if sin_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = safe_sqrt(1.0 - sin_t * sin_t);
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+// Modified Bessel function of the first kind, order zero.
+fn i0(x: f32) -> f32 {
+    let mut value = 0.0;
+    let (mut x2i, mut factorial, mut four_i) = (1.0, 1.0, 1.0);
+    for i in 0..10 {
+        if i > 1 {
+            factorial *= i as f32;
+        }
+        value += x2i / (four_i * factorial * factorial);
+        x2i *= x * x;
+        four_i *= 4.0;
+    }
+    value
+}
+
+fn log_i0(x: f32) -> f32 {
+    if x > 12.0 {
+        x + 0.5 * (-(2.0 * PI).ln() + (1.0 / x).ln() + 1.0 / (8.0 * x))
+    } else {
+        i0(x).ln()
+    }
+}
+
+// Longitudinal scattering.
+fn mp(cos_i: f32, cos_o: f32, sin_i: f32, sin_o: f32, v: f32) -> f32 {
+    let a = cos_i * cos_o / v;
+    let b = sin_i * sin_o / v;
+    if v <= 0.1 {
+        (log_i0(a) - b - 1.0 / v + std::f32::consts::LN_2 + (1.0 / (2.0 * v)).ln()).exp()
+    } else {
+        (-b).exp() * i0(a) / ((1.0 / v).sinh() * 2.0 * v)
+    }
+}
+
+// Attenuation of each lobe: reflection, transmission, one internal bounce and the rest.
+fn ap(cos_o: f32, eta: f32, h: f32, transmittance: Color) -> [Color; P_MAX + 1] {
+    let cos_gamma_o = safe_sqrt(1.0 - h * h);
+    let f = fr_dielectric(cos_o * cos_gamma_o, eta);
+    let r = Color::new(f, f, f);
+    let tt = (1.0 - f) * (1.0 - f) * transmittance;
+    let trt = f * tt * transmittance;
+    let rest = f * trt * transmittance;
+    let rest = Color::new(
+        rest.x() / (1.0 - f * transmittance.x()),
+        rest.y() / (1.0 - f * transmittance.y()),
+        rest.z() / (1.0 - f * transmittance.z()),
+    );
+    [r, tt, trt, rest]
+}
+
+// Azimuthal angle by which lobe `p` leaves the fiber.
+fn phi(p: usize, gamma_o: f32, gamma_t: f32) -> f32 {
+    2.0 * p as f32 * gamma_t - 2.0 * gamma_o + p as f32 * PI
+}
+
+fn logistic(x: f32, s: f32) -> f32 {
+    let x = x.abs();
+    let e = (-x / s).exp();
+    e / (s * (1.0 + e) * (1.0 + e))
+}
+
+fn logistic_cdf(x: f32, s: f32) -> f32 {
+    1.0 / (1.0 + (-x / s).exp())
+}
+
+// Azimuthal scattering: a logistic distribution around the lobe's exit angle.
+fn np(phi_diff: f32, p: usize, s: f32, gamma_o: f32, gamma_t: f32) -> f32 {
+    let mut dphi = phi_diff - phi(p, gamma_o, gamma_t);
+    while dphi > PI {
+        dphi -= 2.0 * PI;
+    }
+    while dphi < -PI {
+        dphi += 2.0 * PI;
+    }
+    logistic(dphi, s) / (logistic_cdf(PI, s) - logistic_cdf(-PI, s))
+}
+
+fn sample_trimmed_logistic(u: f32, s: f32) -> f32 {
+    let (a, b) = (logistic_cdf(-PI, s), logistic_cdf(PI, s));
+    let x = -s * (1.0 / (u * (b - a) + a) - 1.0).ln();
+    x.clamp(-PI, PI)
+}
+
+// The hair fiber model of d'Eon et al. and Chiang et al., after pbrt: light reflects off
+// the cuticle, passes through the colored interior or bounces inside it, each with its
+// own longitudinal and azimuthal spread. Meant for `Curve` hits, which give the fiber
+// direction and where across the fiber was hit; for other shapes the fiber runs along
+// an arbitrary direction in the surface.
+pub struct Hair {
+    sigma_a: Color,
+    eta: f32,
+    // Longitudinal variance and azimuthal scale of each lobe.
+    v: [f32; P_MAX + 1],
+    s: f32,
+    // Sines and cosines of the cuticle scale tilt, twice it and four times it.
+    sin_2k_alpha: [f32; 3],
+    cos_2k_alpha: [f32; 3],
+    beta_m: f32,
+    beta_n: f32,
+    alpha: f32,
+}
+
+impl Hair {
+    // `sigma_a` is the absorption of the fiber interior per unit of diameter.
+    pub fn new(sigma_a: Color) -> Self {
+        Self::with_parameters(sigma_a, 1.55, 0.3, 0.3, 2.0)
+    }
+
+    // Natural hair color from the concentration of the two melanin pigments: eumelanin
+    // goes from blonde around 0.3 through brown around 1.3 to black above 8, and
+    // pheomelanin adds red.
+    pub fn from_melanin(eumelanin: f32, pheomelanin: f32) -> Self {
+        let eumelanin_sigma_a = Color::new(0.419, 0.697, 1.37);
+        let pheomelanin_sigma_a = Color::new(0.187, 0.4, 1.05);
+        Self::new(eumelanin * eumelanin_sigma_a + pheomelanin * pheomelanin_sigma_a)
+    }
+
+    // Picks the absorption that gives roughly `color` after many bounces, for dyed hair,
+    // fur or grass. The match holds for this azimuthal roughness `beta_n`, so change the
+    // roughness here rather than with `with_roughness`.
+    pub fn from_color(color: Color, beta_n: f32) -> Self {
+        let b = beta_n;
+        let denominator = 5.969 - 0.215 * b + 2.532 * b.powi(2) - 10.73 * b.powi(3)
+            + 5.574 * b.powi(4)
+            + 0.245 * b.powi(5);
+        let sigma = |c: f32| (c.max(1.0e-4).ln() / denominator).powi(2);
+        let sigma_a = Color::new(sigma(color.x()), sigma(color.y()), sigma(color.z()));
+        Self::with_parameters(sigma_a, 1.55, 0.3, beta_n, 2.0)
+    }
+
+    // Longitudinal and azimuthal roughness in [0, 1].
+    pub fn with_roughness(self, beta_m: f32, beta_n: f32) -> Self {
+        Self::with_parameters(self.sigma_a, self.eta, beta_m, beta_n, self.alpha)
+    }
+
+    // Tilt of the cuticle scales in degrees, which shifts the highlights along the fiber.
+    pub fn with_scale_tilt(self, alpha: f32) -> Self {
+        Self::with_parameters(self.sigma_a, self.eta, self.beta_m, self.beta_n, alpha)
+    }
+
+    pub fn with_ior(self, eta: f32) -> Self {
+        Self::with_parameters(self.sigma_a, eta, self.beta_m, self.beta_n, self.alpha)
+    }
+
+    fn with_parameters(sigma_a: Color, eta: f32, beta_m: f32, beta_n: f32, alpha: f32) -> Self {
+        let v0 = (0.726 * beta_m + 0.812 * beta_m.powi(2) + 3.7 * beta_m.powi(20)).powi(2);
+        let s = 0.626_657 * (0.265 * beta_n + 1.194 * beta_n.powi(2) + 5.372 * beta_n.powi(22));
+        let mut sin_2k_alpha = [alpha.to_radians().sin(), 0.0, 0.0];
+        let mut cos_2k_alpha = [safe_sqrt(1.0 - sin_2k_alpha[0].powi(2)), 0.0, 0.0];
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1].powi(2) - sin_2k_alpha[i - 1].powi(2);
+        }
+        Self {
+            sigma_a,
+            eta,
+            v: [v0, 0.25 * v0, 4.0 * v0, 4.0 * v0],
+            s,
+            sin_2k_alpha,
+            cos_2k_alpha,
+            beta_m,
+            beta_n,
+            alpha,
+        }
+    }
+
+    // Leaving angle of lobe `p`, shifted by the scale tilt.
+    fn tilted(&self, p: usize, sin_o: f32, cos_o: f32) -> (f32, f32) {
+        let (sin_a, cos_a) = match p {
+            0 => (-self.sin_2k_alpha[1], self.cos_2k_alpha[1]),
+            1 => (self.sin_2k_alpha[0], self.cos_2k_alpha[0]),
+            2 => (self.sin_2k_alpha[2], self.cos_2k_alpha[2]),
+            _ => return (sin_o, cos_o),
+        };
+        (
+            sin_o * cos_a + cos_o * sin_a,
+            (cos_o * cos_a - sin_o * sin_a).abs(),
+        )
+    }
+
+    // Lobe attenuations for light leaving toward `wo` at offset `h`, with the angles
+    // inside the fiber they depend on.
+    fn attenuation(&self, sin_o: f32, cos_o: f32, h: f32) -> ([Color; P_MAX + 1], f32) {
+        let sin_t = sin_o / self.eta;
+        let cos_t = safe_sqrt(1.0 - sin_t * sin_t);
+        let etap = safe_sqrt(self.eta * self.eta - sin_o * sin_o) / cos_o;
+        let sin_gamma_t = h / etap;
+        let cos_gamma_t = safe_sqrt(1.0 - sin_gamma_t * sin_gamma_t);
+        let path = 2.0 * cos_gamma_t / cos_t;
+        let transmittance = Color::new(
+            (-self.sigma_a.x() * path).exp(),
+            (-self.sigma_a.y() * path).exp(),
+            (-self.sigma_a.z() * path).exp(),
+        );
+        (
+            ap(cos_o, self.eta, h, transmittance),
+            safe_asin(sin_gamma_t),
+        )
+    }
+
+    // Scattering (without the cosine) and sampling density from `wo` to `wi`, both in
+    // the fiber frame with x along the fiber.
+    fn evaluate(&self, wo: Vec3, wi: Vec3, h: f32, ap_pdf: &[f32; P_MAX + 1]) -> (Color, f32) {
+        let (sin_o, sin_i) = (wo.x(), wi.x());
+        let (cos_o, cos_i) = (
+            safe_sqrt(1.0 - sin_o * sin_o),
+            safe_sqrt(1.0 - sin_i * sin_i),
+        );
+        let phi_diff = wi.z().atan2(wi.y()) - wo.z().atan2(wo.y());
+        let gamma_o = safe_asin(h);
+        let (ap, gamma_t) = self.attenuation(sin_o, cos_o, h);
+
+        let mut f = Color::new(0.0, 0.0, 0.0);
+        let mut pdf = 0.0;
+        for p in 0..P_MAX {
+            let (sin_op, cos_op) = self.tilted(p, sin_o, cos_o);
+            let m = mp(cos_i, cos_op, sin_i, sin_op, self.v[p]);
+            let n = np(phi_diff, p, self.s, gamma_o, gamma_t);
+            f += m * n * ap[p];
+            pdf += m * n * ap_pdf[p];
+        }
+        let m = mp(cos_i, cos_o, sin_i, sin_o, self.v[P_MAX]) / (2.0 * PI);
+        f += m * ap[P_MAX];
+        pdf += m * ap_pdf[P_MAX];
+        (f, pdf)
+    }
+}
+
+impl Scatter for Hair {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let d = r_in.direction().normalized();
+        let tangent = match rec.tangent {
+            Some(t) => t,
+            None => {
+                let helper = if rec.normal.x().abs() > 0.9 {
+                    Vec3::new(0.0, 1.0, 0.0)
+                } else {
+                    Vec3::new(1.0, 0.0, 0.0)
+                };
+                helper.cross(rec.normal).normalized()
+            }
+        };
+        // The fiber frame: x along the fiber, y across it the way `v` grows, and z away
+        // from the viewer.
+        let y_axis = d.cross(tangent);
+        if y_axis.near_zero() {
+            return None;
+        }
+        let y_axis = y_axis.normalized();
+        let z_axis = tangent.cross(y_axis);
+        let to_local = |w: Vec3| Vec3::new(w.dot(tangent), w.dot(y_axis), w.dot(z_axis));
+        let wo = to_local(-1.0 * d);
+        let h = (2.0 * rec.v - 1.0).clamp(-1.0, 1.0);
+
+        // Pick a lobe in proportion to how much light it carries.
+        let (sin_o, cos_o) = (wo.x(), safe_sqrt(1.0 - wo.x() * wo.x()));
+        let (ap, gamma_t) = self.attenuation(sin_o, cos_o, h);
+        let total: f32 = ap.iter().map(|&a| luminance(a)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut ap_pdf = [0.0; P_MAX + 1];
+        for (pdf, &a) in ap_pdf.iter_mut().zip(ap.iter()) {
+            *pdf = luminance(a) / total;
+        }
+        let mut rng = rand::thread_rng();
+        let pick: f32 = rng.gen();
+        let mut p = 0;
+        let mut cumulative = ap_pdf[0];
+        while p < P_MAX && pick >= cumulative {
+            p += 1;
+            cumulative += ap_pdf[p];
+        }
+
+        // Sample the longitudinal angle about the lobe's tilted cone.
+        let (sin_op, cos_op) = self.tilted(p, sin_o, cos_o);
+        let u: f32 = rng.gen::<f32>().max(1.0e-5);
+        let cos_theta = 1.0 + self.v[p] * (u + (1.0 - u) * (-2.0 / self.v[p]).exp()).ln();
+        let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+        let cos_phi = (2.0 * PI * rng.gen::<f32>()).cos();
+        let sin_i = -cos_theta * sin_op + sin_theta * cos_phi * cos_op;
+        let cos_i = safe_sqrt(1.0 - sin_i * sin_i);
+
+        // And the azimuth around the fiber.
+        let dphi = if p < P_MAX {
+            phi(p, safe_asin(h), gamma_t) + sample_trimmed_logistic(rng.gen(), self.s)
+        } else {
+            2.0 * PI * rng.gen::<f32>()
+        };
+        let phi_i = wo.z().atan2(wo.y()) + dphi;
+        let wi = Vec3::new(sin_i, cos_i * phi_i.cos(), cos_i * phi_i.sin());
+
+        let (f, pdf) = self.evaluate(wo, wi, h, &ap_pdf);
+        if pdf <= 0.0 || !pdf.is_finite() {
+            return None;
+        }
+        let direction = wi.x() * tangent + wi.y() * y_axis + wi.z() * z_axis;
+        Some((f / pdf, Ray::new(rec.p, direction, r_in.time())))
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
+        Vec3::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Midpoint rule over [a, b].
+    fn integrate(a: f32, b: f32, f: impl Fn(f32) -> f32) -> f32 {
+        let n = 2000;
+        let h = (b - a) / n as f32;
+        (0..n).map(|i| f(a + (i as f32 + 0.5) * h)).sum::<f32>() * h
+    }
+
+    #[test]
+    fn longitudinal_lobes_are_normalized() {
+        for v in [0.05, 0.1, 0.3, 0.9] {
+            for theta_o in [-1.2f32, -0.3, 0.0, 0.7] {
+                let (sin_o, cos_o) = theta_o.sin_cos();
+                let total = integrate(-0.5 * PI, 0.5 * PI, |theta_i| {
+                    let (sin_i, cos_i) = theta_i.sin_cos();
+                    mp(cos_i, cos_o, sin_i, sin_o, v) * cos_i
+                });
+                assert!(
+                    (total - 1.0).abs() < 0.02,
+                    "v {} theta {}: {}",
+                    v,
+                    theta_o,
+                    total
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn azimuthal_lobes_are_normalized() {
+        for s in [0.1, 0.5, 1.0] {
+            for p in 0..P_MAX {
+                let total = integrate(-PI, PI, |phi_diff| np(phi_diff, p, s, 0.3, 0.1));
+                assert!((total - 1.0).abs() < 1e-3);
+            }
+            for i in 0..=10 {
+                let x = sample_trimmed_logistic(i as f32 / 10.0, s);
+                assert!((-PI..=PI).contains(&x));
+            }
+        }
+    }
+
+    #[test]
+    fn clear_fibers_lose_no_energy() {
+        // Without absorption the lobes split the light between them and nothing is lost.
+        let clear = Color::new(1.0, 1.0, 1.0);
+        for h in [-0.9, 0.0, 0.5] {
+            for cos_o in [0.2, 0.7, 1.0] {
+                let lobes = ap(cos_o, 1.55, h, clear);
+                let total = lobes
+                    .iter()
+                    .fold(Color::new(0.0, 0.0, 0.0), |sum, &a| sum + a);
+                assert!((total - clear).length() < 1e-4);
+            }
+        }
+        // Absorption darkens the colored lobes but not the surface reflection.
+        let dark = ap(0.8, 1.55, 0.2, Color::new(0.5, 0.5, 0.5));
+        let light = ap(0.8, 1.55, 0.2, clear);
+        assert!((dark[0] - light[0]).length() < 1e-6);
+        assert!(dark[1].x() < light[1].x() && dark[2].x() < light[2].x());
+    }
+}
@@ -0,0 +1,376 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::material::Scatter;
+use super::mesh::LoadError;
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+
+use std::path::Path;
+use std::sync::Arc;
+
+// Ray parameter and barycentrics of the hit on triangle (p0, p1, p2), if any.
+fn hit_triangle(r: &Ray, p0: Point3, p1: Point3, p2: Point3) -> Option<(f32, f32, f32)> {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let pvec = r.direction().cross(e2);
+    let det = e1.dot(pvec);
+    if det.abs() < 1.0e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = r.origin() - p0;
+    let b1 = tvec.dot(pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = tvec.cross(e1);
+    let b2 = r.direction().dot(qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    Some((e2.dot(qvec) * inv_det, b1, b2))
+}
+
+// A terrain surface over the rectangle from `corner` spanning `size.x()` by `size.z()`,
+// sampled on an `nx` by `nz` grid of heights in [0, 1] that are scaled by `size.y()`.
+// Each grid cell is split into two triangles with interpolated vertex normals.
+pub struct Heightfield {
+    nx: usize,
+    nz: usize,
+    corner: Point3,
+    size: Vec3,
+    // World-space heights, row by row along x.
+    heights: Vec<f32>,
+    normals: Vec<Vec3>,
+    // Lowest and highest point of each cell, to skip cells the ray passes over.
+    cell_range: Vec<(f32, f32)>,
+    bounds: AABB,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Heightfield {
+    pub fn new(
+        heights: Vec<f32>,
+        nx: usize,
+        nz: usize,
+        corner: Point3,
+        size: Vec3,
+        mat: Arc<dyn Scatter>,
+    ) -> Self {
+        assert!(nx >= 2 && nz >= 2, "heightfield needs at least 2x2 samples");
+        assert_eq!(heights.len(), nx * nz, "heightfield sample count");
+        let heights: Vec<f32> = heights.iter().map(|h| corner.y() + size.y() * h).collect();
+        let (dx, dz) = (size.x() / (nx - 1) as f32, size.z() / (nz - 1) as f32);
+        let at = |i: usize, j: usize| heights[j * nx + i];
+
+        // Central differences, one-sided at the borders.
+        let mut normals = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(nz - 1));
+                let slope_x = (at(i1, j) - at(i0, j)) / ((i1 - i0) as f32 * dx);
+                let slope_z = (at(i, j1) - at(i, j0)) / ((j1 - j0) as f32 * dz);
+                normals.push(Vec3::new(-slope_x, 1.0, -slope_z).normalized());
+            }
+        }
+
+        let mut cell_range = Vec::with_capacity((nx - 1) * (nz - 1));
+        for j in 0..nz - 1 {
+            for i in 0..nx - 1 {
+                let h = [at(i, j), at(i + 1, j), at(i, j + 1), at(i + 1, j + 1)];
+                cell_range.push((
+                    h.iter().copied().fold(f32::INFINITY, f32::min),
+                    h.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+                ));
+            }
+        }
+
+        let low = heights.iter().copied().fold(f32::INFINITY, f32::min);
+        let high = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        // Padded like `Rect` so a flat field doesn't get a flat box.
+        let bounds = AABB::new(
+            Point3::new(corner.x(), low - 0.0001, corner.z()),
+            Point3::new(corner.x() + size.x(), high + 0.0001, corner.z() + size.z()),
+        );
+
+        Self {
+            nx,
+            nz,
+            corner,
+            size,
+            heights,
+            normals,
+            cell_range,
+            bounds,
+            mat,
+        }
+    }
+
+    // Samples `height(u, v)` for u and v in [0, 1] across x and z.
+    pub fn from_fn<F: Fn(f32, f32) -> f32>(
+        height: F,
+        nx: usize,
+        nz: usize,
+        corner: Point3,
+        size: Vec3,
+        mat: Arc<dyn Scatter>,
+    ) -> Self {
+        let mut heights = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                heights.push(height(
+                    i as f32 / (nx - 1) as f32,
+                    j as f32 / (nz - 1) as f32,
+                ));
+            }
+        }
+        Self::new(heights, nx, nz, corner, size, mat)
+    }
+
+    // Uses the brightness of a grayscale (or color) image as height, one sample per
+    // pixel, with the top row of the image along the low-z edge.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        corner: Point3,
+        size: Vec3,
+        mat: Arc<dyn Scatter>,
+    ) -> Result<Self, LoadError> {
+        let image = image::open(path)
+            .map_err(|err| LoadError::Parse(err.to_string()))?
+            .to_luma16();
+        let (nx, nz) = (image.width() as usize, image.height() as usize);
+        if nx < 2 || nz < 2 {
+            return Err(LoadError::Parse(format!(
+                "heightfield image is only {}x{}",
+                nx, nz
+            )));
+        }
+        let heights = image.pixels().map(|p| p[0] as f32 / 65535.0).collect();
+        Ok(Self::new(heights, nx, nz, corner, size, mat))
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        Point3::new(
+            self.corner.x() + self.size.x() * i as f32 / (self.nx - 1) as f32,
+            self.heights[j * self.nx + i],
+            self.corner.z() + self.size.z() * j as f32 / (self.nz - 1) as f32,
+        )
+    }
+
+    // Nearest hit on the two triangles of cell (i, j).
+    fn hit_cell(&self, r: &Ray, i: usize, j: usize, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (c00, c10, c11, c01) = ((i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1));
+        let triangles = [[c00, c11, c10], [c00, c01, c11]];
+        let mut best: Option<(f32, usize, f32, f32)> = None;
+        for (k, ids) in triangles.iter().enumerate() {
+            let p = ids.map(|(a, b)| self.vertex(a, b));
+            if let Some((t, b1, b2)) = hit_triangle(r, p[0], p[1], p[2]) {
+                if t > t_min && t < t_max && best.is_none_or(|b| t < b.0) {
+                    best = Some((t, k, b1, b2));
+                }
+            }
+        }
+        let (t, k, b1, b2) = best?;
+        let ids = triangles[k];
+        let b0 = 1.0 - b1 - b2;
+        let normal_at = |(a, b): (usize, usize)| self.normals[b * self.nx + a];
+        let outward_normal =
+            (b0 * normal_at(ids[0]) + b1 * normal_at(ids[1]) + b2 * normal_at(ids[2])).normalized();
+        let p = r.at(t);
+        let mut rec = HitRecord {
+            t,
+            p,
+            normal: outward_normal,
+            u: (p.x() - self.corner.x()) / self.size.x(),
+            // Matches `ImageTexture`, so a color image of the same layout drapes over it.
+            v: 1.0 - (p.z() - self.corner.z()) / self.size.z(),
+            mat: self.mat.clone(),
+            front_face: false,
+            color: None,
+            tangent: None,
+        };
+        rec.set_face_normal(r, outward_normal);
+        Some(rec)
+    }
+}
+
+impl Hittable for Heightfield {
+    // Walks the cells under the ray with a 2D DDA, nearest first.
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (o, d) = (r.origin(), r.direction());
+        let (mut t0, mut t1) = (t_min, t_max);
+        for a in 0..3 {
+            let inv_d = 1.0 / d[a];
+            let mut near = (self.bounds.min()[a] - o[a]) * inv_d;
+            let mut far = (self.bounds.max()[a] - o[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+            if t1 < t0 {
+                return None;
+            }
+        }
+
+        // Grid coordinates, one unit per cell.
+        let (cells_x, cells_z) = (self.nx - 1, self.nz - 1);
+        let scale_x = cells_x as f32 / self.size.x();
+        let scale_z = cells_z as f32 / self.size.z();
+        let start = r.at(t0);
+        let gx = (start.x() - self.corner.x()) * scale_x;
+        let gz = (start.z() - self.corner.z()) * scale_z;
+        let mut i = (gx.floor().max(0.0) as usize).min(cells_x - 1);
+        let mut j = (gz.floor().max(0.0) as usize).min(cells_z - 1);
+
+        let axis = |g: f32, cell: usize, dir: f32, scale: f32| {
+            if dir > 0.0 {
+                (
+                    1i64,
+                    t0 + ((cell + 1) as f32 - g) / (dir * scale),
+                    1.0 / (dir * scale),
+                )
+            } else if dir < 0.0 {
+                (
+                    -1,
+                    t0 + (cell as f32 - g) / (dir * scale),
+                    -1.0 / (dir * scale),
+                )
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) = axis(gx, i, d.x(), scale_x);
+        let (step_z, mut next_z, delta_z) = axis(gz, j, d.z(), scale_z);
+
+        let mut t_enter = t0;
+        loop {
+            let t_exit = next_x.min(next_z).min(t1);
+            let (low, high) = self.cell_range[j * cells_x + i];
+            let (y0, y1) = (o.y() + t_enter * d.y(), o.y() + t_exit * d.y());
+            if y0.min(y1) <= high && y0.max(y1) >= low {
+                // A little slack so hits on a shared edge aren't lost between cells.
+                let slack = 1.0e-4 * (t_exit - t_enter).abs().max(1.0e-4);
+                let hit = self.hit_cell(
+                    r,
+                    i,
+                    j,
+                    t_min.max(t_enter - slack),
+                    t_max.min(t_exit + slack),
+                );
+                if hit.is_some() {
+                    return hit;
+                }
+            }
+            if t_exit >= t1 {
+                return None;
+            }
+            t_enter = t_exit;
+            if next_x < next_z {
+                if (step_x < 0 && i == 0) || (step_x > 0 && i + 1 == cells_x) {
+                    return None;
+                }
+                i = (i as i64 + step_x) as usize;
+                next_x += delta_x;
+            } else {
+                if (step_z < 0 && j == 0) || (step_z > 0 && j + 1 == cells_z) {
+                    return None;
+                }
+                j = (j as i64 + step_z) as usize;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::gray;
+    use rand::Rng;
+
+    fn bumps() -> Heightfield {
+        Heightfield::from_fn(
+            |u, v| 0.5 + 0.25 * (9.0 * u).sin() * (7.0 * v).cos(),
+            17,
+            13,
+            Point3::new(-2.0, 0.0, -1.0),
+            Vec3::new(4.0, 1.0, 3.0),
+            gray(),
+        )
+    }
+
+    // Tries every cell, to check the grid walk against.
+    fn brute_force(field: &Heightfield, r: &Ray) -> Option<f32> {
+        let mut nearest: Option<f32> = None;
+        for j in 0..field.nz - 1 {
+            for i in 0..field.nx - 1 {
+                if let Some(rec) = field.hit_cell(r, i, j, 0.001, f32::INFINITY) {
+                    nearest = Some(nearest.map_or(rec.t, |t| t.min(rec.t)));
+                }
+            }
+        }
+        nearest
+    }
+
+    #[test]
+    fn grid_walk_finds_the_nearest_cell() {
+        let field = bumps();
+        let mut rng = rand::thread_rng();
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin = Point3::new(
+                rng.gen_range(-4.0..4.0),
+                rng.gen_range(0.0..3.0),
+                rng.gen_range(-3.0..4.0),
+            );
+            let target = Point3::new(
+                rng.gen_range(-2.0..2.0),
+                rng.gen_range(0.0..1.0),
+                rng.gen_range(-1.0..2.0),
+            );
+            let ray = Ray::new(origin, target - origin, 0.0);
+            let walked = field.hit(&ray, 0.001, f32::INFINITY).map(|rec| rec.t);
+            match (walked, brute_force(&field, &ray)) {
+                (Some(a), Some(b)) => {
+                    assert!((a - b).abs() < 1e-4, "{} vs {}", a, b);
+                    hits += 1;
+                }
+                (None, None) => {}
+                (a, b) => panic!("grid walk {:?}, brute force {:?}", a, b),
+            }
+        }
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn sloped_plane_is_hit_exactly() {
+        // Rises from y = 0 at x = 0 to y = 2 at x = 4.
+        let field = Heightfield::from_fn(
+            |u, _| u,
+            5,
+            5,
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(4.0, 2.0, 4.0),
+            gray(),
+        );
+        let rec = field
+            .hit(
+                &Ray::new(Point3::new(3.0, 5.0, 1.0), Vec3::new(0.0, -1.0, 0.0), 0.0),
+                0.001,
+                f32::INFINITY,
+            )
+            .unwrap();
+        assert!((rec.t - 3.5).abs() < 1e-4);
+        assert!((rec.normal - Vec3::new(-0.5, 1.0, 0.0).normalized()).length() < 1e-4);
+        assert!((rec.u - 0.75).abs() < 1e-4 && (rec.v - 0.75).abs() < 1e-4);
+        // A ray skimming above the slope misses.
+        let over = Ray::new(Point3::new(-1.0, 2.5, 2.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(field.hit(&over, 0.001, f32::INFINITY).is_none());
+    }
+}
@@ -1,7 +1,7 @@
 use super::aabb::AABB;
 use super::material::Scatter;
 use super::ray::Ray;
-use super::vec3::{Point3, Vec3};
+use super::vec3::{Color, Point3, Vec3};
 use std::sync::Arc;
 
 pub struct HitRecord {
@@ -12,6 +12,9 @@ pub struct HitRecord {
     pub u: f32,
     pub v: f32,
     pub front_face: bool,
+    pub color: Option<Color>,
+    // Direction along the surface for materials that need one, such as hair fibers.
+    pub tangent: Option<Vec3>,
 }
 
 impl HitRecord {
@@ -29,4 +32,52 @@ pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
 
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB>;
+
+    // Every surface crossing along the whole line of the ray, nearest first. `front_face`
+    // tells entries into a closed shape from exits.
+    fn all_hits(&self, r: &Ray) -> Vec<HitRecord> {
+        let mut hits: Vec<HitRecord> = Vec::new();
+        let mut t = -f32::MAX;
+        // Bounded in case a degenerate shape keeps reporting the same crossing.
+        for _ in 0..256 {
+            match self.hit(r, t, f32::MAX) {
+                Some(rec) => {
+                    // The next float rather than a fixed step, which far from the origin
+                    // would round away and find the same crossing again.
+                    t = rec.t.next_up();
+                    // Shapes that march or refine can report one crossing a hair apart.
+                    let repeat = hits.last().is_some_and(|last: &HitRecord| {
+                        rec.t - last.t <= 1.0e-6 * last.t.abs().max(1.0)
+                    });
+                    if !repeat {
+                        hits.push(rec);
+                    }
+                }
+                None => break,
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::testing::gray;
+
+    #[test]
+    fn far_crossings_are_each_found_once() {
+        // Out here a fixed step of 1e-4 is below the spacing of f32s.
+        let slab = Cube::new(
+            Vec3::new(-1.0, -1.0, 1.0e4),
+            Vec3::new(1.0, 1.0, 1.0e4 + 0.5),
+            gray(),
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let hits = slab.all_hits(&ray);
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].front_face && !hits[1].front_face);
+        assert!((hits[1].t - hits[0].t - 0.5).abs() < 1e-3);
+    }
 }
@@ -0,0 +1,476 @@
+use rand::Rng;
+
+use super::camera::{look_at_basis, sample_time, CameraModel};
+use super::mesh::LoadError;
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+// Grayscale aperture mask; brighter pixels let through proportionally more light.
+pub struct BokehImage {
+    width: usize,
+    height: usize,
+    weights: Vec<f32>,
+    cdf: Vec<f32>,
+}
+
+impl BokehImage {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        let image = image::open(path)
+            .map_err(|err| LoadError::Parse(err.to_string()))?
+            .to_luma8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let weights: Vec<f32> = image.pixels().map(|p| p[0] as f32 / 255.0).collect();
+        let mut total = 0.0;
+        let cdf: Vec<f32> = weights
+            .iter()
+            .map(|w| {
+                total += w;
+                total
+            })
+            .collect();
+        if total <= 0.0 {
+            return Err(LoadError::Parse(
+                "bokeh image is completely black".to_string(),
+            ));
+        }
+        Ok(Self {
+            width,
+            height,
+            weights,
+            cdf,
+        })
+    }
+
+    // The image spans [-1, 1]^2 with its top row at y = 1.
+    fn sample(&self) -> (f32, f32) {
+        let mut rng = rand::thread_rng();
+        let target = rng.gen_range(0.0..self.cdf[self.cdf.len() - 1]);
+        let index = self
+            .cdf
+            .partition_point(|&c| c <= target)
+            .min(self.cdf.len() - 1);
+        let x = (index % self.width) as f32 + rng.gen::<f32>();
+        let y = (index / self.width) as f32 + rng.gen::<f32>();
+        (
+            2.0 * x / self.width as f32 - 1.0,
+            1.0 - 2.0 * y / self.height as f32,
+        )
+    }
+
+    fn transmission(&self, x: f32, y: f32) -> f32 {
+        let i = (0.5 * (x + 1.0) * self.width as f32).floor();
+        let j = (0.5 * (1.0 - y) * self.height as f32).floor();
+        if i < 0.0 || j < 0.0 || i >= self.width as f32 || j >= self.height as f32 {
+            return 0.0;
+        }
+        self.weights[j as usize * self.width + i as usize]
+    }
+}
+
+// Shape of the lens opening, in units of the lens radius.
+#[derive(Clone)]
+pub enum Aperture {
+    Circular,
+    // A regular polygon of `blades` sides, rotated by `rotation` degrees.
+    Polygon { blades: u32, rotation: f32 },
+    Image(Arc<BokehImage>),
+}
+
+impl Aperture {
+    pub fn sample(&self) -> (f32, f32) {
+        match self {
+            Aperture::Circular => {
+                let p = Vec3::random_in_unit_disk();
+                (p.x(), p.y())
+            }
+            Aperture::Polygon { blades, rotation } => {
+                // Uniform point in one of the triangles fanning out from the center.
+                let mut rng = rand::thread_rng();
+                let n = (*blades).max(3);
+                let k = rng.gen_range(0..n) as f32;
+                let angle = |k: f32| rotation.to_radians() + 2.0 * PI * k / n as f32;
+                let (a, b) = (angle(k), angle(k + 1.0));
+                let (r1, r2): (f32, f32) = (rng.gen(), rng.gen());
+                let s = r1.sqrt();
+                let (wa, wb) = (s * (1.0 - r2), s * r2);
+                (wa * a.cos() + wb * b.cos(), wa * a.sin() + wb * b.sin())
+            }
+            Aperture::Image(image) => image.sample(),
+        }
+    }
+
+    // Fraction of light passing at a point, for apertures placed inside a lens system.
+    pub fn transmission(&self, x: f32, y: f32) -> f32 {
+        match self {
+            Aperture::Circular => {
+                if x * x + y * y <= 1.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Aperture::Polygon { blades, rotation } => {
+                let n = (*blades).max(3) as f32;
+                let sector = 2.0 * PI / n;
+                let angle = (y.atan2(x) - rotation.to_radians()).rem_euclid(sector);
+                let r = (x * x + y * y).sqrt();
+                // Distance to the edge along the ray at `angle` from a vertex.
+                let edge = (0.5 * sector).cos() / (angle - 0.5 * sector).cos();
+                if r <= edge {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Aperture::Image(image) => image.transmission(x, y),
+        }
+    }
+}
+
+// One spherical interface of a lens prescription, in meters. A zero radius marks the
+// aperture stop; `eta` is the index of refraction on the film side, with 0 meaning air.
+#[derive(Clone, Copy)]
+pub struct LensElement {
+    pub radius: f32,
+    pub thickness: f32,
+    pub eta: f32,
+    pub aperture_radius: f32,
+}
+
+// Reads the pbrt lens format: one `radius thickness eta aperture-diameter` line per
+// interface in millimeters, ordered from the scene toward the film.
+pub fn parse_lens(text: &str) -> Result<Vec<LensElement>, LoadError> {
+    let mut elements = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let values: Vec<f32> = line
+            .split_whitespace()
+            .map(|t| t.parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| LoadError::Parse(format!("invalid lens line '{}'", line)))?;
+        if values.len() != 4 {
+            return Err(LoadError::Parse(format!("invalid lens line '{}'", line)));
+        }
+        elements.push(LensElement {
+            radius: 0.001 * values[0],
+            thickness: 0.001 * values[1],
+            eta: values[2],
+            aperture_radius: 0.0005 * values[3],
+        });
+    }
+    if elements.is_empty() {
+        return Err(LoadError::Parse("lens has no elements".to_string()));
+    }
+    Ok(elements)
+}
+
+pub fn load_lens<P: AsRef<Path>>(path: P) -> Result<Vec<LensElement>, LoadError> {
+    parse_lens(&fs::read_to_string(path)?)
+}
+
+fn refract(wi: Vec3, n: Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = n.dot(wi);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-eta * wi + (eta * cos_i - cos_t) * n)
+}
+
+// Camera that traces each ray through the real lens surfaces. Lens space follows pbrt:
+// the film sits at z = 0 and the elements lie along -z.
+pub struct RealisticCamera {
+    origin: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    elements: Vec<LensElement>,
+    stop: Aperture,
+    film_width: f32,
+    film_height: f32,
+    time0: f32,
+    time1: f32,
+}
+
+impl RealisticCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        mut elements: Vec<LensElement>,
+        film_diagonal: f32,
+        aspect_ratio: f32,
+        aperture_diameter: f32,
+        focus_dist: f32,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        for element in elements.iter_mut().filter(|e| e.radius == 0.0) {
+            element.aperture_radius = element.aperture_radius.min(0.0005 * aperture_diameter);
+        }
+        let diagonal = 0.001 * film_diagonal;
+        let film_height = diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        let mut camera = Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            elements,
+            stop: Aperture::Circular,
+            film_width: aspect_ratio * film_height,
+            film_height,
+            time0,
+            time1,
+        };
+        if let Some(thickness) = camera.focus_thick_lens(focus_dist) {
+            let last = camera.elements.len() - 1;
+            camera.elements[last].thickness = thickness;
+        }
+        camera
+    }
+
+    // Shapes the aperture stop, e.g. into a polygon of blades.
+    pub fn with_stop(mut self, stop: Aperture) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    fn front_z(&self) -> f32 {
+        self.elements.iter().map(|e| e.thickness).sum()
+    }
+
+    fn rear_z(&self) -> f32 {
+        self.elements[self.elements.len() - 1].thickness
+    }
+
+    // Intersects the interface centered at `z_center`, returning `t` and the normal
+    // facing against the ray.
+    fn intersect_element(radius: f32, z_center: f32, o: Vec3, d: Vec3) -> Option<(f32, Vec3)> {
+        let oc = o - Vec3::new(0.0, 0.0, z_center);
+        let a = d.dot(d);
+        let b = 2.0 * d.dot(oc);
+        let c = oc.dot(oc) - radius * radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let (t0, t1) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+        let closer = (d.z() > 0.0) ^ (radius < 0.0);
+        let t = if closer { t0.min(t1) } else { t0.max(t1) };
+        if t < 0.0 {
+            return None;
+        }
+        let n = (oc + t * d).normalized();
+        let n = if n.dot(d) > 0.0 { -1.0 * n } else { n };
+        Some((t, n))
+    }
+
+    // Refracts through the element at `index` after reaching its plane or surface.
+    fn pass(&self, index: usize, z: f32, o: Vec3, d: Vec3, eta: f32) -> Option<(Vec3, Vec3)> {
+        let element = &self.elements[index];
+        if element.radius == 0.0 {
+            let t = (z - o.z()) / d.z();
+            if t < 0.0 {
+                return None;
+            }
+            let p = o + t * d;
+            let r = element.aperture_radius;
+            if self.stop.transmission(p.x() / r, p.y() / r) < 0.5 {
+                return None;
+            }
+            return Some((p, d));
+        }
+        let (t, n) = Self::intersect_element(element.radius, z + element.radius, o, d)?;
+        let p = o + t * d;
+        if p.x() * p.x() + p.y() * p.y() > element.aperture_radius * element.aperture_radius {
+            return None;
+        }
+        let wt = refract(-1.0 * d.normalized(), n, eta)?;
+        Some((p, wt))
+    }
+
+    // Traces from the film (camera space, +z toward the scene) out the front element.
+    fn trace_from_film(&self, o: Vec3, d: Vec3) -> Option<(Vec3, Vec3)> {
+        let flip = |v: Vec3| Vec3::new(v.x(), v.y(), -v.z());
+        let (mut o, mut d) = (flip(o), flip(d));
+        let mut z = 0.0;
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            z -= element.thickness;
+            let eta_i = if element.eta != 0.0 { element.eta } else { 1.0 };
+            let eta_t = if i > 0 && self.elements[i - 1].eta != 0.0 {
+                self.elements[i - 1].eta
+            } else {
+                1.0
+            };
+            let (p, wt) = self.pass(i, z, o, d, eta_i / eta_t)?;
+            o = p;
+            d = wt;
+        }
+        Some((flip(o), flip(d)))
+    }
+
+    fn trace_from_scene(&self, o: Vec3, d: Vec3) -> Option<(Vec3, Vec3)> {
+        let flip = |v: Vec3| Vec3::new(v.x(), v.y(), -v.z());
+        let (mut o, mut d) = (flip(o), flip(d));
+        let mut z = -self.front_z();
+        for i in 0..self.elements.len() {
+            let eta_i = if i == 0 || self.elements[i - 1].eta == 0.0 {
+                1.0
+            } else {
+                self.elements[i - 1].eta
+            };
+            let eta_t = if self.elements[i].eta != 0.0 {
+                self.elements[i].eta
+            } else {
+                1.0
+            };
+            let (p, wt) = self.pass(i, z, o, d, eta_i / eta_t)?;
+            o = p;
+            d = wt;
+            z += self.elements[i].thickness;
+        }
+        Some((flip(o), flip(d)))
+    }
+
+    // Principal plane and focal point along z from a paraxial ray and its exit.
+    fn cardinal_points(o_in: Vec3, o_out: Vec3, d_out: Vec3) -> (f32, f32) {
+        let tf = -o_out.x() / d_out.x();
+        let tp = (o_in.x() - o_out.x()) / d_out.x();
+        (-(o_out + tp * d_out).z(), -(o_out + tf * d_out).z())
+    }
+
+    // Film-side thickness of the last element that brings `focus_dist` into focus, using
+    // the thick lens approximation.
+    fn focus_thick_lens(&self, focus_dist: f32) -> Option<f32> {
+        let x = 0.001
+            * (self.film_width * self.film_width + self.film_height * self.film_height).sqrt();
+        let scene_in = Vec3::new(x, 0.0, self.front_z() + 1.0);
+        let (o, d) = self.trace_from_scene(scene_in, Vec3::new(0.0, 0.0, -1.0))?;
+        let (pz0, fz0) = Self::cardinal_points(scene_in, o, d);
+        let film_in = Vec3::new(x, 0.0, self.rear_z() - 1.0);
+        let (o, d) = self.trace_from_film(film_in, Vec3::new(0.0, 0.0, 1.0))?;
+        let (pz1, _) = Self::cardinal_points(film_in, o, d);
+
+        let f = fz0 - pz0;
+        let z = -focus_dist;
+        let c = (pz1 - z - pz0) * (pz1 - z - 4.0 * f - pz0);
+        if c <= 0.0 {
+            return None;
+        }
+        let delta = 0.5 * (pz1 - z + pz0 - c.sqrt());
+        Some(self.rear_z() + delta)
+    }
+}
+
+impl CameraModel for RealisticCamera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        // The lens inverts the image, so the film point mirrors the image point.
+        let film = Vec3::new(
+            -(u - 0.5) * self.film_width,
+            -(v - 0.5) * self.film_height,
+            0.0,
+        );
+        let rear = &self.elements[self.elements.len() - 1];
+        let disk = Vec3::random_in_unit_disk();
+        let target = Vec3::new(
+            rear.aperture_radius * disk.x(),
+            rear.aperture_radius * disk.y(),
+            self.rear_z(),
+        );
+        let (o, d) = self.trace_from_film(film, target - film)?;
+
+        let to_world = |p: Vec3| p.x() * self.u + p.y() * self.v - p.z() * self.w;
+        Some(Ray::new(
+            self.origin + to_world(o),
+            to_world(d),
+            sample_time(self.time0, self.time1),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygon_samples_stay_inside_the_blades() {
+        let aperture = Aperture::Polygon {
+            blades: 5,
+            rotation: 18.0,
+        };
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample();
+            assert_eq!(aperture.transmission(0.999 * x, 0.999 * y), 1.0);
+        }
+        // Vertices sit on the unit circle and edge midpoints inside it.
+        let vertex = 18f32.to_radians();
+        assert_eq!(
+            aperture.transmission(0.99 * vertex.cos(), 0.99 * vertex.sin()),
+            1.0
+        );
+        let midpoint = (18.0 + 36.0f32).to_radians();
+        assert_eq!(
+            aperture.transmission(0.9 * midpoint.cos(), 0.9 * midpoint.sin()),
+            0.0
+        );
+    }
+
+    #[test]
+    fn lens_files_are_read_in_millimeters() {
+        let elements = parse_lens("# comment\n 20 3 1.5 10\n0 2 0 8 # stop\n").unwrap();
+        assert_eq!(elements.len(), 2);
+        assert!((elements[0].radius - 0.02).abs() < 1e-7);
+        assert!((elements[0].thickness - 0.003).abs() < 1e-7);
+        assert!((elements[0].aperture_radius - 0.005).abs() < 1e-7);
+        assert_eq!(elements[1].eta, 0.0);
+        assert!(parse_lens("20 3 1.5").is_err());
+        assert!(parse_lens("# nothing here\n").is_err());
+    }
+
+    #[test]
+    fn realistic_camera_focuses_at_the_focus_distance() {
+        let elements = parse_lens(include_str!("../lenses/dgauss.50mm.dat")).unwrap();
+        let camera = RealisticCamera::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            elements,
+            35.0,
+            1.0,
+            25.0,
+            2.0,
+            0.0,
+            0.0,
+        );
+        // Rays from the image center spread over the aperture but meet again on the axis
+        // 2 units out, and only there.
+        let miss = |ray: &Ray, depth: f32| {
+            let p = ray.at((-depth - ray.origin().z()) / ray.direction().z());
+            (p.x() * p.x() + p.y() * p.y()).sqrt()
+        };
+        let (mut rays, mut in_focus, mut beyond) = (0, 0.0, 0.0);
+        for _ in 0..200 {
+            if let Some(ray) = camera.ray(0.5, 0.5) {
+                in_focus += miss(&ray, 2.0);
+                beyond += miss(&ray, 4.0);
+                rays += 1;
+            }
+        }
+        assert!(rays > 50);
+        assert!(in_focus / (rays as f32) < 0.001);
+        assert!(5.0 * in_focus < beyond);
+    }
+}
@@ -1,60 +1,167 @@
 mod aabb;
+mod animation;
 mod bvh;
 mod camera;
+mod csg;
 mod cube;
+mod curve;
+mod displacement;
+mod exposure;
+mod hair;
+mod heightfield;
 mod hittable;
+mod lens;
 mod material;
+mod matrix;
 mod medium;
+mod mesh;
+mod mitsuba;
 mod moving_sphere;
+mod obj;
+mod ocean;
+mod pbrt;
 mod perlin;
+mod ply;
+mod projection;
+mod quad;
+mod quadric;
 mod ray;
 mod rect;
 mod rotate;
+mod sdf;
+mod shutter;
 mod sphere;
+mod stereo;
+mod stl;
+mod subdivision;
+#[cfg(test)]
+mod testing;
 mod texture;
+mod transform;
 mod translate;
+mod triangle;
+mod usd;
 mod vec3;
 mod world;
 
+use aabb::AABB;
+use animation::{Animation, CameraKey, CameraPath};
 use bvh::BVH;
-use camera::Camera;
+use camera::{fov_from_focal_length, vertical_fov, Camera, CameraModel, FovAxis};
+use csg::{Csg, CsgOp};
 use cube::Cube;
+use curve::{Curve, CurveShape};
+use displacement::Displacement;
+use exposure::Exposure;
+use hair::Hair;
+use heightfield::Heightfield;
 use hittable::Hittable;
-use image;
-use material::{Dielectric, DiffuseLight, Lambertian, Metal};
+use lens::{load_lens, Aperture, BokehImage, RealisticCamera};
+use material::{Dielectric, DiffuseLight, Lambertian, Metal, Scatter};
+use matrix::{Mat4, Quat};
 use medium::ConstantMedium;
+use mesh::TriangleMesh;
+use mitsuba::load_mitsuba;
 use moving_sphere::MovingSphere;
+use obj::load_obj_cage;
+use ocean::Ocean;
+use pbrt::load_pbrt;
+use perlin::Perlin;
+use ply::load_ply;
+use projection::{
+    CubemapCamera, EquirectangularCamera, FisheyeCamera, FisheyeMapping, OrthographicCamera,
+};
+use quad::{Planar, Quad};
+use quadric::{Capsule, Cone, Cylinder, Disk, Torus};
 use rand::Rng;
 use ray::Ray;
 use rayon::prelude::*;
 use rect::Rect;
 use rotate::{Axis, Rotate};
-use sphere::Sphere;
+use sdf::{Mandelbulb, Repeat, SdfBox, SdfCapsule, SdfShape, SdfSphere, SdfTorus, SmoothUnion};
+use shutter::Shutter;
+use sphere::{Sphere, SphereUv};
 use std::sync::Arc;
-use texture::{CheckerTexture, ConstantTexture, ImageTexture, NoiseTexture};
+use stereo::{OdsCamera, StereoCamera, StereoLayout};
+use stl::load_stl;
+use subdivision::{Boundary, SubdivisionMesh};
+use texture::{CheckerTexture, ConstantTexture, ImageTexture, NoiseTexture, VertexColorTexture};
+use transform::{AnimatedTransform, Keyframe, Transform};
 use translate::Translate;
+use usd::load_usda;
 use vec3::{Color, Point3, Vec3, VectorConst};
 use world::{HitableList, World};
 
-fn format_color(color: &Color, samples_per_pixel: u32) -> String {
-    format!(
-        "{} {} {}",
-        (255.999
-            * (color[0] / (samples_per_pixel as f32))
-                .sqrt()
-                .clamp(0.0, 0.999)) as u64,
-        (255.999
-            * (color[1] / (samples_per_pixel as f32))
-                .sqrt()
-                .clamp(0.0, 0.999)) as u64,
-        (255.999
-            * (color[2] / (samples_per_pixel as f32))
-                .sqrt()
-                .clamp(0.0, 0.999)) as u64
-    )
+fn format_color(rgb: [u8; 3]) -> String {
+    format!("{} {} {}", rgb[0], rgb[1], rgb[2])
+}
+
+// Returns summed radiance per pixel, top row first.
+fn render(
+    world: &dyn Hittable,
+    cam: &dyn CameraModel,
+    width: u64,
+    height: u64,
+    samples_per_pixel: u32,
+    max_depth: i32,
+) -> Vec<Color> {
+    (0..(width * height))
+        .into_par_iter()
+        .map(|cnt| {
+            let j = height - cnt / width - 1;
+            let i = cnt % width;
+            let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+            let mut rng = rand::thread_rng();
+            for _ in 0..samples_per_pixel {
+                let random_u: f32 = rng.gen();
+                let random_v: f32 = rng.gen();
+
+                let u = (i as f32 + random_u) / (width - 1) as f32;
+                let v = (j as f32 + random_v) / (height - 1) as f32;
+
+                if let Some(r) = cam.ray(u, v) {
+                    pixel_color += ray_color(&r, world, max_depth);
+                }
+            }
+
+            pixel_color
+        })
+        .collect()
+}
+
+#[allow(dead_code)]
+fn render_animation(
+    world: &dyn Hittable,
+    animation: &Animation,
+    width: u64,
+    samples_per_pixel: u32,
+    max_depth: i32,
+) -> std::io::Result<()> {
+    let height = (width as f32 / animation.aspect_ratio) as u64;
+    std::fs::create_dir_all(&animation.directory)?;
+    for frame in animation.frames() {
+        eprintln!("Rendering frame {}", frame.number);
+        let pixels = render(
+            world,
+            &frame.camera,
+            width,
+            height,
+            samples_per_pixel,
+            max_depth,
+        );
+        let bytes = pixels
+            .iter()
+            .flat_map(|c| animation.exposure.develop(c, samples_per_pixel))
+            .collect();
+        image::RgbImage::from_raw(width as u32, height as u32, bytes)
+            .expect("pixel buffer matches the frame size")
+            .save(&frame.path)
+            .map_err(std::io::Error::other)?;
+    }
+    Ok(())
 }
 
-fn ray_color(r: &Ray, world: &Box<dyn Hittable>, depth: i32) -> Color {
+fn ray_color(r: &Ray, world: &dyn Hittable, depth: i32) -> Color {
     if let Some(rec) = world.hit(r, 0.001, f32::INFINITY) {
         let emitted = rec.mat.emitted(rec.u, rec.v, rec.p);
         if depth > 0 {
@@ -145,7 +252,7 @@ fn random_scene() -> Box<dyn Hittable> {
     // Box::new(world)
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::vec_init_then_push)]
 fn two_spheres() -> Box<dyn Hittable> {
     let checker = CheckerTexture::new(
         ConstantTexture::new(Vec3::new(0.2, 0.3, 0.1)),
@@ -155,7 +262,7 @@ fn two_spheres() -> Box<dyn Hittable> {
     world.push(Box::new(Sphere::new(
         Point3::new(0.0, -10.0, 0.0),
         10.0,
-        Arc::new(Lambertian::new(checker.clone())),
+        Arc::new(Lambertian::new(checker)),
     )));
     world.push(Box::new(Sphere::new(
         Point3::new(0.0, 10.0, 0.0),
@@ -165,7 +272,7 @@ fn two_spheres() -> Box<dyn Hittable> {
     Box::new(world)
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::vec_init_then_push)]
 fn two_perlin_spheres() -> Box<dyn Hittable> {
     let pertext = NoiseTexture::new(4.0);
     let mut world = World::new();
@@ -199,6 +306,54 @@ fn earth() -> Box<dyn Hittable> {
 }
 
 #[allow(dead_code)]
+fn partial_spheres() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+
+    // A lamp: a bulb under an open hemispherical shade.
+    world.push(Box::new(
+        Sphere::new(
+            Point3::new(-1.5, 2.0, 0.0),
+            1.0,
+            Arc::new(Metal::new(Vec3::new(0.9, 0.8, 0.5), 0.3)),
+        )
+        .with_height_range(0.0, 1.0),
+    ));
+    world.push(Box::new(Sphere::new(
+        Point3::new(-1.5, 2.1, 0.0),
+        0.25,
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            20.0, 18.0, 14.0,
+        )))),
+    )));
+
+    // A globe with a wedge cut out, showing its inside.
+    let image = image::open("earthmap.png")
+        .expect("image not found")
+        .to_rgb8();
+    let (nx, ny) = image.dimensions();
+    let earth = Arc::new(Lambertian::new(ImageTexture::new(image.into_raw(), nx, ny)));
+    world.push(Box::new(
+        Sphere::new(Point3::new(1.5, 1.0, 0.0), 1.0, earth.clone()).with_phi_max(270.0),
+    ));
+
+    // A dome over everything, textured to be seen from below.
+    world.push(Box::new(
+        Sphere::new(Point3::new(0.0, 0.0, 0.0), 20.0, earth)
+            .with_height_range(0.0, 20.0)
+            .with_uv(SphereUv::Inside),
+    ));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code, clippy::vec_init_then_push)]
 fn simple_light() -> Box<dyn Hittable> {
     let noise = NoiseTexture::new(4.0);
     let mut world = World::new();
@@ -234,7 +389,7 @@ fn simple_light() -> Box<dyn Hittable> {
     Box::new(world)
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::vec_init_then_push)]
 fn cornell_box() -> Box<dyn Hittable> {
     let red = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
         0.65, 0.05, 0.05,
@@ -342,7 +497,7 @@ fn cornell_box() -> Box<dyn Hittable> {
     Box::new(BVH::new(world, 0.0, 1.0))
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::vec_init_then_push)]
 fn cornell_smoke() -> Box<dyn Hittable> {
     let red = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
         0.65, 0.05, 0.05,
@@ -477,6 +632,1138 @@ fn cornell_smoke() -> Box<dyn Hittable> {
     Box::new(BVH::new(world, 0.0, 1.0))
 }
 
+#[allow(dead_code)]
+fn scanned_meshes() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, 10.0, 0.0),
+        3.0,
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            8.0, 8.0, 8.0,
+        )))),
+    )));
+
+    let scan = load_ply("bunny.ply").expect("failed to load bunny.ply");
+    let scan_mat = Arc::new(Lambertian::new(VertexColorTexture::new(
+        ConstantTexture::new(Vec3::new(0.8, 0.8, 0.8)),
+    )));
+    world.push(Box::new(BVH::new(scan.triangles(scan_mat), 0.0, 1.0)));
+
+    let part = load_stl("part.stl").expect("failed to load part.stl");
+    let part_mat = Arc::new(Metal::new(Vec3::new(0.8, 0.8, 0.9), 0.1));
+    world.push(Box::new(BVH::new(part.triangles(part_mat), 0.0, 1.0)));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn instanced_shapes() -> Box<dyn Hittable> {
+    let mut rng = rand::thread_rng();
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+
+    // Every instance shares the same prototype BVH.
+    let mat: Arc<dyn Scatter> = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.7, 0.3, 0.2,
+    ))));
+    let prototype: Arc<dyn Hittable> =
+        Arc::new(BVH::new(TriangleMesh::cube().triangles(mat), 0.0, 1.0));
+    for i in -5..5 {
+        for j in -5..5 {
+            let axis = Vec3::random(-1.0..1.0);
+            let matrix = Mat4::translate(Vec3::new(2.5 * i as f32, 1.0, 2.5 * j as f32))
+                * Mat4::rotate(rng.gen_range(0.0..360.0), axis)
+                * Mat4::shear(rng.gen_range(-0.3..0.3), 0.0, 0.0, 0.0, 0.0, 0.0)
+                * Mat4::scale(Vec3::new(0.5, rng.gen_range(0.3..0.8), 0.5));
+            world.push(Box::new(Transform::new(prototype.clone(), matrix)));
+        }
+    }
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn quadric_shapes() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+
+    let checker: Arc<dyn Scatter> = Arc::new(Lambertian::new(CheckerTexture::new(
+        ConstantTexture::new(Vec3::new(0.2, 0.3, 0.1)),
+        ConstantTexture::new(Vec3::new(0.9, 0.9, 0.9)),
+    )));
+    let copper: Arc<dyn Scatter> = Arc::new(Metal::new(Vec3::new(0.8, 0.5, 0.3), 0.1));
+
+    // A cut-away can with a lid, a lampshade frustum and a tilted ring.
+    world.push(Box::new(
+        Cylinder::new(Point3::new(-3.0, 0.0, 0.0), 0.8, 0.0, 1.5, checker.clone())
+            .with_phi_max(270.0),
+    ));
+    world.push(Box::new(Disk::new(
+        Point3::new(-3.0, 1.5, 0.0),
+        0.8,
+        copper.clone(),
+    )));
+    world.push(Box::new(
+        Cone::new(Point3::new(-1.0, 0.0, 0.0), 0.8, 2.0, checker.clone())
+            .with_height_range(0.0, 1.2)
+            .with_phi_max(300.0),
+    ));
+    world.push(Box::new(
+        Disk::new(Point3::new(-1.0, 0.01, 1.5), 0.6, checker.clone())
+            .with_inner_radius(0.3)
+            .with_phi_max(300.0),
+    ));
+    world.push(Box::new(Transform::new(
+        Arc::new(
+            Torus::new(Point3::new(0.0, 0.0, 0.0), 0.7, 0.25, copper.clone()).with_phi_max(320.0),
+        ),
+        Mat4::translate(Vec3::new(1.2, 1.0, 0.0)) * Mat4::rotate(70.0, Vec3::new(1.0, 0.0, 0.0)),
+    )));
+    world.push(Box::new(Capsule::new(
+        Point3::new(2.8, 0.3, -0.5),
+        Point3::new(3.4, 1.6, 0.5),
+        0.3,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.push(Box::new(Disk::new(
+        Point3::new(0.0, 6.0, 2.0),
+        2.5,
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            4.0, 4.0, 4.0,
+        )))),
+    )));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn planar_shapes() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    let white: Arc<dyn Scatter> = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.73, 0.73, 0.73,
+    ))));
+    let red: Arc<dyn Scatter> = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.65, 0.05, 0.05,
+    ))));
+    let checker: Arc<dyn Scatter> = Arc::new(Lambertian::new(CheckerTexture::new(
+        ConstantTexture::new(Vec3::new(0.1, 0.2, 0.5)),
+        ConstantTexture::new(Vec3::new(0.9, 0.9, 0.9)),
+    )));
+
+    // Floor and a back wall leaning away from the camera.
+    world.push(Box::new(Quad::new(
+        Point3::new(-5.0, 0.0, 5.0),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -10.0),
+        white.clone(),
+    )));
+    world.push(Box::new(Quad::new(
+        Point3::new(-5.0, 0.0, -5.0),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 6.0, -2.0),
+        white,
+    )));
+
+    world.push(Box::new(Quad::triangle(
+        Point3::new(-3.0, 0.0, -1.0),
+        Vec3::new(2.0, 0.0, 0.5),
+        Vec3::new(0.5, 2.5, 0.0),
+        red,
+    )));
+    world.push(Box::new(Quad::ellipse(
+        Point3::new(1.5, 1.3, -1.0),
+        Vec3::new(1.2, 0.0, 0.6),
+        Vec3::new(0.0, 1.0, 0.2),
+        checker.clone(),
+    )));
+    world.push(Box::new(Quad::with_shape(
+        Planar::Parallelogram,
+        Point3::new(-0.6, 0.0, 0.5),
+        Vec3::new(1.2, 0.0, 0.3),
+        Vec3::new(0.4, 1.0, -0.4),
+        checker,
+    )));
+
+    // Tilted area lights, with no `Rotate` needed.
+    world.push(Box::new(Quad::new(
+        Point3::new(-4.0, 4.0, 2.0),
+        Vec3::new(2.0, -0.5, 0.0),
+        Vec3::new(0.0, 0.3, -1.0),
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            5.0, 4.5, 4.0,
+        )))),
+    )));
+    world.push(Box::new(Quad::ellipse(
+        Point3::new(3.0, 4.0, 1.0),
+        Vec3::new(0.6, 0.3, 0.0),
+        Vec3::new(0.0, 0.0, 0.4),
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            12.0, 13.5, 15.0,
+        )))),
+    )));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn csg_parts() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+
+    let steel: Arc<dyn Scatter> = Arc::new(Metal::new(Vec3::new(0.7, 0.7, 0.75), 0.2));
+    let paint: Arc<dyn Scatter> = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.8, 0.4, 0.1,
+    ))));
+
+    // The classic CSG part: a box rounded by a sphere and drilled through on all three
+    // axes.
+    let rounded: Arc<dyn Hittable> = Arc::new(Csg::intersection(
+        Arc::new(Cube::new(
+            Point3::new(-2.0, 0.0, -1.0),
+            Point3::new(0.0, 2.0, 1.0),
+            steel.clone(),
+        )),
+        Arc::new(Sphere::new(
+            Point3::new(-1.0, 1.0, 0.0),
+            1.35,
+            steel.clone(),
+        )),
+    ));
+    let drill = |from: Point3, to: Point3| -> Arc<dyn Hittable> {
+        Arc::new(Capsule::new(from, to, 0.5, paint.clone()))
+    };
+    let holes: Arc<dyn Hittable> = Arc::new(Csg::union(
+        Arc::new(Csg::union(
+            drill(Point3::new(-3.0, 1.0, 0.0), Point3::new(1.0, 1.0, 0.0)),
+            drill(Point3::new(-1.0, -1.0, 0.0), Point3::new(-1.0, 3.0, 0.0)),
+        )),
+        drill(Point3::new(-1.0, 1.0, -2.0), Point3::new(-1.0, 1.0, 2.0)),
+    ));
+    world.push(Box::new(Csg::difference(rounded, holes)));
+
+    // A glass lens: the overlap of two spheres.
+    world.push(Box::new(Csg::new(
+        CsgOp::Intersection,
+        Arc::new(Sphere::new(
+            Point3::new(1.5, 1.0, -1.2),
+            1.5,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Arc::new(Sphere::new(
+            Point3::new(1.5, 1.0, 1.2),
+            1.5,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+    )));
+
+    world.push(Box::new(Quad::new(
+        Point3::new(-3.0, 6.0, -2.0),
+        Vec3::new(6.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            3.0, 3.0, 3.0,
+        )))),
+    )));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn sdf_shapes() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+
+    let clay: Arc<dyn Scatter> = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.8, 0.5, 0.3,
+    ))));
+    let gold: Arc<dyn Scatter> = Arc::new(Metal::new(Vec3::new(0.9, 0.7, 0.3), 0.15));
+
+    world.push(Box::new(SdfShape::new(
+        Arc::new(SdfBox::new(
+            Point3::new(-3.0, 0.8, 0.0),
+            Vec3::new(0.8, 0.8, 0.8),
+            0.2,
+        )),
+        AABB::new(Point3::new(-3.8, 0.0, -0.8), Point3::new(-2.2, 1.6, 0.8)),
+        clay.clone(),
+    )));
+
+    // A torus and a capsule melted together.
+    world.push(Box::new(SdfShape::new(
+        Arc::new(SmoothUnion::new(
+            Arc::new(SdfTorus::new(Point3::new(-0.5, 0.3, 0.0), 0.7, 0.25)),
+            Arc::new(SdfCapsule::new(
+                Point3::new(-0.5, 0.3, 0.0),
+                Point3::new(-0.5, 1.8, 0.0),
+                0.2,
+            )),
+            0.3,
+        )),
+        AABB::new(Point3::new(-1.6, 0.0, -1.1), Point3::new(0.6, 2.1, 1.1)),
+        gold.clone(),
+    )));
+
+    // A field of spheres from a single one, limited to a patch of the floor.
+    world.push(Box::new(SdfShape::new(
+        Arc::new(Repeat::new(
+            Arc::new(SdfSphere::new(Point3::new(0.0, 0.15, 0.0), 0.15)),
+            Vec3::new(0.5, 0.0, 0.5),
+        )),
+        AABB::new(Point3::new(-4.0, 0.0, 1.25), Point3::new(4.0, 0.3, 3.25)),
+        clay,
+    )));
+
+    world.push(Box::new(
+        SdfShape::new(
+            Arc::new(Mandelbulb::new(Point3::new(2.5, 1.2, 0.0), 1.0, 8.0, 8)),
+            AABB::new(Point3::new(1.3, 0.0, -1.2), Point3::new(3.7, 2.4, 1.2)),
+            gold,
+        )
+        .with_epsilon(0.001)
+        .with_step_scale(0.8, 512),
+    ));
+
+    world.push(Box::new(Quad::new(
+        Point3::new(-3.0, 6.0, -2.0),
+        Vec3::new(6.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            3.0, 3.0, 3.0,
+        )))),
+    )));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+// Hilly terrain from a grayscale heightmap, or from turbulence when none is given, with
+// a lake filling the valleys.
+#[allow(dead_code)]
+fn terrain(heightmap: Option<&str>) -> Box<dyn Hittable> {
+    let mut world = World::new();
+    let grass: Arc<dyn Scatter> = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.35, 0.45, 0.2,
+    ))));
+    let corner = Point3::new(-50.0, 0.0, -50.0);
+    let size = Vec3::new(100.0, 30.0, 100.0);
+    let ground = match heightmap {
+        Some(path) => Heightfield::load(path, corner, size, grass)
+            .unwrap_or_else(|err| panic!("failed to load {}: {}", path, err)),
+        None => {
+            let perlin = Perlin::new();
+            Heightfield::from_fn(
+                |u, v| 0.5 * perlin.turb(Vec3::new(6.0 * u, 0.0, 6.0 * v), 7),
+                512,
+                512,
+                corner,
+                size,
+                grass,
+            )
+        }
+    };
+    world.push(Box::new(ground));
+
+    world.push(Box::new(Quad::new(
+        Point3::new(-50.0, 2.0, 50.0),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -100.0),
+        Arc::new(Metal::new(Vec3::new(0.3, 0.4, 0.5), 0.05)),
+    )));
+    // An overcast sky.
+    world.push(Box::new(
+        Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            500.0,
+            Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+                0.8, 0.9, 1.0,
+            )))),
+        )
+        .with_height_range(0.0, 500.0),
+    ));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn ocean(time: f32) -> Box<dyn Hittable> {
+    let mut world = World::new();
+    // The same seed at every time, so frames of an animation line up.
+    let sea = Ocean::new(128, 64.0, 12.0, Vec3::new(1.0, 0.0, 0.6), 2.0e-5, 7)
+        .with_choppiness(0.8)
+        .with_loop(20.0);
+    let water: Arc<dyn Scatter> = Arc::new(Dielectric::new(1.33));
+    world.push(Box::new(BVH::new(
+        sea.mesh(time, 3).triangles(water),
+        0.0,
+        1.0,
+    )));
+
+    world.push(Box::new(Quad::new(
+        Point3::new(-96.0, -6.0, 96.0),
+        Vec3::new(192.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -192.0),
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.05, 0.15, 0.2,
+        )))),
+    )));
+    world.push(Box::new(
+        Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            500.0,
+            Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+                0.8, 0.9, 1.0,
+            )))),
+        )
+        .with_height_range(0.0, 500.0),
+    ));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn subdivision_surfaces(cage: Option<&str>) -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+    world.push(Box::new(Quad::new(
+        Point3::new(-6.0, 8.0, -4.0),
+        Vec3::new(12.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 8.0),
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            3.0, 3.0, 3.0,
+        )))),
+    )));
+
+    let mat: Arc<dyn Scatter> = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.7, 0.3, 0.2,
+    ))));
+    let cube = |center: Point3| {
+        let positions = (0..8)
+            .map(|i| {
+                let corner = Vec3::new(
+                    if i & 1 == 0 { -1.0 } else { 1.0 },
+                    if i & 2 == 0 { -1.0 } else { 1.0 },
+                    if i & 4 == 0 { -1.0 } else { 1.0 },
+                );
+                center + 0.8 * corner
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+        ];
+        SubdivisionMesh::new(positions, faces).unwrap()
+    };
+    let mut add = |mesh: TriangleMesh| {
+        world.push(Box::new(BVH::new(mesh.triangles(mat.clone()), 0.0, 1.0)));
+    };
+
+    // A plain cube rounds into a blob, sharp edges around the top keep a crisp rim, and
+    // semi-sharp edges give a rounded box.
+    add(cube(Point3::new(-4.5, 0.8, 0.0)).catmull_clark(4));
+    let mut rim = cube(Point3::new(-1.5, 0.8, 0.0));
+    for (a, b) in [(2, 6), (6, 7), (7, 3), (3, 2)] {
+        rim = rim.with_crease(a, b, f32::INFINITY);
+    }
+    add(rim.catmull_clark(4));
+    let mut rounded = cube(Point3::new(1.5, 0.8, 0.0));
+    for (a, b) in [
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (0, 2),
+        (1, 3),
+        (4, 6),
+        (5, 7),
+    ] {
+        rounded = rounded.with_crease(a, b, 2.0);
+    }
+    for (a, b) in [(0, 4), (1, 5), (2, 6), (3, 7)] {
+        rounded = rounded.with_crease(a, b, 2.0);
+    }
+    add(rounded.catmull_clark(4));
+
+    // An octahedron with a sharp equator becomes a spinning top.
+    let c = Point3::new(4.5, 1.0, 0.0);
+    let octahedron = SubdivisionMesh::new(
+        vec![
+            c + Vec3::new(1.0, 0.0, 0.0),
+            c + Vec3::new(0.0, 0.0, -1.0),
+            c + Vec3::new(-1.0, 0.0, 0.0),
+            c + Vec3::new(0.0, 0.0, 1.0),
+            c + Vec3::new(0.0, 1.0, 0.0),
+            c + Vec3::new(0.0, -1.0, 0.0),
+        ],
+        vec![
+            vec![0, 1, 4],
+            vec![1, 2, 4],
+            vec![2, 3, 4],
+            vec![3, 0, 4],
+            vec![1, 0, 5],
+            vec![2, 1, 5],
+            vec![3, 2, 5],
+            vec![0, 3, 5],
+        ],
+    )
+    .unwrap()
+    .with_crease(0, 1, f32::INFINITY)
+    .with_crease(1, 2, f32::INFINITY)
+    .with_crease(2, 3, f32::INFINITY)
+    .with_crease(3, 0, f32::INFINITY)
+    .with_corner(4);
+    add(octahedron.loop_subdivision(4));
+
+    // An open sheet whose boundary corners stay pinned while the middle bulges up.
+    let sheet = SubdivisionMesh::new(
+        (0..9)
+            .map(|i| {
+                let (x, z) = ((i % 3) as f32 - 1.0, (i / 3) as f32 - 1.0);
+                let y = if i == 4 { 1.5 } else { 0.1 };
+                Point3::new(1.2 * x, y, 1.2 * z + 3.0)
+            })
+            .collect(),
+        vec![
+            vec![0, 3, 4, 1],
+            vec![1, 4, 5, 2],
+            vec![3, 6, 7, 4],
+            vec![4, 7, 8, 5],
+        ],
+    )
+    .unwrap()
+    .with_boundary(Boundary::EdgesAndCorners);
+    add(sheet.loop_subdivision(4));
+
+    if let Some(path) = cage {
+        let cage =
+            load_obj_cage(path).unwrap_or_else(|err| panic!("failed to load {}: {}", path, err));
+        add(cage.catmull_clark(3));
+    }
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn displaced_shapes() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+    world.push(Box::new(Quad::new(
+        Point3::new(-5.0, 6.0, -3.0),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 6.0),
+        Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+            4.0, 4.0, 4.0,
+        )))),
+    )));
+
+    // A banded rock: a smoothed cube pushed in and out by marble noise, set down so its
+    // lowest possible point touches the ground.
+    let blob = SubdivisionMesh::new(
+        (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 { -1.0 } else { 1.0 },
+                    if i & 2 == 0 { -0.7 } else { 0.7 },
+                    if i & 4 == 0 { -1.0 } else { 1.0 },
+                )
+            })
+            .collect(),
+        vec![
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+        ],
+    )
+    .unwrap()
+    .catmull_clark(2);
+    let strata = Displacement::new(Arc::new(NoiseTexture::new(6.0)), 0.15, 0.03).with_midlevel(0.5);
+    let lowest = strata.bounds(&blob).unwrap().min().y();
+    let mut rock = strata.apply(&blob);
+    rock.transform(&Mat4::translate(Vec3::new(-1.6, -lowest, 0.0)));
+    world.push(Box::new(BVH::new(
+        rock.triangles(Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.6, 0.5, 0.4,
+        ))))),
+        0.0,
+        1.0,
+    )));
+
+    // A relief map: the earth texture raised by its own brightness.
+    let image = image::open("earthmap.png")
+        .expect("image not found")
+        .to_rgb8();
+    let (nx, ny) = image.dimensions();
+    let earth = Arc::new(ImageTexture::new(image.into_raw(), nx, ny));
+    let mut map = TriangleMesh::rectangle();
+    map.transform(
+        &(Mat4::translate(Vec3::new(1.6, 0.05, 0.0))
+            * Mat4::rotate(-90.0, Vec3::new(1.0, 0.0, 0.0))
+            * Mat4::scale(Vec3::new(1.4, 0.7, 1.0))),
+    );
+    let relief = Displacement::new(earth.clone(), 0.3, 0.02).apply(&map);
+    world.push(Box::new(BVH::new(
+        relief.triangles(Arc::new(Lambertian::new(earth))),
+        0.0,
+        1.0,
+    )));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn fur_and_grass() -> Box<dyn Hittable> {
+    let mut rng = rand::thread_rng();
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.25, 0.18, 0.1,
+        )))),
+    )));
+    world.push(Box::new(
+        Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            50.0,
+            Arc::new(DiffuseLight::new(ConstantTexture::new(Vec3::new(
+                0.9, 0.95, 1.0,
+            )))),
+        )
+        .with_height_range(0.0, 50.0),
+    ));
+
+    // Grass blades as ribbons that taper to a point and bend the way they face.
+    let grass: Arc<dyn Scatter> = Arc::new(Hair::from_color(Color::new(0.25, 0.55, 0.1), 0.4));
+    let mut blades = World::new();
+    for _ in 0..3000 {
+        let base = Point3::new(rng.gen_range(-3.5..-0.5), 0.0, rng.gen_range(-1.5..1.5));
+        let height = rng.gen_range(0.3..0.7);
+        let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+        let lean = Vec3::new(angle.cos(), 0.0, angle.sin());
+        let bend = rng.gen_range(0.1..0.5) * height;
+        let points = [
+            base,
+            base + Vec3::new(0.0, height / 3.0, 0.0),
+            base + Vec3::new(0.0, 2.0 * height / 3.0, 0.0) + 0.3 * bend * lean,
+            base + Vec3::new(0.0, height, 0.0) + bend * lean,
+        ];
+        let tip_normal = lean + Vec3::new(0.0, bend / height, 0.0);
+        let shape = CurveShape::Ribbon(lean, tip_normal);
+        blades.extend(Curve::segments(points, 0.03, 0.0, shape, 2, grass.clone()));
+    }
+    world.push(Box::new(BVH::new(blades, 0.0, 1.0)));
+
+    // A ball of brown fur that droops a little under its own weight.
+    let center = Point3::new(1.5, 0.6, 0.0);
+    let radius = 0.45;
+    world.push(Box::new(Sphere::new(
+        center,
+        radius,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.2, 0.1, 0.05,
+        )))),
+    )));
+    let fur: Arc<dyn Scatter> = Arc::new(
+        Hair::from_melanin(1.0, 0.4)
+            .with_roughness(0.25, 0.3)
+            .with_scale_tilt(3.0)
+            .with_ior(1.55),
+    );
+    let mut hairs = World::new();
+    for _ in 0..6000 {
+        let n = Vec3::random_in_unit_sphere().normalized();
+        let root = center + radius * n;
+        let length = rng.gen_range(0.2..0.3);
+        let droop = Vec3::new(0.0, -0.3 * length, 0.0);
+        let points = [
+            root,
+            root + length / 3.0 * n,
+            root + 2.0 * length / 3.0 * n + 0.4 * droop + 0.03 * Vec3::random(-1.0..1.0),
+            root + length * n + droop + 0.05 * Vec3::random(-1.0..1.0),
+        ];
+        hairs.extend(Curve::segments(
+            points,
+            0.006,
+            0.002,
+            CurveShape::Tube,
+            2,
+            fur.clone(),
+        ));
+    }
+    world.push(Box::new(BVH::new(hairs, 0.0, 1.0)));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn animated_shapes() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+
+    let mat: Arc<dyn Scatter> = Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.2));
+    let prototype: Arc<dyn Hittable> =
+        Arc::new(BVH::new(TriangleMesh::cube().triangles(mat), 0.0, 1.0));
+
+    // A cube that tumbles across the frame while squashing, blurred over the shutter.
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    world.push(Box::new(AnimatedTransform::new(
+        prototype.clone(),
+        vec![
+            Keyframe::new(
+                0.0,
+                Vec3::new(-1.5, 1.0, 0.0),
+                Quat::IDENTITY,
+                Vec3::new(0.5, 0.5, 0.5),
+            ),
+            Keyframe::new(
+                0.5,
+                Vec3::new(0.0, 1.5, 0.0),
+                Quat::from_axis_angle(60.0, Vec3::new(1.0, 1.0, 0.0)),
+                Vec3::new(0.6, 0.4, 0.6),
+            ),
+            Keyframe::new(
+                1.0,
+                Vec3::new(1.5, 1.0, 0.0),
+                Quat::from_axis_angle(120.0, up),
+                Vec3::new(0.5, 0.5, 0.5),
+            ),
+        ],
+    )));
+    world.push(Box::new(AnimatedTransform::new(
+        prototype,
+        vec![
+            Keyframe::new(
+                0.0,
+                Vec3::new(0.0, 0.5, -2.0),
+                Quat::IDENTITY,
+                Vec3::ONE * 0.5,
+            ),
+            Keyframe::new(
+                1.0,
+                Vec3::new(0.0, 0.5, -2.0),
+                Quat::from_axis_angle(90.0, up),
+                Vec3::ONE * 0.5,
+            ),
+        ],
+    )));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn deforming_mesh() -> Box<dyn Hittable> {
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+
+    // A flag whose travelling wave advances during the shutter interval.
+    let n = 32;
+    let wave = |phase: f32| -> Vec<Point3> {
+        let mut positions = Vec::new();
+        for j in 0..=n {
+            for i in 0..=n {
+                let x = 3.0 * i as f32 / n as f32;
+                let y = 0.5 + 2.0 * j as f32 / n as f32;
+                let z = 0.25 * x * (3.0 * x - phase).sin();
+                positions.push(Point3::new(x - 1.5, y, z));
+            }
+        }
+        positions
+    };
+    let mut indices = Vec::new();
+    for j in 0..n {
+        for i in 0..n {
+            let k = j * (n + 1) + i;
+            indices.push([k, k + 1, k + n + 2]);
+            indices.push([k, k + n + 2, k + n + 1]);
+        }
+    }
+    let mut flag = TriangleMesh::new(wave(0.0), indices);
+    flag.set_motion(vec![(0.0, wave(0.0)), (0.5, wave(0.6)), (1.0, wave(1.5))])
+        .expect("flag samples share one topology");
+    let mat = Arc::new(Lambertian::new(CheckerTexture::new(
+        ConstantTexture::new(Vec3::new(0.8, 0.1, 0.1)),
+        ConstantTexture::new(Vec3::new(0.9, 0.9, 0.9)),
+    )));
+    world.push(Box::new(BVH::new(flag.triangles(mat), 0.0, 1.0)));
+
+    Box::new(BVH::new(world, 0.0, 1.0))
+}
+
+#[allow(dead_code)]
+fn turntable_animation() {
+    let world = scanned_meshes();
+    let mut animation = Animation::new(
+        CameraPath::turntable(Point3::new(0.0, 0.5, 0.0), 4.0, 1.5, (0, 119), 40.0),
+        0,
+        119,
+        16.0 / 9.0,
+    );
+    animation.prefix = "turntable_".to_string();
+    animation.skip_existing = true;
+    render_animation(world.as_ref(), &animation, 640, 64, 50).expect("failed to write frames");
+}
+
+#[allow(dead_code)]
+fn flythrough_animation() {
+    let key = |frame: f32, lookfrom: Point3, vfov: f32, aperture: f32| CameraKey {
+        frame,
+        lookfrom,
+        lookat: Point3::new(0.0, 1.0, 0.0),
+        vfov,
+        focus_dist: (lookfrom - Point3::new(0.0, 1.0, 0.0)).length(),
+        aperture,
+    };
+    let mut animation = Animation::new(
+        CameraPath::new(
+            vec![
+                key(0.0, Point3::new(0.0, 2.0, 12.0), 30.0, 0.0),
+                key(24.0, Point3::new(6.0, 3.0, 6.0), 40.0, 0.1),
+                key(48.0, Point3::new(8.0, 1.0, -2.0), 50.0, 0.2),
+            ],
+            Vec3::new(0.0, 1.0, 0.0),
+        ),
+        0,
+        48,
+        16.0 / 9.0,
+    );
+    animation.prefix = "flythrough_".to_string();
+
+    // The cube spins once over the two seconds of animation, in scene time.
+    let (time0, time1) = animation.duration();
+    let mut world = World::new();
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+            0.5, 0.5, 0.5,
+        )))),
+    )));
+    let mat: Arc<dyn Scatter> = Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.2));
+    let cube: Arc<dyn Hittable> =
+        Arc::new(BVH::new(TriangleMesh::cube().triangles(mat), time0, time1));
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let keyframes = (0..=4)
+        .map(|i| {
+            Keyframe::new(
+                animation.time(12 * i),
+                Vec3::new(0.0, 1.0, 0.0),
+                Quat::from_axis_angle(90.0 * i as f32, up),
+                Vec3::ONE,
+            )
+        })
+        .collect();
+    world.push(Box::new(AnimatedTransform::new(cube, keyframes)));
+    let world = BVH::new(world, time0, time1);
+
+    render_animation(&world, &animation, 640, 64, 50).expect("failed to write frames");
+}
+
+// A 50mm lens on a full-frame sensor at f/2.8, 1/60 s and ISO 400, with the scene in
+// meters and lit by a 3200 K tungsten source, pushed one stop brighter.
+#[allow(dead_code)]
+fn photographic_camera(lookfrom: Point3, lookat: Point3, aspect_ratio: f32) -> (Camera, Exposure) {
+    let exposure = Exposure::photographic(400.0, 1.0 / 60.0, 2.8)
+        .with_compensation(1.0)
+        .with_white_balance(3200.0)
+        .with_noise(1.0);
+    let (time0, time1) = exposure.shutter_interval(0.0);
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        Vec3::new(0.0, 1.0, 0.0),
+        27.0,
+        aspect_ratio,
+        exposure.lens_aperture(0.05),
+        (lookat - lookfrom).length(),
+        time0,
+        time1,
+    )
+    .with_shutter(Shutter::trapezoid(0.2, 0.2));
+    (camera, exposure)
+}
+
+// Alternative projections of the same view, selected by name.
+#[allow(dead_code)]
+fn camera_model(
+    name: &str,
+    lookfrom: Point3,
+    lookat: Point3,
+    aspect_ratio: f32,
+) -> Box<dyn CameraModel> {
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    match name {
+        "orthographic" => Box::new(OrthographicCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            (lookat - lookfrom).length() * 0.75,
+            aspect_ratio,
+            0.0,
+            1.0,
+        )),
+        "fisheye" => Box::new(FisheyeCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            180.0,
+            aspect_ratio,
+            FisheyeMapping::Equidistant,
+            0.0,
+            1.0,
+        )),
+        "equisolid" => Box::new(FisheyeCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            180.0,
+            aspect_ratio,
+            FisheyeMapping::Equisolid,
+            0.0,
+            1.0,
+        )),
+        "equirectangular" => Box::new(EquirectangularCamera::new(lookfrom, lookat, vup, 0.0, 1.0)),
+        "cubemap" => Box::new(CubemapCamera::new(lookfrom, lookat, vup, 0.0, 1.0)),
+        // A 24mm full-frame architectural lens: level camera, frame raised by lens shift
+        // and the focal plane tipped toward the ground.
+        "tilt-shift" => Box::new(
+            Camera::new(
+                lookfrom,
+                Point3::new(lookat.x(), lookfrom.y(), lookat.z()),
+                vup,
+                vertical_fov(
+                    fov_from_focal_length(24.0, 36.0),
+                    FovAxis::Horizontal,
+                    aspect_ratio,
+                ),
+                aspect_ratio,
+                0.2,
+                (lookat - lookfrom).length(),
+                0.0,
+                1.0,
+            )
+            .with_shift(0.0, 0.2)
+            .with_tilt(-8.0, 0.0),
+        ),
+        // Phone-style footage: a CMOS sensor read out over most of the frame time, with
+        // an electronic shutter that opens gradually and cuts off sharply.
+        "rolling-shutter" => Box::new(
+            Camera::new(
+                lookfrom,
+                lookat,
+                vup,
+                40.0,
+                aspect_ratio,
+                0.0,
+                (lookat - lookfrom).length(),
+                0.0,
+                1.0,
+            )
+            .with_shutter(Shutter::custom(&[0.25, 0.5, 0.75, 1.0, 1.0, 1.0]).rolling(0.8)),
+        ),
+        "hexagonal-bokeh" => Box::new(
+            Camera::new(
+                lookfrom,
+                lookat,
+                vup,
+                40.0,
+                aspect_ratio,
+                0.5,
+                (lookat - lookfrom).length(),
+                0.0,
+                1.0,
+            )
+            .with_aperture(Aperture::Polygon {
+                blades: 6,
+                rotation: 15.0,
+            })
+            .with_cat_eye(0.6),
+        ),
+        "image-bokeh" => Box::new(
+            Camera::new(
+                lookfrom,
+                lookat,
+                vup,
+                40.0,
+                aspect_ratio,
+                0.5,
+                (lookat - lookfrom).length(),
+                0.0,
+                1.0,
+            )
+            .with_aperture(Aperture::Image(Arc::new(
+                BokehImage::load("bokeh.png").expect("failed to load bokeh.png"),
+            ))),
+        ),
+        "double-gauss" => Box::new(
+            RealisticCamera::new(
+                lookfrom,
+                lookat,
+                vup,
+                load_lens("lenses/dgauss.50mm.dat").expect("failed to load lens"),
+                35.0,
+                aspect_ratio,
+                25.0,
+                (lookat - lookfrom).length(),
+                0.0,
+                1.0,
+            )
+            .with_stop(Aperture::Polygon {
+                blades: 7,
+                rotation: 0.0,
+            }),
+        ),
+        // Stereo rigs with a 6.4cm eye separation, assuming scene units of meters.
+        "side-by-side" => Box::new(StereoCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            40.0,
+            0.5 * aspect_ratio,
+            0.0,
+            1.0,
+            0.064,
+            (lookat - lookfrom).length(),
+            StereoLayout::SideBySide,
+            0.0,
+            1.0,
+        )),
+        "over-under" => Box::new(StereoCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            40.0,
+            2.0 * aspect_ratio,
+            0.0,
+            1.0,
+            0.064,
+            f32::INFINITY,
+            StereoLayout::OverUnder,
+            0.0,
+            1.0,
+        )),
+        "ods" => Box::new(OdsCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            0.064,
+            f32::INFINITY,
+            StereoLayout::OverUnder,
+            0.0,
+            1.0,
+        )),
+        _ => Box::new(Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            40.0,
+            aspect_ratio,
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+        )),
+    }
+}
+
+#[allow(dead_code)]
+fn pbrt_scene(path: &str) -> (Box<dyn Hittable>, Camera) {
+    let scene = load_pbrt(path).expect("failed to load pbrt scene");
+    for warning in &scene.warnings {
+        eprintln!("pbrt: {}", warning);
+    }
+    eprintln!(
+        "pbrt: scene requests {}x{} at {} spp, output '{}'",
+        scene.width,
+        scene.height,
+        scene.samples_per_pixel,
+        scene.filename.as_deref().unwrap_or("")
+    );
+    (scene.world, scene.camera)
+}
+
+#[allow(dead_code)]
+fn mitsuba_scene(path: &str) -> (Box<dyn Hittable>, Camera) {
+    let scene = load_mitsuba(path).expect("failed to load mitsuba scene");
+    for warning in &scene.warnings {
+        eprintln!("mitsuba: {}", warning);
+    }
+    eprintln!(
+        "mitsuba: scene requests {}x{} at {} spp",
+        scene.width, scene.height, scene.samples_per_pixel
+    );
+    (scene.world, scene.camera)
+}
+
+#[allow(dead_code)]
+fn usd_scene(path: &str) -> (Box<dyn Hittable>, Camera) {
+    let scene = load_usda(path).expect("failed to load usd stage");
+    for warning in &scene.warnings {
+        eprintln!("usd: {}", warning);
+    }
+    eprintln!("usd: stage requests {}x{}", scene.width, scene.height);
+    (scene.world, scene.camera)
+}
+
 fn final_scene() -> Box<dyn Hittable> {
     let mut rng = rand::thread_rng();
     let ground = Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
@@ -621,13 +1908,14 @@ fn main() {
     // let world = simple_light();
     // let world = cornell_box();
     // let world = cornell_smoke();
+    // let world = scanned_meshes();
     let world = final_scene();
 
     // Camera
     let lookfrom = Point3::new(478.0, 278.0, -600.0); //Point3::new(13.0, 2.0, 3.0);
     let lookat = Point3::new(278.0, 278.0, 0.0); //Point3::new(0.0, 0.0, 0.0);
                                                  // let aperture = 0.1;
-    let dist_to_focus = 10.0;
+    let dist_to_focus = (lookat - lookfrom).length();
     let cam = Camera::new(
         lookfrom,
         lookat,
@@ -638,44 +1926,30 @@ fn main() {
         dist_to_focus,
         0.0,
         1.0,
-    );
+    )
+    .with_autofocus(world.as_ref(), 0.5, 0.5);
+    eprintln!("Focus distance: {}", cam.focus_dist());
+
+    let exposure = Exposure::default();
 
     println!("P3");
     println!("{} {}", WIDTH, HEIGHT);
     println!("255");
 
-    // for j in (0..HEIGHT).rev() {
-    //     eprintln!("Scanlines remaining: {}", j + 1);
-
-    let scanline: Vec<Color> = (0..(WIDTH * HEIGHT))
-        .into_par_iter()
-        .map(|cnt| {
-            let j = HEIGHT - cnt / WIDTH - 1;
-            let i = cnt % WIDTH;
-            let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-            let mut rng = rand::thread_rng();
-            for _ in 0..SAMPLES_PER_PIXEL {
-                // let mut rng = rand::thread_rng();
-                let random_u: f32 = rng.gen();
-                let random_v: f32 = rng.gen();
-
-                let u = (i as f32 + random_u) / (WIDTH - 1) as f32;
-                let v = (j as f32 + random_v) / (HEIGHT - 1) as f32;
-
-                let r = cam.get_ray(u, v);
-                pixel_color += ray_color(&r, &world, MAX_DEPTH);
-            }
-
-            pixel_color
-
-            // println!("{}", format_color(&pixel_color, SAMPLES_PER_PIXEL));
-        })
-        .collect();
+    let scanline = render(
+        world.as_ref(),
+        &cam,
+        WIDTH,
+        HEIGHT,
+        SAMPLES_PER_PIXEL,
+        MAX_DEPTH,
+    );
 
     for pixel_color in scanline {
-        println!("{}", format_color(&pixel_color, SAMPLES_PER_PIXEL));
+        println!(
+            "{}",
+            format_color(exposure.develop(&pixel_color, SAMPLES_PER_PIXEL))
+        );
     }
-    // }
-    // eprintln!("");
     eprintln!("Done.");
 }
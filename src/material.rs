@@ -35,7 +35,7 @@ impl<T: Texture> Scatter for Lambertian<T> {
 
         let scattered = Ray::new(rec.p, scatter_direction, r_in.time());
 
-        Some((self.albedo.value(rec.u, rec.v, rec.p), scattered))
+        Some((self.albedo.value_at(rec), scattered))
     }
 
     fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
@@ -52,6 +52,36 @@ impl Metal {
     pub fn new(a: Color, f: f32) -> Self {
         Self { albedo: a, fuzz: f }
     }
+
+    // Normal-incidence reflectance of a conductor with complex index of refraction eta + ik.
+    pub fn fresnel_reflectance(eta: Color, k: Color) -> Color {
+        let mut r = Color::new(0.0, 0.0, 0.0);
+        for i in 0..3 {
+            r[i] = ((eta[i] - 1.0).powi(2) + k[i] * k[i]) / ((eta[i] + 1.0).powi(2) + k[i] * k[i]);
+        }
+        r
+    }
+
+    // Approximate reflectance of common metals by chemical name, e.g. "Au" or "metal-Au-eta".
+    pub fn named_reflectance(name: &str) -> Option<Color> {
+        let metal = name.trim_start_matches("metal-").split('-').next()?;
+        match metal {
+            "Ag" => Some(Color::new(0.97, 0.96, 0.91)),
+            "Al" => Some(Color::new(0.91, 0.92, 0.92)),
+            "Au" => Some(Color::new(1.0, 0.78, 0.34)),
+            "Cr" => Some(Color::new(0.55, 0.56, 0.55)),
+            "Cu" => Some(Color::new(0.95, 0.64, 0.54)),
+            "CuZn" => Some(Color::new(0.89, 0.78, 0.49)),
+            "Fe" => Some(Color::new(0.56, 0.57, 0.58)),
+            "MgO" => Some(Color::new(0.75, 0.75, 0.75)),
+            "Ni" => Some(Color::new(0.66, 0.61, 0.53)),
+            "Pt" => Some(Color::new(0.67, 0.64, 0.59)),
+            "Ti" => Some(Color::new(0.54, 0.50, 0.45)),
+            "TiO2" => Some(Color::new(0.62, 0.64, 0.68)),
+            "W" => Some(Color::new(0.50, 0.50, 0.47)),
+            _ => None,
+        }
+    }
 }
 
 impl Scatter for Metal {
@@ -159,7 +189,7 @@ impl Isotropic {
 impl Scatter for Isotropic {
     fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Vec3, Ray)> {
         let scattered = Ray::new(hit.p, Vec3::random_in_unit_sphere(), ray.time());
-        Some((self.albedo.value(hit.u, hit.v, hit.p), scattered))
+        Some((self.albedo.value_at(hit), scattered))
     }
 
     fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
@@ -0,0 +1,401 @@
+use super::vec3::{Point3, Vec3};
+
+use std::ops::Mul;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn new(m: [[f32; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, offset.x()],
+            [0.0, 1.0, 0.0, offset.y()],
+            [0.0, 0.0, 1.0, offset.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(s: Vec3) -> Self {
+        Self::new([
+            [s.x(), 0.0, 0.0, 0.0],
+            [0.0, s.y(), 0.0, 0.0],
+            [0.0, 0.0, s.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Each coordinate is offset by the others, e.g. x' = x + xy * y + xz * z.
+    pub fn shear(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Self {
+        Self::new([
+            [1.0, xy, xz, 0.0],
+            [yx, 1.0, yz, 0.0],
+            [zx, zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Rotation by `angle` degrees about an arbitrary axis through the origin.
+    pub fn rotate(angle: f32, axis: Vec3) -> Self {
+        let a = axis.normalized();
+        let radians = (std::f32::consts::PI / 180.0) * angle;
+        let (sin_theta, cos_theta) = radians.sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        Self::new([
+            [
+                x * x + (1.0 - x * x) * cos_theta,
+                x * y * (1.0 - cos_theta) - z * sin_theta,
+                x * z * (1.0 - cos_theta) + y * sin_theta,
+                0.0,
+            ],
+            [
+                x * y * (1.0 - cos_theta) + z * sin_theta,
+                y * y + (1.0 - y * y) * cos_theta,
+                y * z * (1.0 - cos_theta) - x * sin_theta,
+                0.0,
+            ],
+            [
+                x * z * (1.0 - cos_theta) - y * sin_theta,
+                y * z * (1.0 - cos_theta) + x * sin_theta,
+                z * z + (1.0 - z * z) * cos_theta,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Rotation by the unit quaternion w + xi + yj + zk.
+    pub fn from_quaternion(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for i in 0..4 {
+            for j in 0..4 {
+                m[i][j] = self.m[j][i];
+            }
+        }
+        Self::new(m)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting; `None` for singular matrices and
+    // ones with NaN or infinite entries.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::IDENTITY.m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if !a[pivot][col].is_finite() || a[pivot][col].abs() < 1.0e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+
+        Some(Self::new(inv))
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3];
+        let y = m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3];
+        let z = m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3];
+        let w = m[3][0] * p.x() + m[3][1] * p.y() + m[3][2] * p.z() + m[3][3];
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x, y, z) / w
+        }
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    // Normals transform by the inverse transpose, so this expects the inverse of the
+    // matrix that was applied to the points.
+    pub fn transform_normal(inverse: &Self, n: Vec3) -> Vec3 {
+        let m = &inverse.m;
+        Vec3::new(
+            m[0][0] * n.x() + m[1][0] * n.y() + m[2][0] * n.z(),
+            m[0][1] * n.x() + m[1][1] * n.y() + m[2][1] * n.z(),
+            m[0][2] * n.x() + m[1][2] * n.y() + m[2][2] * n.z(),
+        )
+    }
+
+    // The factor `s` when the matrix is an affine map whose 3x3 part is `s` times a
+    // rotation or reflection, so it keeps spheres round.
+    pub fn uniform_scale(&self) -> Option<f32> {
+        let m = &self.m;
+        if m[3] != [0.0, 0.0, 0.0, 1.0] {
+            return None;
+        }
+        let column = |j: usize| Vec3::new(m[0][j], m[1][j], m[2][j]);
+        let (x, y, z) = (column(0), column(1), column(2));
+        let s2 = x.dot(x);
+        let tolerance = 1.0e-4 * s2;
+        let round = s2 > 0.0
+            && (y.dot(y) - s2).abs() <= tolerance
+            && (z.dot(z) - s2).abs() <= tolerance
+            && x.dot(y).abs() <= tolerance
+            && y.dot(z).abs() <= tolerance
+            && z.dot(x).abs() <= tolerance;
+        round.then(|| s2.sqrt())
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    #[allow(clippy::needless_range_loop)]
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for i in 0..4 {
+            for j in 0..4 {
+                m[i][j] = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Mat4::new(m)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Quat {
+    w: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+impl Quat {
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        let len = (w * w + x * x + y * y + z * z).sqrt();
+        Self {
+            w: w / len,
+            x: x / len,
+            y: y / len,
+            z: z / len,
+        }
+    }
+
+    // Rotation by `angle` degrees about `axis`, matching `Mat4::rotate`.
+    pub fn from_axis_angle(angle: f32, axis: Vec3) -> Self {
+        let a = axis.normalized();
+        let (s, c) = (0.5 * angle.to_radians()).sin_cos();
+        Self::new(c, s * a.x(), s * a.y(), s * a.z())
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    // Angle in radians of the rotation taking `self` to `other` along the shorter arc.
+    pub fn angle_to(self, other: Self) -> f32 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        // q and -q are the same rotation; flip to interpolate along the shorter arc.
+        let mut cos_theta = self.dot(other);
+        let other = if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            Self {
+                w: -other.w,
+                x: -other.x,
+                y: -other.y,
+                z: -other.z,
+            }
+        } else {
+            other
+        };
+
+        let (a, b) = if cos_theta > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (
+                ((1.0 - t) * theta).sin() / sin_theta,
+                (t * theta).sin() / sin_theta,
+            )
+        };
+        Self::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+    }
+
+    pub fn to_matrix(self) -> Mat4 {
+        Mat4::from_quaternion(self.w, self.x, self.y, self.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Mat4, b: &Mat4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!(
+                    (a.m[i][j] - b.m[i][j]).abs() < 1e-5,
+                    "entry ({}, {}): {} != {}",
+                    i,
+                    j,
+                    a.m[i][j],
+                    b.m[i][j]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = Mat4::translate(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotate(37.0, Vec3::new(1.0, 2.0, -0.5))
+            * Mat4::scale(Vec3::new(2.0, 0.5, 3.0));
+        let inverse = m.inverse().unwrap();
+        assert_near(&(m * inverse), &Mat4::IDENTITY);
+        assert_near(&(inverse * m), &Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        assert!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        let m =
+            Mat4::scale(Vec3::new(4.0, 1.0, 1.0)) * Mat4::rotate(30.0, Vec3::new(0.0, 0.0, 1.0));
+        let (tangent, normal) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        let tangent = m.transform_vector(tangent);
+        let normal = Mat4::transform_normal(&m.inverse().unwrap(), normal);
+        assert!(tangent.dot(normal).abs() < 1e-5);
+    }
+
+    #[test]
+    fn slerp_halfway_is_half_the_rotation() {
+        let a = Quat::IDENTITY;
+        let b = Quat::from_axis_angle(90.0, Vec3::new(0.0, 0.0, 1.0));
+        let half = a.slerp(b, 0.5);
+        assert_near(
+            &half.to_matrix(),
+            &Mat4::rotate(45.0, Vec3::new(0.0, 0.0, 1.0)),
+        );
+        assert!((a.angle_to(half) - 45f32.to_radians()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn slerp_takes_the_shorter_arc() {
+        // -q is the same rotation as q, so interpolating toward it goes nowhere.
+        let q = Quat::from_axis_angle(60.0, Vec3::new(1.0, 1.0, 0.0));
+        let negated = Quat::new(-q.w, -q.x, -q.y, -q.z);
+        assert_near(&q.slerp(negated, 0.5).to_matrix(), &q.to_matrix());
+    }
+
+    #[test]
+    fn quaternions_match_axis_angle_matrices() {
+        let axis = Vec3::new(0.3, -1.0, 2.0);
+        assert_near(
+            &Quat::from_axis_angle(123.0, axis).to_matrix(),
+            &Mat4::rotate(123.0, axis),
+        );
+    }
+
+    #[test]
+    fn uniform_scale_needs_a_round_linear_part() {
+        let turned = Mat4::translate(Vec3::new(1.0, 2.0, 3.0))
+            * Mat4::rotate(30.0, Vec3::new(1.0, 1.0, 0.0))
+            * Mat4::scale(Vec3::new(-2.0, 2.0, 2.0));
+        assert!((turned.uniform_scale().unwrap() - 2.0).abs() < 1e-5);
+        assert!(Mat4::scale(Vec3::new(1.0, 2.0, 1.0))
+            .uniform_scale()
+            .is_none());
+        assert!(Mat4::shear(0.5, 0.0, 0.0, 0.0, 0.0, 0.0)
+            .uniform_scale()
+            .is_none());
+        assert!(Mat4::scale(Vec3::new(0.0, 0.0, 0.0))
+            .uniform_scale()
+            .is_none());
+    }
+
+    #[test]
+    fn non_finite_matrices_have_no_inverse() {
+        for bad in [f32::NAN, f32::INFINITY] {
+            let m = Mat4::translate(Vec3::new(bad, 0.0, 0.0));
+            assert!(m.inverse().is_none());
+            let m = Mat4::scale(Vec3::new(1.0, bad, 1.0));
+            assert!(m.inverse().is_none());
+        }
+    }
+}
@@ -29,8 +29,8 @@ impl ConstantMedium {
 impl Hittable for ConstantMedium {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         let mut rng = rand::thread_rng();
-        if let Some(mut hit1) = self.boundary.hit(&r, -f32::MAX, f32::MAX) {
-            if let Some(mut hit2) = self.boundary.hit(&r, hit1.t + 0.0001, f32::MAX) {
+        if let Some(mut hit1) = self.boundary.hit(r, -f32::MAX, f32::MAX) {
+            if let Some(mut hit2) = self.boundary.hit(r, hit1.t + 0.0001, f32::MAX) {
                 if hit1.t < t_min {
                     hit1.t = t_min
                 }
@@ -50,6 +50,8 @@ impl Hittable for ConstantMedium {
                             normal: Vec3::new(1.0, 0.0, 0.0),
                             front_face: true,
                             mat: self.phase_function.clone(),
+                            color: None,
+                            tangent: None,
                         });
                     }
                 }
@@ -0,0 +1,254 @@
+use super::material::Scatter;
+use super::matrix::Mat4;
+use super::triangle::Triangle;
+use super::vec3::{Color, Point3, Vec3};
+use super::world::HitableList;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "io error: {}", err),
+            LoadError::Parse(msg) => write!(f, "parse error: {}", msg),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+#[derive(Clone)]
+pub struct TriangleMesh {
+    pub positions: Vec<Point3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(f32, f32)>>,
+    pub colors: Option<Vec<Color>>,
+    pub indices: Vec<[usize; 3]>,
+    // Vertex positions at increasing times for deforming meshes; empty for static ones.
+    pub motion: Vec<(f32, Vec<Point3>)>,
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Point3>, indices: Vec<[usize; 3]>) -> Self {
+        Self {
+            positions,
+            normals: None,
+            uvs: None,
+            colors: None,
+            indices,
+            motion: Vec::new(),
+        }
+    }
+
+    // Replaces the static positions with time samples that are linearly interpolated at
+    // each ray's time. The first sample also becomes `positions`.
+    pub fn set_motion(&mut self, mut samples: Vec<(f32, Vec<Point3>)>) -> Result<(), LoadError> {
+        if samples.is_empty() {
+            return Err(LoadError::Parse(
+                "motion needs at least one sample".to_string(),
+            ));
+        }
+        if samples.iter().any(|(_, p)| p.len() != self.positions.len()) {
+            return Err(LoadError::Parse(
+                "motion samples must match the vertex count".to_string(),
+            ));
+        }
+        if samples.iter().any(|(t, _)| !t.is_finite()) {
+            return Err(LoadError::Parse(
+                "motion sample times must be finite".to_string(),
+            ));
+        }
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.positions = samples[0].1.clone();
+        // Rest-pose shading normals would not follow the deformation.
+        self.normals = None;
+        self.motion = samples;
+        Ok(())
+    }
+
+    pub fn position(&self, index: usize, time: f32) -> Point3 {
+        if self.motion.len() < 2 {
+            return self.positions[index];
+        }
+        let i = self.motion.partition_point(|(t, _)| *t <= time);
+        if i == 0 {
+            return self.motion[0].1[index];
+        }
+        if i == self.motion.len() {
+            return self.motion[i - 1].1[index];
+        }
+        let (t0, p0) = &self.motion[i - 1];
+        let (t1, p1) = &self.motion[i];
+        let t = (time - t0) / (t1 - t0);
+        (1.0 - t) * p0[index] + t * p1[index]
+    }
+
+    // Times at which the vertices change direction within [time0, time1], including both
+    // ends; positions are linear in between.
+    pub fn motion_times(&self, time0: f32, time1: f32) -> Vec<f32> {
+        let mut times = vec![time0];
+        times.extend(
+            self.motion
+                .iter()
+                .map(|(t, _)| *t)
+                .filter(|&t| t > time0 && t < time1),
+        );
+        times.push(time1);
+        times
+    }
+
+    // The [-1, 1]^2 square in the z = 0 plane, facing +z.
+    pub fn rectangle() -> Self {
+        let mut mesh = Self::new(
+            vec![
+                Point3::new(-1.0, -1.0, 0.0),
+                Point3::new(1.0, -1.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(-1.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        );
+        mesh.uvs = Some(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        mesh
+    }
+
+    // The [-1, 1]^3 cube with outward facing windings and per-face uvs.
+    pub fn cube() -> Self {
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+
+        for axis in 0..3 {
+            for &sign in &[1.0f32, -1.0] {
+                let base = positions.len();
+                for &(b, c) in &corners {
+                    let mut p = Point3::new(0.0, 0.0, 0.0);
+                    p[axis] = sign;
+                    p[(axis + 1) % 3] = b;
+                    p[(axis + 2) % 3] = c;
+                    positions.push(p);
+                    uvs.push((0.5 * (b + 1.0), 0.5 * (c + 1.0)));
+                }
+                if sign > 0.0 {
+                    indices.push([base, base + 1, base + 2]);
+                    indices.push([base, base + 2, base + 3]);
+                } else {
+                    indices.push([base, base + 2, base + 1]);
+                    indices.push([base, base + 3, base + 2]);
+                }
+            }
+        }
+
+        let mut mesh = Self::new(positions, indices);
+        mesh.uvs = Some(uvs);
+        mesh
+    }
+
+    // Bakes a transform into the vertex data.
+    pub fn transform(&mut self, m: &Mat4) {
+        for p in self.positions.iter_mut() {
+            *p = m.transform_point(*p);
+        }
+        for (_, positions) in self.motion.iter_mut() {
+            for p in positions.iter_mut() {
+                *p = m.transform_point(*p);
+            }
+        }
+        match m.inverse() {
+            Some(inverse) => {
+                if let Some(normals) = self.normals.as_mut() {
+                    for n in normals.iter_mut() {
+                        *n = Mat4::transform_normal(&inverse, *n).normalized();
+                    }
+                }
+            }
+            None => self.normals = None,
+        }
+    }
+
+    // Splits the mesh into one hittable per triangle, ready to be handed to `BVH::new`.
+    pub fn triangles(self, mat: Arc<dyn Scatter>) -> HitableList {
+        let mesh = Arc::new(self);
+        let mut list = HitableList::new();
+        for index in 0..mesh.indices.len() {
+            list.push(Box::new(Triangle::new(mesh.clone(), index, mat.clone())));
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+    use crate::ray::Ray;
+    use crate::testing::gray;
+
+    // The unit rectangle rising from z = 0 at time 0 to z = 2 at time 1.
+    fn rising_rectangle() -> TriangleMesh {
+        let mut mesh = TriangleMesh::rectangle();
+        let start = mesh.positions.clone();
+        let end = start
+            .iter()
+            .map(|&p| p + Vec3::new(0.0, 0.0, 2.0))
+            .collect();
+        mesh.set_motion(vec![(1.0, end), (0.0, start)]).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn positions_follow_the_samples_in_time_order() {
+        let mesh = rising_rectangle();
+        assert!((mesh.position(0, 0.0).z() - 0.0).abs() < 1e-6);
+        assert!((mesh.position(0, 0.25).z() - 0.5).abs() < 1e-6);
+        assert!((mesh.position(0, 1.0).z() - 2.0).abs() < 1e-6);
+        // Outside the samples the mesh holds still.
+        assert!((mesh.position(0, -1.0).z() - 0.0).abs() < 1e-6);
+        assert!((mesh.position(0, 5.0).z() - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rays_see_the_mesh_at_their_own_time() {
+        let world = rising_rectangle().triangles(gray());
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        for (time, z) in [(0.0, 0.0), (0.5, 1.0), (1.0, 2.0)] {
+            let ray = Ray::new(Point3::new(0.2, 0.3, 10.0), down, time);
+            let rec = world.hit(&ray, 0.001, f32::INFINITY).unwrap();
+            assert!((rec.t - (10.0 - z)).abs() < 1e-4);
+        }
+        let bbox = world.bounding_box(0.0, 1.0).unwrap();
+        assert!(bbox.min().z() < 0.0 && bbox.max().z() > 2.0);
+    }
+
+    #[test]
+    fn samples_must_match_the_vertex_count() {
+        let mut mesh = TriangleMesh::rectangle();
+        assert!(mesh.set_motion(Vec::new()).is_err());
+        let short = vec![Point3::new(0.0, 0.0, 0.0)];
+        assert!(mesh.set_motion(vec![(0.0, short)]).is_err());
+    }
+
+    #[test]
+    fn sample_times_must_be_finite() {
+        let mut mesh = TriangleMesh::rectangle();
+        let rest = mesh.positions.clone();
+        let samples = vec![(0.0, rest.clone()), (f32::NAN, rest)];
+        assert!(mesh.set_motion(samples).is_err());
+    }
+}
@@ -0,0 +1,913 @@
+use super::bvh::BVH;
+use super::camera::Camera;
+use super::hittable::Hittable;
+use super::material::{Dielectric, DiffuseLight, Lambertian, Metal, Scatter};
+use super::matrix::Mat4;
+use super::mesh::{LoadError, TriangleMesh};
+use super::obj::load_obj;
+use super::ply::load_ply;
+use super::sphere::Sphere;
+use super::texture::{ConstantTexture, ImageTexture, ScaledTexture, Texture, UvCheckerTexture};
+use super::transform::place_sphere;
+use super::vec3::{Color, Point3, Vec3};
+use super::world::HitableList;
+
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub struct MitsubaScene {
+    pub world: Box<dyn Hittable>,
+    pub camera: Camera,
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub warnings: Vec<String>,
+}
+
+struct Sensor {
+    to_world: Mat4,
+    fov: f32,
+    fov_axis: String,
+    aperture: f32,
+    focus_dist: f32,
+}
+
+enum Environment {
+    Constant(Color),
+    Map(Arc<dyn Texture>),
+    Point(Point3, Color),
+}
+
+fn parse_floats(s: &str) -> Result<Vec<f32>, LoadError> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            t.parse::<f32>()
+                .map_err(|_| LoadError::Parse(format!("invalid number '{}'", t)))
+        })
+        .collect()
+}
+
+fn named_ior(name: &str) -> Option<f32> {
+    match name {
+        "vacuum" => Some(1.0),
+        "helium" => Some(1.000036),
+        "hydrogen" => Some(1.000132),
+        "air" => Some(1.000277),
+        "carbon dioxide" => Some(1.00045),
+        "water" => Some(1.333),
+        "acetone" => Some(1.36),
+        "ethanol" => Some(1.361),
+        "carbon tetrachloride" => Some(1.461),
+        "glycerol" => Some(1.4729),
+        "benzene" => Some(1.501),
+        "silicone oil" => Some(1.52045),
+        "bromine" => Some(1.661),
+        "water ice" => Some(1.31),
+        "fused quartz" => Some(1.458),
+        "pyrex" => Some(1.470),
+        "acrylic glass" => Some(1.49),
+        "polypropylene" => Some(1.49),
+        "bk7" => Some(1.5046),
+        "sodium chloride" => Some(1.544),
+        "amber" => Some(1.55),
+        "pet" => Some(1.575),
+        "diamond" => Some(2.419),
+        _ => None,
+    }
+}
+
+struct Importer {
+    base_dir: PathBuf,
+    defaults: HashMap<String, String>,
+    bsdfs: HashMap<String, Arc<dyn Scatter>>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    objects: HitableList,
+    environment: Vec<Environment>,
+    sensor: Option<Sensor>,
+    width: u32,
+    height: u32,
+    samples_per_pixel: u32,
+    warnings: Vec<String>,
+}
+
+impl Importer {
+    fn warn(&mut self, msg: String) {
+        self.warnings.push(msg);
+    }
+
+    // Attribute value with `$name` references to <default> parameters substituted.
+    fn attr(&self, node: Node, name: &str) -> Option<String> {
+        let value = node.attribute(name)?;
+        match value.strip_prefix('$') {
+            Some(key) => Some(
+                self.defaults
+                    .get(key)
+                    .cloned()
+                    .unwrap_or_else(|| value.to_string()),
+            ),
+            None => Some(value.to_string()),
+        }
+    }
+
+    fn property<'a, 'b>(&self, node: Node<'a, 'b>, name: &str) -> Option<Node<'a, 'b>> {
+        node.children()
+            .find(|c| c.is_element() && c.attribute("name") == Some(name))
+    }
+
+    fn float(&self, node: Node, name: &str, default: f32) -> Result<f32, LoadError> {
+        match self
+            .property(node, name)
+            .and_then(|p| self.attr(p, "value"))
+        {
+            Some(v) => v
+                .trim()
+                .parse()
+                .map_err(|_| LoadError::Parse(format!("invalid value for '{}'", name))),
+            None => Ok(default),
+        }
+    }
+
+    fn string(&self, node: Node, name: &str) -> Option<String> {
+        self.property(node, name)
+            .and_then(|p| self.attr(p, "value"))
+    }
+
+    fn boolean(&self, node: Node, name: &str, default: bool) -> bool {
+        self.string(node, name)
+            .map(|v| v == "true")
+            .unwrap_or(default)
+    }
+
+    fn color_value(&self, node: Node) -> Result<Option<Color>, LoadError> {
+        let value = match self.attr(node, "value") {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        match node.tag_name().name() {
+            "rgb" | "float" => {
+                let f = parse_floats(&value)?;
+                Ok(match f.len() {
+                    1 => Some(Color::new(f[0], f[0], f[0])),
+                    3 => Some(Color::new(f[0], f[1], f[2])),
+                    _ => None,
+                })
+            }
+            "spectrum" => {
+                // Either a constant or "wavelength:value" pairs, averaged to gray.
+                let values: Vec<f32> = value
+                    .split(',')
+                    .filter_map(|pair| pair.rsplit(':').next())
+                    .filter_map(|v| v.trim().parse::<f32>().ok())
+                    .collect();
+                if values.is_empty() {
+                    Ok(None)
+                } else {
+                    let avg = values.iter().sum::<f32>() / values.len() as f32;
+                    Ok(Some(Color::new(avg, avg, avg)))
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn color(&self, node: Node, name: &str) -> Result<Option<Color>, LoadError> {
+        match self.property(node, name) {
+            Some(p) => self.color_value(p),
+            None => Ok(None),
+        }
+    }
+
+    fn point(&self, node: Node, name: &str, default: Point3) -> Result<Point3, LoadError> {
+        match self.property(node, name) {
+            Some(p) => self.vector_attrs(p, default),
+            None => Ok(default),
+        }
+    }
+
+    fn vector_attrs(&self, node: Node, default: Vec3) -> Result<Vec3, LoadError> {
+        if let Some(value) = self.attr(node, "value") {
+            let f = parse_floats(&value)?;
+            return match f.len() {
+                1 => Ok(Vec3::new(f[0], f[0], f[0])),
+                3 => Ok(Vec3::new(f[0], f[1], f[2])),
+                _ => Err(LoadError::Parse(format!("invalid vector '{}'", value))),
+            };
+        }
+        let mut v = default;
+        for (i, axis) in ["x", "y", "z"].iter().enumerate() {
+            if let Some(s) = self.attr(node, axis) {
+                v[i] = s
+                    .trim()
+                    .parse()
+                    .map_err(|_| LoadError::Parse(format!("invalid coordinate '{}'", s)))?;
+            }
+        }
+        Ok(v)
+    }
+
+    fn transform(&mut self, node: Node) -> Result<Mat4, LoadError> {
+        let mut m = Mat4::IDENTITY;
+        for op in node.children().filter(|c| c.is_element()) {
+            let step = match op.tag_name().name() {
+                "translate" => Mat4::translate(self.vector_attrs(op, Vec3::new(0.0, 0.0, 0.0))?),
+                "scale" => Mat4::scale(self.vector_attrs(op, Vec3::new(1.0, 1.0, 1.0))?),
+                "rotate" => {
+                    let angle = self
+                        .attr(op, "angle")
+                        .and_then(|a| a.trim().parse().ok())
+                        .unwrap_or(0.0);
+                    Mat4::rotate(angle, self.vector_attrs(op, Vec3::new(0.0, 0.0, 0.0))?)
+                }
+                "matrix" => {
+                    let f = parse_floats(&self.attr(op, "value").unwrap_or_default())?;
+                    match f.len() {
+                        16 => Mat4::new([
+                            [f[0], f[1], f[2], f[3]],
+                            [f[4], f[5], f[6], f[7]],
+                            [f[8], f[9], f[10], f[11]],
+                            [f[12], f[13], f[14], f[15]],
+                        ]),
+                        9 => Mat4::new([
+                            [f[0], f[1], f[2], 0.0],
+                            [f[3], f[4], f[5], 0.0],
+                            [f[6], f[7], f[8], 0.0],
+                            [0.0, 0.0, 0.0, 1.0],
+                        ]),
+                        _ => {
+                            return Err(LoadError::Parse("matrix needs 9 or 16 values".to_string()))
+                        }
+                    }
+                }
+                "lookat" => {
+                    let parse = |name: &str| -> Result<Vec3, LoadError> {
+                        let f = parse_floats(&self.attr(op, name).unwrap_or_default())?;
+                        if f.len() == 3 {
+                            Ok(Vec3::new(f[0], f[1], f[2]))
+                        } else {
+                            Err(LoadError::Parse(format!("lookat needs a 3D '{}'", name)))
+                        }
+                    };
+                    let origin = parse("origin")?;
+                    let dir = (parse("target")? - origin).normalized();
+                    let up = match self.attr(op, "up") {
+                        Some(_) => parse("up")?,
+                        None => Vec3::new(0.0, 1.0, 0.0),
+                    };
+                    let left = up.normalized().cross(dir).normalized();
+                    let new_up = dir.cross(left);
+                    Mat4::new([
+                        [left.x(), new_up.x(), dir.x(), origin.x()],
+                        [left.y(), new_up.y(), dir.y(), origin.y()],
+                        [left.z(), new_up.z(), dir.z(), origin.z()],
+                        [0.0, 0.0, 0.0, 1.0],
+                    ])
+                }
+                other => {
+                    self.warn(format!("transform '{}' ignored", other));
+                    Mat4::IDENTITY
+                }
+            };
+            // Later operations are applied after earlier ones.
+            m = step * m;
+        }
+        Ok(m)
+    }
+
+    fn world_transform(&mut self, node: Node) -> Result<Mat4, LoadError> {
+        match self.property(node, "to_world") {
+            Some(t) if t.tag_name().name() == "transform" => self.transform(t),
+            _ => Ok(Mat4::IDENTITY),
+        }
+    }
+
+    fn texture(&mut self, node: Node) -> Result<Option<Arc<dyn Texture>>, LoadError> {
+        let ty = self.attr(node, "type").unwrap_or_default();
+        match ty.as_str() {
+            "bitmap" => {
+                let filename = self.string(node, "filename").unwrap_or_default();
+                match image::open(self.base_dir.join(&filename)) {
+                    Ok(img) => {
+                        let img = img.to_rgb8();
+                        let (nx, ny) = img.dimensions();
+                        Ok(Some(Arc::new(ImageTexture::new(img.into_raw(), nx, ny))))
+                    }
+                    Err(err) => {
+                        self.warn(format!("could not load bitmap '{}': {}", filename, err));
+                        Ok(None)
+                    }
+                }
+            }
+            "checkerboard" => {
+                let color0 = self
+                    .color(node, "color0")?
+                    .unwrap_or(Color::new(0.4, 0.4, 0.4));
+                let color1 = self
+                    .color(node, "color1")?
+                    .unwrap_or(Color::new(0.2, 0.2, 0.2));
+                // Two cells per unit of uv, stretched by any scale in `to_uv`.
+                let to_uv = match self.property(node, "to_uv") {
+                    Some(t) => self.transform(t)?,
+                    None => Mat4::IDENTITY,
+                };
+                let su = to_uv.transform_vector(Vec3::new(1.0, 0.0, 0.0)).length();
+                let sv = to_uv.transform_vector(Vec3::new(0.0, 1.0, 0.0)).length();
+                Ok(Some(Arc::new(UvCheckerTexture::new(
+                    ConstantTexture::new(color0),
+                    ConstantTexture::new(color1),
+                    2.0 * su,
+                    2.0 * sv,
+                ))))
+            }
+            _ => {
+                self.warn(format!("texture '{}' not supported", ty));
+                Ok(None)
+            }
+        }
+    }
+
+    fn color_or_texture(
+        &mut self,
+        node: Node,
+        name: &str,
+        default: Color,
+    ) -> Result<Arc<dyn Texture>, LoadError> {
+        if let Some(p) = self.property(node, name) {
+            match p.tag_name().name() {
+                "texture" => {
+                    if let Some(t) = self.texture(p)? {
+                        return Ok(t);
+                    }
+                }
+                "ref" => {
+                    let id = self.attr(p, "id").unwrap_or_default();
+                    match self.textures.get(&id) {
+                        Some(t) => return Ok(t.clone()),
+                        None => self.warn(format!("unknown texture reference '{}'", id)),
+                    }
+                }
+                _ => {
+                    if let Some(c) = self.color_value(p)? {
+                        return Ok(Arc::new(ConstantTexture::new(c)));
+                    }
+                }
+            }
+        }
+        Ok(Arc::new(ConstantTexture::new(default)))
+    }
+
+    fn ior(&mut self, node: Node, name: &str, default: f32) -> f32 {
+        match self.string(node, name) {
+            Some(v) => match v.trim().parse::<f32>() {
+                Ok(f) => f,
+                Err(_) => named_ior(&v).unwrap_or_else(|| {
+                    self.warn(format!("unknown index of refraction '{}'", v));
+                    default
+                }),
+            },
+            None => default,
+        }
+    }
+
+    fn bsdf(&mut self, node: Node) -> Result<Arc<dyn Scatter>, LoadError> {
+        if node.tag_name().name() == "ref" {
+            let id = self.attr(node, "id").unwrap_or_default();
+            return match self.bsdfs.get(&id) {
+                Some(b) => Ok(b.clone()),
+                None => {
+                    self.warn(format!("unknown bsdf reference '{}'", id));
+                    Ok(self.default_bsdf())
+                }
+            };
+        }
+
+        let ty = self.attr(node, "type").unwrap_or_default();
+        let nested = node
+            .children()
+            .find(|c| c.is_element() && c.tag_name().name() == "bsdf");
+        let gray = Color::new(0.5, 0.5, 0.5);
+
+        Ok(match ty.as_str() {
+            "diffuse" => Arc::new(Lambertian::new(self.color_or_texture(
+                node,
+                "reflectance",
+                gray,
+            )?)),
+            "twosided" => match nested {
+                Some(inner) => self.bsdf(inner)?,
+                None => self.default_bsdf(),
+            },
+            "conductor" | "roughconductor" => {
+                let base = match (self.color(node, "eta")?, self.color(node, "k")?) {
+                    (Some(eta), Some(k)) => Metal::fresnel_reflectance(eta, k),
+                    _ => {
+                        let material = self.string(node, "material").unwrap_or_default();
+                        match material.as_str() {
+                            "" | "none" => Color::new(1.0, 1.0, 1.0),
+                            name => Metal::named_reflectance(name).unwrap_or_else(|| {
+                                self.warn(format!("unknown conductor '{}'", name));
+                                Color::new(1.0, 1.0, 1.0)
+                            }),
+                        }
+                    }
+                };
+                let specular = self
+                    .color(node, "specular_reflectance")?
+                    .unwrap_or(Color::new(1.0, 1.0, 1.0));
+                let alpha = if ty == "roughconductor" {
+                    self.float(node, "alpha", 0.1)?
+                } else {
+                    0.0
+                };
+                Arc::new(Metal::new(specular * base, alpha))
+            }
+            "dielectric" | "thindielectric" | "roughdielectric" => {
+                if ty != "dielectric" {
+                    self.warn(format!("bsdf '{}' approximated as smooth dielectric", ty));
+                }
+                let int_ior = self.ior(node, "int_ior", 1.5046);
+                let ext_ior = self.ior(node, "ext_ior", 1.000277);
+                Arc::new(Dielectric::new(int_ior / ext_ior))
+            }
+            "mask" | "bumpmap" | "normalmap" => {
+                self.warn(format!("bsdf '{}' ignored, using its nested bsdf", ty));
+                match nested {
+                    Some(inner) => self.bsdf(inner)?,
+                    None => self.default_bsdf(),
+                }
+            }
+            _ => {
+                self.warn(format!("bsdf '{}' approximated as diffuse", ty));
+                let name = if self.property(node, "base_color").is_some() {
+                    "base_color"
+                } else {
+                    "diffuse_reflectance"
+                };
+                Arc::new(Lambertian::new(self.color_or_texture(node, name, gray)?))
+            }
+        })
+    }
+
+    fn default_bsdf(&self) -> Arc<dyn Scatter> {
+        Arc::new(Lambertian::new(ConstantTexture::new(Color::new(
+            0.5, 0.5, 0.5,
+        ))))
+    }
+
+    fn shape(&mut self, node: Node) -> Result<(), LoadError> {
+        let ty = self.attr(node, "type").unwrap_or_default();
+        let to_world = self.world_transform(node)?;
+
+        let mut mat = self.default_bsdf();
+        for child in node.children().filter(|c| c.is_element()) {
+            match child.tag_name().name() {
+                "bsdf" | "ref" => mat = self.bsdf(child)?,
+                "emitter" if child.attribute("type") != Some("area") => {
+                    let emitter_ty = self.attr(child, "type").unwrap_or_default();
+                    self.warn(format!("emitter '{}' on a shape not supported", emitter_ty));
+                }
+                _ => {}
+            }
+        }
+        // An area emitter takes precedence regardless of element order.
+        if let Some(emitter) = node.children().find(|c| {
+            c.is_element()
+                && c.tag_name().name() == "emitter"
+                && c.attribute("type") == Some("area")
+        }) {
+            let radiance = self
+                .color(emitter, "radiance")?
+                .unwrap_or(Color::new(1.0, 1.0, 1.0));
+            mat = Arc::new(DiffuseLight::new(ConstantTexture::new(radiance)));
+        }
+
+        let mesh = match ty.as_str() {
+            "obj" | "ply" => {
+                let filename = self
+                    .string(node, "filename")
+                    .ok_or_else(|| LoadError::Parse(format!("{} shape without filename", ty)))?;
+                let path = self.base_dir.join(filename);
+                let mut mesh = if ty == "obj" {
+                    load_obj(path)?
+                } else {
+                    load_ply(path)?
+                };
+                if self.boolean(node, "face_normals", false) {
+                    mesh.normals = None;
+                }
+                Some(mesh)
+            }
+            "rectangle" => Some(TriangleMesh::rectangle()),
+            "cube" => Some(TriangleMesh::cube()),
+            "sphere" => {
+                let center = self.point(node, "center", Point3::new(0.0, 0.0, 0.0))?;
+                let radius = self.float(node, "radius", 1.0)?;
+                match place_sphere(center, radius, to_world, mat.clone()) {
+                    Some(sphere) => self.objects.push(sphere),
+                    None => self.warn("sphere with a singular transform skipped".to_string()),
+                }
+                None
+            }
+            _ => {
+                self.warn(format!("shape '{}' not supported", ty));
+                None
+            }
+        };
+
+        if let Some(mut mesh) = mesh {
+            mesh.transform(&to_world);
+            self.objects.extend(mesh.triangles(mat));
+        }
+        Ok(())
+    }
+
+    fn emitter(&mut self, node: Node) -> Result<(), LoadError> {
+        let ty = self.attr(node, "type").unwrap_or_default();
+        match ty.as_str() {
+            "constant" => {
+                let radiance = self
+                    .color(node, "radiance")?
+                    .unwrap_or(Color::new(1.0, 1.0, 1.0));
+                self.environment.push(Environment::Constant(radiance));
+            }
+            "envmap" => {
+                let filename = self.string(node, "filename").unwrap_or_default();
+                let scale = self.float(node, "scale", 1.0)?;
+                if self.property(node, "to_world").is_some() {
+                    self.warn("envmap to_world ignored".to_string());
+                }
+                match image::open(self.base_dir.join(&filename)) {
+                    Ok(img) => {
+                        let img = img.to_rgb8();
+                        let (nx, ny) = img.dimensions();
+                        let map = ImageTexture::new(img.into_raw(), nx, ny);
+                        self.environment
+                            .push(Environment::Map(Arc::new(ScaledTexture::new(map, scale))));
+                    }
+                    Err(err) => self.warn(format!("could not load envmap '{}': {}", filename, err)),
+                }
+            }
+            "point" => {
+                let position = match self.property(node, "position") {
+                    Some(p) => self.vector_attrs(p, Point3::new(0.0, 0.0, 0.0))?,
+                    None => self
+                        .world_transform(node)?
+                        .transform_point(Point3::new(0.0, 0.0, 0.0)),
+                };
+                let intensity = self
+                    .color(node, "intensity")?
+                    .unwrap_or(Color::new(1.0, 1.0, 1.0));
+                self.environment
+                    .push(Environment::Point(position, intensity));
+            }
+            _ => self.warn(format!("emitter '{}' not supported", ty)),
+        }
+        Ok(())
+    }
+
+    fn sensor(&mut self, node: Node) -> Result<(), LoadError> {
+        let ty = self.attr(node, "type").unwrap_or_default();
+        if ty != "perspective" && ty != "thinlens" {
+            self.warn(format!("sensor '{}' treated as perspective", ty));
+        }
+        let to_world = self.world_transform(node)?;
+        let fov = match self.string(node, "focal_length") {
+            Some(f) => {
+                // 35mm film equivalent focal length along the x axis.
+                let mm: f32 = f
+                    .trim_end_matches("mm")
+                    .parse()
+                    .map_err(|_| LoadError::Parse(format!("invalid focal length '{}'", f)))?;
+                2.0 * (36.0 / (2.0 * mm)).atan().to_degrees()
+            }
+            None => self.float(node, "fov", 45.0)?,
+        };
+        let fov_axis = if self.string(node, "focal_length").is_some() {
+            "x".to_string()
+        } else {
+            self.string(node, "fov_axis")
+                .unwrap_or_else(|| "x".to_string())
+        };
+        let (aperture, focus_dist) = if ty == "thinlens" {
+            (
+                2.0 * self.float(node, "aperture_radius", 0.0)?,
+                self.float(node, "focus_distance", 1.0)?,
+            )
+        } else {
+            (0.0, 1.0)
+        };
+
+        for child in node.children().filter(|c| c.is_element()) {
+            match child.tag_name().name() {
+                "film" => {
+                    self.width = self.float(child, "width", 768.0)? as u32;
+                    self.height = self.float(child, "height", 576.0)? as u32;
+                }
+                "sampler" => {
+                    self.samples_per_pixel = self.float(child, "sample_count", 4.0)? as u32;
+                }
+                _ => {}
+            }
+        }
+
+        self.sensor = Some(Sensor {
+            to_world,
+            fov,
+            fov_axis,
+            aperture,
+            focus_dist,
+        });
+        Ok(())
+    }
+
+    fn scene(&mut self, root: Node) -> Result<(), LoadError> {
+        for node in root.children().filter(|c| c.is_element()) {
+            if node.tag_name().name() == "default" {
+                if let (Some(name), Some(value)) = (node.attribute("name"), node.attribute("value"))
+                {
+                    self.defaults.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+
+        for node in root.children().filter(|c| c.is_element()) {
+            let tag = node.tag_name().name();
+            match tag {
+                "default" => {}
+                "sensor" => self.sensor(node)?,
+                "shape" => self.shape(node)?,
+                "emitter" => self.emitter(node)?,
+                "bsdf" => {
+                    let bsdf = self.bsdf(node)?;
+                    match self.attr(node, "id") {
+                        Some(id) => {
+                            self.bsdfs.insert(id, bsdf);
+                        }
+                        None => self.warn("top-level bsdf without id ignored".to_string()),
+                    }
+                }
+                "texture" => {
+                    if let (Some(id), Some(texture)) = (self.attr(node, "id"), self.texture(node)?)
+                    {
+                        self.textures.insert(id, texture);
+                    }
+                }
+                _ => {
+                    let ty = node.attribute("type").unwrap_or("");
+                    self.warn(format!("element <{} type=\"{}\"> ignored", tag, ty));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<MitsubaScene, LoadError> {
+        let aspect_ratio = self.width as f32 / self.height as f32;
+        let sensor = self.sensor.take().unwrap_or(Sensor {
+            to_world: Mat4::IDENTITY,
+            fov: 45.0,
+            fov_axis: "x".to_string(),
+            aperture: 0.0,
+            focus_dist: 1.0,
+        });
+        let to_world = sensor.to_world;
+
+        let tan_half = (sensor.fov.to_radians() / 2.0).tan();
+        let vertical_tan = match sensor.fov_axis.as_str() {
+            "y" => tan_half,
+            "diagonal" => tan_half / (1.0 + aspect_ratio * aspect_ratio).sqrt(),
+            "smaller" if aspect_ratio >= 1.0 => tan_half,
+            "larger" if aspect_ratio < 1.0 => tan_half,
+            _ => tan_half / aspect_ratio,
+        };
+        let vfov = 2.0 * vertical_tan.atan().to_degrees();
+
+        let lookfrom = to_world.transform_point(Point3::new(0.0, 0.0, 0.0));
+        let lookat = to_world.transform_point(Point3::new(0.0, 0.0, 1.0));
+        let vup = to_world.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+        let camera = Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            vfov,
+            aspect_ratio,
+            sensor.aperture,
+            sensor.focus_dist,
+            0.0,
+            1.0,
+        );
+
+        if self.objects.is_empty() {
+            return Err(LoadError::Parse(
+                "scene contains no supported shapes".to_string(),
+            ));
+        }
+        let bounds = self.objects.bounding_box(0.0, 1.0);
+        let (center, extent) = match bounds {
+            Some(b) => (
+                0.5 * (b.min() + b.max()),
+                (b.max() - b.min()).length().max(1.0),
+            ),
+            None => (lookfrom, 1.0),
+        };
+        let radius = 10.0 * (extent + (lookfrom - center).length());
+
+        for env in self.environment.drain(..) {
+            match env {
+                Environment::Constant(radiance) => self.objects.push(Box::new(Sphere::new(
+                    center,
+                    radius,
+                    Arc::new(DiffuseLight::new(ConstantTexture::new(radiance))),
+                ))),
+                Environment::Map(map) => self.objects.push(Box::new(Sphere::new(
+                    center,
+                    radius,
+                    Arc::new(DiffuseLight::new(map)),
+                ))),
+                Environment::Point(p, intensity) => {
+                    // A small sphere with the same power as the point light.
+                    let r = 0.005 * extent;
+                    let radiance = intensity / (std::f32::consts::PI * r * r);
+                    self.objects.push(Box::new(Sphere::new(
+                        p,
+                        r,
+                        Arc::new(DiffuseLight::new(ConstantTexture::new(radiance))),
+                    )));
+                }
+            }
+        }
+
+        Ok(MitsubaScene {
+            world: Box::new(BVH::new(self.objects, 0.0, 1.0)),
+            camera,
+            width: self.width,
+            height: self.height,
+            samples_per_pixel: self.samples_per_pixel,
+            warnings: self.warnings,
+        })
+    }
+}
+
+pub fn parse_mitsuba(text: &str, base_dir: &Path) -> Result<MitsubaScene, LoadError> {
+    let doc = Document::parse(text).map_err(|err| LoadError::Parse(err.to_string()))?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "scene" {
+        return Err(LoadError::Parse("root element is not <scene>".to_string()));
+    }
+
+    let mut importer = Importer {
+        base_dir: base_dir.to_path_buf(),
+        defaults: HashMap::new(),
+        bsdfs: HashMap::new(),
+        textures: HashMap::new(),
+        objects: HitableList::new(),
+        environment: Vec::new(),
+        sensor: None,
+        width: 768,
+        height: 576,
+        samples_per_pixel: 4,
+        warnings: Vec::new(),
+    };
+    importer.scene(root)?;
+    importer.finish()
+}
+
+pub fn load_mitsuba<P: AsRef<Path>>(path: P) -> Result<MitsubaScene, LoadError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    parse_mitsuba(&text, path.parent().unwrap_or_else(|| Path::new(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraModel;
+    use crate::testing::first_hit;
+
+    fn parse(xml: &str) -> MitsubaScene {
+        parse_mitsuba(xml, Path::new(".")).unwrap()
+    }
+
+    #[test]
+    fn sensor_and_defaults() {
+        // Looking down +z with y up, +x is on the left of a right-handed view.
+        let scene = parse(
+            r#"<scene version="3.0.0">
+                <default name="spp" value="16"/>
+                <sensor type="perspective">
+                    <float name="fov" value="90"/>
+                    <transform name="to_world">
+                        <lookat origin="0, 0, -5" target="0, 0, 0" up="0, 1, 0"/>
+                    </transform>
+                    <sampler type="independent"><integer name="sample_count" value="$spp"/></sampler>
+                    <film type="hdrfilm">
+                        <integer name="width" value="200"/>
+                        <integer name="height" value="100"/>
+                    </film>
+                </sensor>
+                <shape type="sphere"><point name="center" x="2" y="0" z="0"/></shape>
+            </scene>"#,
+        );
+        assert_eq!((scene.width, scene.height), (200, 100));
+        assert_eq!(scene.samples_per_pixel, 16);
+        let hits = |u: f32| {
+            let ray = scene.camera.ray(u, 0.5).unwrap();
+            scene.world.hit(&ray, 0.001, f32::INFINITY).is_some()
+        };
+        assert!(hits(0.4));
+        assert!(!hits(0.6));
+    }
+
+    #[test]
+    fn transforms_apply_in_document_order() {
+        // Scaled about the origin first, then moved, so the center is at z = 5.
+        let scene = parse(
+            r#"<scene version="3.0.0">
+                <shape type="sphere">
+                    <transform name="to_world">
+                        <scale value="2"/>
+                        <translate x="0" y="0" z="5"/>
+                    </transform>
+                </shape>
+            </scene>"#,
+        );
+        let back = Vec3::new(0.0, 0.0, -1.0);
+        let p = first_hit(scene.world.as_ref(), Point3::new(0.0, 0.0, 20.0), back);
+        assert!((p.unwrap().z() - 7.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn matrices_are_row_major() {
+        let scene = parse(
+            r#"<scene version="3.0.0">
+                <shape type="rectangle">
+                    <transform name="to_world">
+                        <matrix value="1 0 0 0  0 1 0 3  0 0 1 0  0 0 0 1"/>
+                    </transform>
+                </shape>
+            </scene>"#,
+        );
+        let world = scene.world.as_ref();
+        let back = Vec3::new(0.0, 0.0, -1.0);
+        let p = first_hit(world, Point3::new(0.0, 3.5, 10.0), back);
+        assert!(p.unwrap().z().abs() < 1e-4);
+        assert!(first_hit(world, Point3::new(0.0, 0.5, 10.0), back).is_none());
+    }
+
+    #[test]
+    fn unsupported_elements_warn() {
+        let scene = parse(
+            r#"<scene version="3.0.0">
+                <integrator type="path"/>
+                <shape type="cylinder"/>
+                <shape type="sphere"/>
+            </scene>"#,
+        );
+        assert_eq!(scene.warnings.len(), 2, "{:?}", scene.warnings);
+    }
+
+    #[test]
+    fn unknown_transforms_and_iors_warn() {
+        let scene = parse(
+            r#"<scene version="3.0.0">
+                <shape type="sphere">
+                    <transform name="to_world">
+                        <shear value="0.5"/>
+                        <translate x="0" y="0" z="5"/>
+                    </transform>
+                    <bsdf type="dielectric"><string name="int_ior" value="unobtainium"/></bsdf>
+                </shape>
+            </scene>"#,
+        );
+        assert_eq!(scene.warnings.len(), 2, "{:?}", scene.warnings);
+        // The unknown step is skipped and the rest of the transform still applies.
+        let back = Vec3::new(0.0, 0.0, -1.0);
+        let p = first_hit(scene.world.as_ref(), Point3::new(0.0, 0.0, 20.0), back);
+        assert!((p.unwrap().z() - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn squashed_spheres_keep_their_shape() {
+        let scene = parse(
+            r#"<scene version="3.0.0">
+                <shape type="sphere">
+                    <point name="center" x="0" y="1" z="0"/>
+                    <transform name="to_world"><scale x="1" y="3" z="1"/></transform>
+                </shape>
+            </scene>"#,
+        );
+        let world = scene.world.as_ref();
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let top = first_hit(world, Point3::new(0.0, 10.0, 0.0), down);
+        assert!((top.unwrap().y() - 6.0).abs() < 1e-4);
+        let side = first_hit(
+            world,
+            Point3::new(0.0, 3.0, 10.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        assert!((side.unwrap().z() - 1.0).abs() < 1e-4);
+    }
+}
@@ -2,20 +2,12 @@ use super::aabb::AABB;
 use super::hittable::{HitRecord, Hittable};
 use super::material::Scatter;
 use super::ray::Ray;
+use super::sphere::get_sphere_uv;
 use super::vec3::{Point3, Vec3};
 
 use std::f32;
 use std::sync::Arc;
 
-fn get_sphere_uv(p: Vec3) -> (f32, f32) {
-    let phi = (-p.z()).atan2(p.x()) + f32::consts::PI;
-    let theta = (-p.y()).acos();
-
-    let u = phi / (2.0 * f32::consts::PI);
-    let v = theta / f32::consts::PI;
-    (u, v)
-}
-
 pub struct MovingSphere {
     center0: Point3,
     center1: Point3,
@@ -75,12 +67,14 @@ impl Hittable for MovingSphere {
         let p = r.at(root);
         let mut rec = HitRecord {
             t: root,
-            p: p,
+            p,
             normal: Vec3::new(0.0, 0.0, 0.0),
             u: 0.0,
             v: 0.0,
             mat: self.mat.clone(),
             front_face: false,
+            color: None,
+            tangent: None,
         };
 
         let outward_normal = (rec.p - self.center(r.time())) / self.radius;
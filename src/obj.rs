@@ -0,0 +1,227 @@
+use super::mesh::{LoadError, TriangleMesh};
+use super::subdivision::SubdivisionMesh;
+use super::vec3::{Point3, Vec3};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// Resolves a 1-based (or negative, relative) OBJ index against `len` elements.
+fn resolve(index: &str, len: usize) -> Result<usize, LoadError> {
+    let i: i64 = index
+        .parse()
+        .map_err(|_| LoadError::Parse(format!("invalid obj index '{}'", index)))?;
+    let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(LoadError::Parse(format!("obj index {} out of range", i)));
+    }
+    Ok(resolved as usize)
+}
+
+pub fn parse_obj(text: &str) -> Result<TriangleMesh, LoadError> {
+    let mut v = Vec::new();
+    let mut vt = Vec::new();
+    let mut vn = Vec::new();
+
+    // OBJ indexes each attribute separately, so every distinct combination becomes a vertex.
+    let mut vertices: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        let floats = |tokens: std::str::SplitWhitespace| -> Result<Vec<f32>, LoadError> {
+            tokens
+                .map(|t| {
+                    t.parse::<f32>()
+                        .map_err(|_| LoadError::Parse(format!("invalid obj line '{}'", line)))
+                })
+                .collect()
+        };
+        match tokens.next() {
+            Some("v") => {
+                let f = floats(tokens)?;
+                if f.len() < 3 {
+                    return Err(LoadError::Parse(format!("invalid obj line '{}'", line)));
+                }
+                v.push(Point3::new(f[0], f[1], f[2]));
+            }
+            Some("vt") => {
+                let f = floats(tokens)?;
+                vt.push((
+                    f.first().copied().unwrap_or(0.0),
+                    f.get(1).copied().unwrap_or(0.0),
+                ));
+            }
+            Some("vn") => {
+                let f = floats(tokens)?;
+                if f.len() < 3 {
+                    return Err(LoadError::Parse(format!("invalid obj line '{}'", line)));
+                }
+                vn.push(Vec3::new(f[0], f[1], f[2]));
+            }
+            Some("f") => {
+                let mut face = Vec::new();
+                for corner in tokens {
+                    let mut parts = corner.split('/');
+                    let p = resolve(parts.next().unwrap_or(""), v.len())?;
+                    let t = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve(s, vt.len())?),
+                        _ => None,
+                    };
+                    let n = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve(s, vn.len())?),
+                        _ => None,
+                    };
+                    let index = *vertices.entry((p, t, n)).or_insert_with(|| {
+                        positions.push(v[p]);
+                        uvs.push(t.map(|t| vt[t]));
+                        normals.push(n.map(|n| vn[n]));
+                        positions.len() - 1
+                    });
+                    face.push(index);
+                }
+                for i in 1..face.len().saturating_sub(1) {
+                    indices.push([face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut mesh = TriangleMesh::new(positions, indices);
+    if uvs.iter().all(|uv| uv.is_some()) && !uvs.is_empty() {
+        mesh.uvs = Some(uvs.into_iter().flatten().collect());
+    }
+    if normals.iter().all(|n| n.is_some()) && !normals.is_empty() {
+        mesh.normals = Some(normals.into_iter().flatten().collect());
+    }
+    Ok(mesh)
+}
+
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, LoadError> {
+    parse_obj(&fs::read_to_string(path)?)
+}
+
+// Reads only positions and polygon faces, keeping faces whole and vertices shared across
+// texture seams, as subdivision needs.
+pub fn parse_obj_cage(text: &str) -> Result<SubdivisionMesh, LoadError> {
+    let mut v = Vec::new();
+    let mut faces = Vec::new();
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let f = tokens
+                    .map(|t| t.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| LoadError::Parse(format!("invalid obj line '{}'", line)))?;
+                if f.len() < 3 {
+                    return Err(LoadError::Parse(format!("invalid obj line '{}'", line)));
+                }
+                v.push(Point3::new(f[0], f[1], f[2]));
+            }
+            Some("f") => {
+                let face = tokens
+                    .map(|corner| resolve(corner.split('/').next().unwrap_or(""), v.len()))
+                    .collect::<Result<Vec<usize>, _>>()?;
+                if face.len() < 3 {
+                    return Err(LoadError::Parse(format!("invalid obj line '{}'", line)));
+                }
+                // Faces that repeat a vertex have collapsed edges and no place in a cage.
+                if face
+                    .iter()
+                    .enumerate()
+                    .any(|(i, v)| face[i + 1..].contains(v))
+                {
+                    return Err(LoadError::Parse(format!(
+                        "obj face '{}' repeats a vertex",
+                        line
+                    )));
+                }
+                faces.push(face);
+            }
+            _ => {}
+        }
+    }
+    SubdivisionMesh::new(v, faces)
+}
+
+pub fn load_obj_cage<P: AsRef<Path>>(path: P) -> Result<SubdivisionMesh, LoadError> {
+    parse_obj_cage(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygons_are_fanned_and_relative_indices_resolve() {
+        let mesh = parse_obj(
+            "v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3 4
+f -4 -2 -1
+",
+        )
+        .unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3], [0, 2, 3]]);
+        assert!(mesh.uvs.is_none() && mesh.normals.is_none());
+    }
+
+    #[test]
+    fn each_attribute_combination_is_a_vertex() {
+        let mesh = parse_obj(
+            "v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vn 0 0 1
+vn 0 0 -1
+f 1/1/1 2/2/1 3/1/1
+f 1/1/2 3/1/2 2/2/2
+",
+        )
+        .unwrap();
+        // The second face shares positions but not normals with the first.
+        assert_eq!(mesh.positions.len(), 6);
+        assert_eq!(mesh.uvs.unwrap()[1], (1.0, 0.0));
+        assert!((mesh.normals.unwrap()[3].z() + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bad_indices_are_errors() {
+        for text in [
+            "v 0 0 0\nf 1 1 2\n",
+            "v 0 0 0\nf 1 1 x\n",
+            "v 0 0 0\nf 0 1 1\n",
+        ] {
+            assert!(matches!(parse_obj(text), Err(LoadError::Parse(_))));
+        }
+    }
+
+    #[test]
+    fn cages_keep_polygons_whole() {
+        let cage = parse_obj_cage(
+            "v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+f 1/1 2/1 3/1 4/1
+",
+        )
+        .unwrap();
+        // One quad splits into four quads, where two fanned triangles would give six.
+        assert_eq!(cage.catmull_clark(1).indices.len(), 8);
+        assert!(parse_obj_cage("v 0 0 0\nf 1 2 1\n").is_err());
+        let repeated = parse_obj_cage("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nf 1 2 2 3\n");
+        assert!(matches!(repeated, Err(LoadError::Parse(_))));
+    }
+}
@@ -0,0 +1,392 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::mesh::TriangleMesh;
+use super::vec3::{Point3, Vec3};
+
+use std::f32::consts::PI;
+
+const GRAVITY: f32 = 9.81;
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    const ZERO: Self = Self { re: 0.0, im: 0.0 };
+
+    fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin)
+    }
+
+    fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    fn scale(self, s: f32) -> Self {
+        Self::new(s * self.re, s * self.im)
+    }
+}
+
+// In-place unnormalized inverse DFT of a power-of-two length slice.
+fn inverse_fft(data: &mut [Complex]) {
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = Complex::from_angle(2.0 * PI / len as f32);
+        for start in (0..n).step_by(len) {
+            let mut w = Complex::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let a = data[start + k];
+                let b = data[start + k + len / 2].mul(w);
+                data[start + k] = a.add(b);
+                data[start + k + len / 2] = a.sub(b);
+                w = w.mul(step);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+// Inverse 2D DFT of an n x n grid whose frequencies run from -n/2 to n/2 - 1 along each
+// axis; returns the real part of the spatial signal.
+fn inverse_fft_2d(mut data: Vec<Complex>, n: usize) -> Vec<f32> {
+    for row in data.chunks_mut(n) {
+        inverse_fft(row);
+    }
+    let mut column = vec![Complex::ZERO; n];
+    for i in 0..n {
+        for j in 0..n {
+            column[j] = data[j * n + i];
+        }
+        inverse_fft(&mut column);
+        for j in 0..n {
+            data[j * n + i] = column[j];
+        }
+    }
+    // Shifting the frequencies by n/2 flips the sign of every other sample.
+    data.iter()
+        .enumerate()
+        .map(|(idx, c)| {
+            let (i, j) = (idx % n, idx / n);
+            if (i + j) % 2 == 0 {
+                c.re
+            } else {
+                -c.re
+            }
+        })
+        .collect()
+}
+
+// Deep-water waves after Tessendorf's "Simulating Ocean Water": a Phillips spectrum of
+// random amplitudes evolved in time and summed by FFT over a square patch of
+// `patch_size` meters that tiles seamlessly. Time is in seconds.
+pub struct Ocean {
+    resolution: usize,
+    patch_size: f32,
+    choppiness: f32,
+    // Spectrum amplitude and the conjugate of its mirror image, per wave vector.
+    h0: Vec<Complex>,
+    h0_mirror: Vec<Complex>,
+    omega: Vec<f32>,
+}
+
+impl Ocean {
+    // `resolution` samples per side (a power of two), wind speed in m/s along the xz
+    // direction `wind_direction`, and a spectrum scale `amplitude`. The same `seed` gives
+    // the same sea, so frames of an animation match up.
+    pub fn new(
+        resolution: usize,
+        patch_size: f32,
+        wind_speed: f32,
+        wind_direction: Vec3,
+        amplitude: f32,
+        seed: u64,
+    ) -> Self {
+        assert!(
+            resolution.is_power_of_two(),
+            "ocean resolution must be a power of two"
+        );
+        let n = resolution;
+        let wind = Vec3::new(wind_direction.x(), 0.0, wind_direction.z()).normalized();
+        // Largest wave that the wind can build.
+        let largest = wind_speed * wind_speed / GRAVITY;
+        // Suppresses ripples much smaller than a grid cell.
+        let smallest = 0.5 * patch_size / n as f32;
+        let phillips = |k: Vec3| {
+            let k2 = k.dot(k);
+            if k2 < 1.0e-12 {
+                return 0.0;
+            }
+            let cos = k.dot(wind) / k2.sqrt();
+            amplitude * (-1.0 / (k2 * largest * largest)).exp() / (k2 * k2)
+                * cos
+                * cos
+                * (-k2 * smallest * smallest).exp()
+        };
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut gaussian = || {
+            let (u1, u2): (f32, f32) = (rng.gen_range(f32::EPSILON..1.0), rng.gen());
+            (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+        };
+        let wave_vector = |i: usize, j: usize| {
+            let step = 2.0 * PI / patch_size;
+            Vec3::new(
+                step * (i as f32 - (n / 2) as f32),
+                0.0,
+                step * (j as f32 - (n / 2) as f32),
+            )
+        };
+
+        let mut h0 = Vec::with_capacity(n * n);
+        let mut omega = Vec::with_capacity(n * n);
+        for j in 0..n {
+            for i in 0..n {
+                let k = wave_vector(i, j);
+                let scale = (0.5 * phillips(k)).sqrt();
+                h0.push(Complex::new(gaussian(), gaussian()).scale(scale));
+                omega.push((GRAVITY * k.length()).sqrt());
+            }
+        }
+        // The mirror of frequency index m is n - m, which wraps the most negative
+        // frequency onto itself.
+        let h0_mirror = (0..n * n)
+            .map(|idx| {
+                let (i, j) = (idx % n, idx / n);
+                h0[((n - j) % n) * n + (n - i) % n].conj()
+            })
+            .collect();
+
+        Self {
+            resolution,
+            patch_size,
+            choppiness: 0.0,
+            h0,
+            h0_mirror,
+            omega,
+        }
+    }
+
+    // Pulls points toward wave crests for sharper peaks; around 1 is realistic, and too
+    // much makes the surface fold over itself.
+    pub fn with_choppiness(mut self, choppiness: f32) -> Self {
+        self.choppiness = choppiness;
+        self
+    }
+
+    // Rounds each wave's frequency so the whole sea repeats every `period` seconds.
+    pub fn with_loop(mut self, period: f32) -> Self {
+        let base = 2.0 * PI / period;
+        for w in self.omega.iter_mut() {
+            *w = (*w / base).floor() * base;
+        }
+        self
+    }
+
+    // Height and horizontal displacement in x and z of each grid sample at `time`.
+    fn displacement(&self, time: f32) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+        let n = self.resolution;
+        let step = 2.0 * PI / self.patch_size;
+        let mut height = Vec::with_capacity(n * n);
+        let mut dx = Vec::with_capacity(n * n);
+        let mut dz = Vec::with_capacity(n * n);
+        for idx in 0..n * n {
+            let phase = Complex::from_angle(self.omega[idx] * time);
+            let h = self.h0[idx]
+                .mul(phase)
+                .add(self.h0_mirror[idx].mul(phase.conj()));
+            height.push(h);
+
+            // i * k / |k| * h moves points horizontally toward the crests; Tessendorf
+            // writes it as -i * k / |k| * h with a negative choppiness.
+            let (kx, kz) = (
+                step * ((idx % n) as f32 - (n / 2) as f32),
+                step * ((idx / n) as f32 - (n / 2) as f32),
+            );
+            let k = (kx * kx + kz * kz).sqrt();
+            let i_h = Complex::new(-h.im, h.re);
+            if k > 0.0 {
+                dx.push(i_h.scale(kx / k));
+                dz.push(i_h.scale(kz / k));
+            } else {
+                dx.push(Complex::ZERO);
+                dz.push(Complex::ZERO);
+            }
+        }
+        (
+            inverse_fft_2d(height, n),
+            inverse_fft_2d(dx, n),
+            inverse_fft_2d(dz, n),
+        )
+    }
+
+    // The sea at `time` as a mesh of `tiles` by `tiles` copies of the patch, centered on
+    // the origin at height 0, with smooth normals.
+    pub fn mesh(&self, time: f32, tiles: usize) -> TriangleMesh {
+        let n = self.resolution;
+        let (height, dx, dz) = self.displacement(time);
+        let cell = self.patch_size / n as f32;
+        let side = tiles * n;
+        let half = 0.5 * side as f32 * cell;
+
+        // Position of grid point (i, j), wrapping samples so the patch tiles.
+        let position = |i: i64, j: i64| {
+            let sample = (j.rem_euclid(n as i64) as usize) * n + i.rem_euclid(n as i64) as usize;
+            Point3::new(
+                i as f32 * cell - half + self.choppiness * dx[sample],
+                height[sample],
+                j as f32 * cell - half + self.choppiness * dz[sample],
+            )
+        };
+
+        let mut positions = Vec::with_capacity((side + 1) * (side + 1));
+        let mut normals = Vec::with_capacity((side + 1) * (side + 1));
+        let mut uvs = Vec::with_capacity((side + 1) * (side + 1));
+        for j in 0..=side as i64 {
+            for i in 0..=side as i64 {
+                positions.push(position(i, j));
+                let along_x = position(i + 1, j) - position(i - 1, j);
+                let along_z = position(i, j + 1) - position(i, j - 1);
+                normals.push(along_z.cross(along_x).normalized());
+                uvs.push((i as f32 / side as f32, 1.0 - j as f32 / side as f32));
+            }
+        }
+
+        let mut indices = Vec::with_capacity(2 * side * side);
+        let row = side + 1;
+        for j in 0..side {
+            for i in 0..side {
+                let (a, b) = (j * row + i, j * row + i + 1);
+                let (c, d) = ((j + 1) * row + i + 1, (j + 1) * row + i);
+                // Wound so the geometric normals face up.
+                indices.push([a, c, b]);
+                indices.push([a, d, c]);
+            }
+        }
+
+        let mut mesh = TriangleMesh::new(positions, indices);
+        mesh.normals = Some(normals);
+        mesh.uvs = Some(uvs);
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_matches_the_direct_sum() {
+        let n = 16;
+        let data: Vec<Complex> = (0..n)
+            .map(|i| Complex::new((i as f32 * 0.7).sin(), (i as f32 * 1.3).cos()))
+            .collect();
+        let mut fast = data.clone();
+        inverse_fft(&mut fast);
+        for (x, value) in fast.iter().enumerate() {
+            let direct = data.iter().enumerate().fold(Complex::ZERO, |sum, (k, c)| {
+                sum.add(c.mul(Complex::from_angle(2.0 * PI * (k * x) as f32 / n as f32)))
+            });
+            assert!((value.re - direct.re).abs() < 1e-4 && (value.im - direct.im).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn centered_frequencies_come_out_as_waves() {
+        // One cycle along x, as the pair of frequencies +1 and -1.
+        let n = 8;
+        let mut data = vec![Complex::ZERO; n * n];
+        data[n / 2 * n + n / 2 + 1] = Complex::new(0.5, 0.0);
+        data[n / 2 * n + n / 2 - 1] = Complex::new(0.5, 0.0);
+        let wave = inverse_fft_2d(data, n);
+        for j in 0..n {
+            for i in 0..n {
+                let expected = (2.0 * PI * i as f32 / n as f32).cos();
+                assert!((wave[j * n + i] - expected).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn sea_tiles_and_loops() {
+        let n = 16;
+        let ocean = Ocean::new(n, 20.0, 8.0, Vec3::new(1.0, 0.0, 0.5), 0.01, 7)
+            .with_choppiness(1.0)
+            .with_loop(5.0);
+        let mesh = ocean.mesh(1.3, 2);
+        let row = 2 * n + 1;
+        let mut peak: f32 = 0.0;
+        for j in 0..=n {
+            for i in 0..=n {
+                let p = mesh.positions[j * row + i];
+                let q = mesh.positions[(j + n) * row + i + n];
+                assert!((q - p - Vec3::new(20.0, 0.0, 20.0)).length() < 1e-3);
+                peak = peak.max(p.y().abs());
+            }
+        }
+        assert!(peak > 0.0);
+
+        // Same seed, same sea; and a loop later it is back where it started.
+        let again = Ocean::new(n, 20.0, 8.0, Vec3::new(1.0, 0.0, 0.5), 0.01, 7)
+            .with_choppiness(1.0)
+            .with_loop(5.0)
+            .mesh(6.3, 2);
+        for (p, q) in mesh.positions.iter().zip(again.positions.iter()) {
+            assert!((*p - *q).length() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn choppy_points_gather_under_the_crests() {
+        // Horizontal displacement converges where the sea is high and spreads where it is
+        // low, so height and the divergence of the displacement are anti-correlated.
+        let n = 32;
+        let ocean = Ocean::new(n, 40.0, 10.0, Vec3::new(1.0, 0.0, 0.3), 0.01, 3);
+        let (height, dx, dz) = ocean.displacement(0.7);
+        let at = |v: &[f32], i: usize, j: usize| v[(j % n) * n + i % n];
+        let mut correlation = 0.0;
+        for j in 0..n {
+            for i in 0..n {
+                let divergence = at(&dx, i + 1, j) - at(&dx, i + n - 1, j) + at(&dz, i, j + 1)
+                    - at(&dz, i, j + n - 1);
+                correlation += at(&height, i, j) * divergence;
+            }
+        }
+        assert!(correlation < 0.0);
+    }
+}
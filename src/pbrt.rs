@@ -0,0 +1,986 @@
+use super::aabb::AABB;
+use super::bvh::BVH;
+use super::camera::Camera;
+use super::exposure::blackbody;
+use super::hittable::Hittable;
+use super::material::{Dielectric, DiffuseLight, Lambertian, Metal, Scatter};
+use super::matrix::Mat4;
+use super::mesh::{LoadError, TriangleMesh};
+use super::ply::load_ply;
+use super::sphere::Sphere;
+use super::texture::{ConstantTexture, ImageTexture, Texture, UvCheckerTexture};
+use super::transform::{place_sphere, Transform};
+use super::vec3::{Color, Point3, Vec3};
+use super::world::HitableList;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f32),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, LoadError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ']' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => s.push(c),
+                        None => return Err(LoadError::Parse("unterminated string".to_string())),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '[' || c == ']' || c == '"' || c == '#' {
+                        break;
+                    }
+                    s.push(c);
+                    chars.next();
+                }
+                match s.parse::<f32>() {
+                    Ok(n) => tokens.push(Token::Num(n)),
+                    Err(_) => tokens.push(Token::Ident(s)),
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Param {
+    ty: String,
+    name: String,
+    nums: Vec<f32>,
+    strs: Vec<String>,
+}
+
+#[derive(Default)]
+struct ParamSet {
+    params: Vec<Param>,
+}
+
+impl ParamSet {
+    fn get(&self, name: &str) -> Option<&Param> {
+        self.params.iter().find(|p| p.name == name)
+    }
+
+    fn float(&self, name: &str, default: f32) -> f32 {
+        self.get(name)
+            .and_then(|p| p.nums.first().copied())
+            .unwrap_or(default)
+    }
+
+    fn floats(&self, name: &str) -> Option<&[f32]> {
+        self.get(name).map(|p| p.nums.as_slice())
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.get(name)
+            .and_then(|p| p.strs.first())
+            .map(|s| s.as_str())
+    }
+
+    fn points(&self, name: &str) -> Option<Vec<Vec3>> {
+        self.floats(name).map(|f| {
+            f.chunks_exact(3)
+                .map(|c| Vec3::new(c[0], c[1], c[2]))
+                .collect()
+        })
+    }
+
+    // Color-valued parameter, approximating spectral inputs by RGB.
+    fn color(&self, name: &str) -> Option<Color> {
+        let p = self.get(name)?;
+        match p.ty.as_str() {
+            "rgb" | "color" if p.nums.len() >= 3 => {
+                Some(Color::new(p.nums[0], p.nums[1], p.nums[2]))
+            }
+            "blackbody" => p.nums.first().map(|&t| blackbody(t)),
+            "spectrum" | "float" if !p.nums.is_empty() => {
+                let avg = if p.ty == "float" {
+                    p.nums[0]
+                } else {
+                    // Interleaved (wavelength, value) pairs.
+                    let values: Vec<f32> = p.nums.iter().skip(1).step_by(2).copied().collect();
+                    values.iter().sum::<f32>() / values.len().max(1) as f32
+                };
+                Some(Color::new(avg, avg, avg))
+            }
+            _ => None,
+        }
+    }
+
+    fn texture(&self, name: &str) -> Option<&str> {
+        self.get(name)
+            .filter(|p| p.ty == "texture")
+            .and_then(|p| p.strs.first())
+            .map(|s| s.as_str())
+    }
+}
+
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Mat4,
+    material: Arc<dyn Scatter>,
+    area_light: Option<Color>,
+}
+
+enum DeferredLight {
+    Infinite(Color),
+    Point(Point3, Color),
+}
+
+pub struct PbrtScene {
+    pub world: Box<dyn Hittable>,
+    pub camera: Camera,
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub filename: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    base_dir: PathBuf,
+    // Files whose directives are being parsed, so including one of them again is a cycle.
+    including: Vec<PathBuf>,
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    named_coordinate_systems: HashMap<String, Mat4>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Scatter>>,
+    camera_from_world: Mat4,
+    camera_params: ParamSet,
+    film_params: ParamSet,
+    sampler_params: ParamSet,
+    objects: HitableList,
+    lights: Vec<DeferredLight>,
+    warnings: Vec<String>,
+}
+
+// pbrt is left handed; mirroring x maps its scenes onto this crate's right-handed camera
+// without flipping the rendered image.
+fn handedness() -> Mat4 {
+    Mat4::scale(Vec3::new(-1.0, 1.0, 1.0))
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>, base_dir: PathBuf) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            base_dir,
+            including: Vec::new(),
+            state: GraphicsState {
+                ctm: Mat4::IDENTITY,
+                material: Arc::new(Lambertian::new(ConstantTexture::new(Color::new(
+                    0.5, 0.5, 0.5,
+                )))),
+                area_light: None,
+            },
+            stack: Vec::new(),
+            named_coordinate_systems: HashMap::new(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            camera_from_world: Mat4::IDENTITY,
+            camera_params: ParamSet::default(),
+            film_params: ParamSet::default(),
+            sampler_params: ParamSet::default(),
+            objects: HitableList::new(),
+            lights: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, msg: String) {
+        self.warnings.push(msg);
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, LoadError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| LoadError::Parse("unexpected end of pbrt file".to_string()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn num(&mut self) -> Result<f32, LoadError> {
+        match self.next()? {
+            Token::Num(n) => Ok(n),
+            _ => Err(LoadError::Parse("expected a number".to_string())),
+        }
+    }
+
+    fn nums<const N: usize>(&mut self) -> Result<[f32; N], LoadError> {
+        // Matrices may be given with or without brackets.
+        let bracketed = self.peek() == Some(&Token::Open);
+        if bracketed {
+            self.next()?;
+        }
+        let mut out = [0.0; N];
+        for v in out.iter_mut() {
+            *v = self.num()?;
+        }
+        if bracketed && self.next()? != Token::Close {
+            return Err(LoadError::Parse("expected ']'".to_string()));
+        }
+        Ok(out)
+    }
+
+    fn string(&mut self) -> Result<String, LoadError> {
+        match self.next()? {
+            Token::Str(s) => Ok(s),
+            _ => Err(LoadError::Parse("expected a quoted string".to_string())),
+        }
+    }
+
+    fn params(&mut self) -> Result<ParamSet, LoadError> {
+        let mut set = ParamSet::default();
+        while let Some(Token::Str(decl)) = self.peek() {
+            let mut parts = decl.split_whitespace();
+            let (ty, name) = match (parts.next(), parts.next()) {
+                (Some(ty), Some(name)) => (ty.to_string(), name.to_string()),
+                _ => break,
+            };
+            self.next()?;
+
+            let mut values = Vec::new();
+            if self.peek() == Some(&Token::Open) {
+                self.next()?;
+                loop {
+                    match self.next()? {
+                        Token::Close => break,
+                        token => values.push(token),
+                    }
+                }
+            } else {
+                values.push(self.next()?);
+            }
+
+            let mut param = Param {
+                ty,
+                name,
+                nums: Vec::new(),
+                strs: Vec::new(),
+            };
+            for value in values {
+                match value {
+                    Token::Num(n) => param.nums.push(n),
+                    Token::Str(s) | Token::Ident(s) => param.strs.push(s),
+                    _ => return Err(LoadError::Parse("unexpected '[' in parameter".to_string())),
+                }
+            }
+            set.params.push(param);
+        }
+        Ok(set)
+    }
+
+    fn skip_directive(&mut self, directive: &str) {
+        self.warn(format!("unsupported directive '{}' ignored", directive));
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Ident(s) if s != "true" && s != "false" => break,
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn concat(&mut self, m: Mat4) {
+        self.state.ctm = self.state.ctm * m;
+    }
+
+    fn parse(&mut self) -> Result<(), LoadError> {
+        while let Some(token) = self.peek().cloned() {
+            let directive = match token {
+                Token::Ident(s) => s,
+                _ => return Err(LoadError::Parse("expected a directive".to_string())),
+            };
+            self.next()?;
+
+            match directive.as_str() {
+                "Identity" => self.state.ctm = Mat4::IDENTITY,
+                "Translate" => {
+                    let [x, y, z] = self.nums()?;
+                    self.concat(Mat4::translate(Vec3::new(x, y, z)));
+                }
+                "Scale" => {
+                    let [x, y, z] = self.nums()?;
+                    self.concat(Mat4::scale(Vec3::new(x, y, z)));
+                }
+                "Rotate" => {
+                    let [angle, x, y, z] = self.nums()?;
+                    self.concat(Mat4::rotate(angle, Vec3::new(x, y, z)));
+                }
+                "LookAt" => {
+                    let [ex, ey, ez, lx, ly, lz, ux, uy, uz] = self.nums()?;
+                    let m = look_at(
+                        Point3::new(ex, ey, ez),
+                        Point3::new(lx, ly, lz),
+                        Vec3::new(ux, uy, uz),
+                    )?;
+                    self.concat(m);
+                }
+                "Transform" | "ConcatTransform" => {
+                    let m: [f32; 16] = self.nums()?;
+                    // pbrt lists matrices column by column.
+                    let mut rows = [[0.0; 4]; 4];
+                    for (i, v) in m.iter().enumerate() {
+                        rows[i / 4][i % 4] = *v;
+                    }
+                    let m = Mat4::new(rows).transpose();
+                    if directive == "Transform" {
+                        self.state.ctm = m;
+                    } else {
+                        self.concat(m);
+                    }
+                }
+                "CoordinateSystem" => {
+                    let name = self.string()?;
+                    self.named_coordinate_systems.insert(name, self.state.ctm);
+                }
+                "CoordSysTransform" => {
+                    let name = self.string()?;
+                    match self.named_coordinate_systems.get(&name) {
+                        Some(m) => self.state.ctm = *m,
+                        None => self.warn(format!("unknown coordinate system '{}'", name)),
+                    }
+                }
+                "Camera" => {
+                    let ty = self.string()?;
+                    if ty != "perspective" {
+                        self.warn(format!("camera '{}' not supported, using perspective", ty));
+                    }
+                    self.camera_params = self.params()?;
+                    self.camera_from_world = self.state.ctm;
+                    self.named_coordinate_systems.insert(
+                        "camera".to_string(),
+                        self.state.ctm.inverse().unwrap_or(Mat4::IDENTITY),
+                    );
+                }
+                "Film" => {
+                    self.string()?;
+                    self.film_params = self.params()?;
+                }
+                "Sampler" => {
+                    self.string()?;
+                    self.sampler_params = self.params()?;
+                }
+                "WorldBegin" => {
+                    self.state.ctm = Mat4::IDENTITY;
+                    self.named_coordinate_systems
+                        .insert("world".to_string(), Mat4::IDENTITY);
+                }
+                "WorldEnd" => {}
+                "AttributeBegin" | "TransformBegin" => self.stack.push(self.state.clone()),
+                "AttributeEnd" | "TransformEnd" => {
+                    let saved = self
+                        .stack
+                        .pop()
+                        .ok_or_else(|| LoadError::Parse(format!("unmatched {}", directive)))?;
+                    if directive == "TransformEnd" {
+                        self.state.ctm = saved.ctm;
+                    } else {
+                        self.state = saved;
+                    }
+                }
+                "Texture" => {
+                    let name = self.string()?;
+                    let _ty = self.string()?;
+                    let class = self.string()?;
+                    let params = self.params()?;
+                    if let Some(texture) = self.make_texture(&class, &params)? {
+                        self.textures.insert(name, texture);
+                    }
+                }
+                "Material" => {
+                    let ty = self.string()?;
+                    let params = self.params()?;
+                    self.state.material = self.make_material(&ty, &params);
+                }
+                "MakeNamedMaterial" => {
+                    let name = self.string()?;
+                    let params = self.params()?;
+                    let ty = params.string("type").unwrap_or("diffuse").to_string();
+                    let material = self.make_material(&ty, &params);
+                    self.materials.insert(name, material);
+                }
+                "NamedMaterial" => {
+                    let name = self.string()?;
+                    match self.materials.get(&name) {
+                        Some(m) => self.state.material = m.clone(),
+                        None => self.warn(format!("unknown named material '{}'", name)),
+                    }
+                }
+                "AreaLightSource" => {
+                    let ty = self.string()?;
+                    let params = self.params()?;
+                    if ty != "diffuse" {
+                        self.warn(format!("area light '{}' treated as diffuse", ty));
+                    }
+                    let l = params.color("L").unwrap_or(Color::new(1.0, 1.0, 1.0));
+                    self.state.area_light = Some(params.float("scale", 1.0) * l);
+                }
+                "LightSource" => {
+                    let ty = self.string()?;
+                    let params = self.params()?;
+                    self.add_light(&ty, &params);
+                }
+                "Shape" => {
+                    let ty = self.string()?;
+                    let params = self.params()?;
+                    self.add_shape(&ty, &params)?;
+                }
+                "Include" | "Import" => {
+                    let file = self.string()?;
+                    let path = self.base_dir.join(&file);
+                    // Canonical so two spellings of one file are still seen as the same.
+                    let key = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                    if self.including.contains(&key) {
+                        return Err(LoadError::Parse(format!(
+                            "include cycle through '{}'",
+                            file
+                        )));
+                    }
+                    let included = tokenize(&fs::read_to_string(&path)?)?;
+                    let tokens = std::mem::replace(&mut self.tokens, included);
+                    let pos = std::mem::replace(&mut self.pos, 0);
+                    self.including.push(key);
+                    let result = self.parse();
+                    self.including.pop();
+                    self.tokens = tokens;
+                    self.pos = pos;
+                    result?;
+                }
+                _ => self.skip_directive(&directive),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn color_texture(&mut self, params: &ParamSet, name: &str, default: Color) -> Arc<dyn Texture> {
+        if let Some(tex) = params.texture(name) {
+            match self.textures.get(tex) {
+                Some(t) => return t.clone(),
+                None => self.warn(format!("unknown texture '{}'", tex)),
+            }
+        }
+        Arc::new(ConstantTexture::new(params.color(name).unwrap_or(default)))
+    }
+
+    fn make_texture(
+        &mut self,
+        class: &str,
+        params: &ParamSet,
+    ) -> Result<Option<Arc<dyn Texture>>, LoadError> {
+        match class {
+            "constant" => Ok(Some(self.color_texture(
+                params,
+                "value",
+                Color::new(1.0, 1.0, 1.0),
+            ))),
+            "imagemap" => {
+                let filename = params.string("filename").unwrap_or_default();
+                match image::open(self.base_dir.join(filename)) {
+                    Ok(img) => {
+                        let img = img.to_rgb8();
+                        let (nx, ny) = img.dimensions();
+                        Ok(Some(Arc::new(ImageTexture::new(img.into_raw(), nx, ny))))
+                    }
+                    Err(err) => {
+                        self.warn(format!("could not load image '{}': {}", filename, err));
+                        Ok(None)
+                    }
+                }
+            }
+            "checkerboard" => {
+                if params.float("dimension", 2.0) != 2.0 {
+                    self.warn("3D checkerboard approximated in texture space".to_string());
+                }
+                let tex1 = self.color_texture(params, "tex1", Color::new(1.0, 1.0, 1.0));
+                let tex2 = self.color_texture(params, "tex2", Color::new(0.0, 0.0, 0.0));
+                Ok(Some(Arc::new(UvCheckerTexture::new(
+                    tex1,
+                    tex2,
+                    params.float("uscale", 1.0),
+                    params.float("vscale", 1.0),
+                ))))
+            }
+            _ => {
+                self.warn(format!("texture '{}' not supported", class));
+                Ok(None)
+            }
+        }
+    }
+
+    fn make_material(&mut self, ty: &str, params: &ParamSet) -> Arc<dyn Scatter> {
+        let gray = Color::new(0.5, 0.5, 0.5);
+        match ty {
+            "diffuse" | "matte" => {
+                let albedo = if params.get("reflectance").is_some() {
+                    self.color_texture(params, "reflectance", gray)
+                } else {
+                    self.color_texture(params, "Kd", gray)
+                };
+                Arc::new(Lambertian::new(albedo))
+            }
+            "conductor" | "metal" => {
+                let albedo = match (
+                    params.color("reflectance"),
+                    params.color("eta"),
+                    params.color("k"),
+                ) {
+                    (Some(reflectance), _, _) => reflectance,
+                    (None, Some(eta), Some(k)) => Metal::fresnel_reflectance(eta, k),
+                    _ => params
+                        .string("eta")
+                        .and_then(Metal::named_reflectance)
+                        .unwrap_or(Color::new(0.95, 0.64, 0.54)),
+                };
+                let roughness = params.float("roughness", params.float("uroughness", 0.0));
+                Arc::new(Metal::new(albedo, roughness))
+            }
+            "dielectric" | "glass" | "thindielectric" => {
+                let eta = params.float("eta", params.float("index", 1.5));
+                Arc::new(Dielectric::new(eta))
+            }
+            _ => {
+                self.warn(format!("material '{}' approximated as diffuse", ty));
+                let albedo = if params.get("reflectance").is_some() {
+                    self.color_texture(params, "reflectance", gray)
+                } else {
+                    self.color_texture(params, "Kd", gray)
+                };
+                Arc::new(Lambertian::new(albedo))
+            }
+        }
+    }
+
+    fn add_light(&mut self, ty: &str, params: &ParamSet) {
+        let scale = params.float("scale", 1.0);
+        match ty {
+            "infinite" => {
+                if params.string("filename").is_some() {
+                    self.warn("infinite light image map ignored".to_string());
+                }
+                let l = params.color("L").unwrap_or(Color::new(1.0, 1.0, 1.0));
+                self.lights.push(DeferredLight::Infinite(scale * l));
+            }
+            "point" => {
+                let from = params
+                    .points("from")
+                    .and_then(|p| p.first().copied())
+                    .unwrap_or(Point3::new(0.0, 0.0, 0.0));
+                let i = params.color("I").unwrap_or(Color::new(1.0, 1.0, 1.0));
+                let world_from = (handedness() * self.state.ctm).transform_point(from);
+                self.lights
+                    .push(DeferredLight::Point(world_from, scale * i));
+            }
+            _ => self.warn(format!("light source '{}' not supported", ty)),
+        }
+    }
+
+    fn material_for_shape(&self) -> Arc<dyn Scatter> {
+        match self.state.area_light {
+            Some(l) => Arc::new(DiffuseLight::new(ConstantTexture::new(l))),
+            None => self.state.material.clone(),
+        }
+    }
+
+    fn add_shape(&mut self, ty: &str, params: &ParamSet) -> Result<(), LoadError> {
+        let object_to_world = handedness() * self.state.ctm;
+        let mat = self.material_for_shape();
+
+        match ty {
+            "sphere" => {
+                let radius = params.float("radius", 1.0);
+                let placed = if params.get("zmin").is_some()
+                    || params.get("zmax").is_some()
+                    || params.get("phimax").is_some()
+                {
+                    let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), radius, mat)
+                        .with_height_range(
+                            params.float("zmin", -radius),
+                            params.float("zmax", radius),
+                        )
+                        .with_phi_max(params.float("phimax", 360.0));
+                    // pbrt's spheres are z-up with longitude from +x toward +y.
+                    let z_up = Mat4::new([
+                        [-1.0, 0.0, 0.0, 0.0],
+                        [0.0, 0.0, 1.0, 0.0],
+                        [0.0, 1.0, 0.0, 0.0],
+                        [0.0, 0.0, 0.0, 1.0],
+                    ]);
+                    Transform::try_new(Arc::new(sphere), object_to_world * z_up)
+                        .map(|t| Box::new(t) as Box<dyn Hittable>)
+                } else {
+                    let origin = Point3::new(0.0, 0.0, 0.0);
+                    place_sphere(origin, radius, object_to_world, mat)
+                };
+                match placed {
+                    Some(sphere) => self.objects.push(sphere),
+                    None => self.warn("sphere with a singular transform skipped".to_string()),
+                }
+            }
+            "trianglemesh" => {
+                let positions = params
+                    .points("P")
+                    .ok_or_else(|| LoadError::Parse("trianglemesh without P".to_string()))?;
+                let indices: Vec<[usize; 3]> = match params.floats("indices") {
+                    Some(idx) => {
+                        // Numbers are read as floats, so `as usize` would quietly turn -1
+                        // into 0 and 1.5 into 1.
+                        let in_range =
+                            |i: f32| i >= 0.0 && i.fract() == 0.0 && (i as usize) < positions.len();
+                        if !idx.iter().all(|&i| in_range(i)) {
+                            return Err(LoadError::Parse(
+                                "trianglemesh index out of range".to_string(),
+                            ));
+                        }
+                        idx.chunks_exact(3)
+                            .map(|c| [c[0] as usize, c[1] as usize, c[2] as usize])
+                            .collect()
+                    }
+                    None if positions.len() == 3 => vec![[0, 1, 2]],
+                    None => {
+                        return Err(LoadError::Parse("trianglemesh without indices".to_string()))
+                    }
+                };
+                let n = positions.len();
+                let mut mesh = TriangleMesh::new(positions, indices);
+                mesh.normals = params.points("N").filter(|v| v.len() == n);
+                mesh.uvs = params
+                    .floats("uv")
+                    .or_else(|| params.floats("st"))
+                    .map(|f| f.chunks_exact(2).map(|c| (c[0], c[1])).collect::<Vec<_>>())
+                    .filter(|v| v.len() == n);
+                mesh.transform(&object_to_world);
+                self.objects.extend(mesh.triangles(mat));
+            }
+            "plymesh" => {
+                let filename = params
+                    .string("filename")
+                    .ok_or_else(|| LoadError::Parse("plymesh without filename".to_string()))?;
+                let mut mesh = load_ply(self.base_dir.join(filename))?;
+                mesh.transform(&object_to_world);
+                self.objects.extend(mesh.triangles(mat));
+            }
+            _ => self.warn(format!("shape '{}' not supported", ty)),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<PbrtScene, LoadError> {
+        let width = self.film_params.float("xresolution", 1280.0) as u32;
+        let height = self.film_params.float("yresolution", 720.0) as u32;
+        let aspect_ratio = width as f32 / height as f32;
+
+        // pbrt's fov spans the shorter image axis.
+        let fov = self.camera_params.float("fov", 90.0);
+        let vfov = if aspect_ratio >= 1.0 {
+            fov
+        } else {
+            let half = (fov.to_radians() / 2.0).tan() / aspect_ratio;
+            2.0 * half.atan().to_degrees()
+        };
+
+        let world_from_camera = handedness()
+            * self
+                .camera_from_world
+                .inverse()
+                .ok_or_else(|| LoadError::Parse("singular camera transform".to_string()))?;
+        let lookfrom = world_from_camera.transform_point(Point3::new(0.0, 0.0, 0.0));
+        let lookat = world_from_camera.transform_point(Point3::new(0.0, 0.0, 1.0));
+        let vup = world_from_camera.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+
+        // Ray directions are scaled by the focus distance, so only honor pbrt's huge
+        // default when there is a lens for it to matter.
+        let lens_radius = self.camera_params.float("lensradius", 0.0);
+        let focus_dist = if lens_radius > 0.0 {
+            self.camera_params.float("focaldistance", 1.0e6)
+        } else {
+            1.0
+        };
+        let camera = Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            vfov,
+            aspect_ratio,
+            2.0 * lens_radius,
+            focus_dist,
+            self.camera_params.float("shutteropen", 0.0),
+            self.camera_params.float("shutterclose", 1.0),
+        );
+
+        if self.objects.is_empty() {
+            return Err(LoadError::Parse(
+                "scene contains no supported shapes".to_string(),
+            ));
+        }
+        let bounds = self
+            .objects
+            .bounding_box(0.0, 1.0)
+            .unwrap_or_else(|| AABB::new(lookfrom, lookfrom));
+        let extent = (bounds.max() - bounds.min()).length().max(1.0);
+        let center = 0.5 * (bounds.min() + bounds.max());
+
+        for light in self.lights.drain(..) {
+            match light {
+                DeferredLight::Infinite(l) => {
+                    // An emissive sphere enclosing the scene and the camera.
+                    let radius = 10.0 * (extent + (lookfrom - center).length());
+                    self.objects.push(Box::new(Sphere::new(
+                        center,
+                        radius,
+                        Arc::new(DiffuseLight::new(ConstantTexture::new(l))),
+                    )));
+                }
+                DeferredLight::Point(p, i) => {
+                    // A small sphere with the same power as the point light.
+                    let radius = 0.005 * extent;
+                    let radiance = i / (std::f32::consts::PI * radius * radius);
+                    self.objects.push(Box::new(Sphere::new(
+                        p,
+                        radius,
+                        Arc::new(DiffuseLight::new(ConstantTexture::new(radiance))),
+                    )));
+                }
+            }
+        }
+
+        Ok(PbrtScene {
+            world: Box::new(BVH::new(self.objects, 0.0, 1.0)),
+            camera,
+            width,
+            height,
+            samples_per_pixel: self.sampler_params.float("pixelsamples", 16.0) as u32,
+            filename: self.film_params.string("filename").map(|s| s.to_string()),
+            warnings: self.warnings,
+        })
+    }
+}
+
+fn look_at(eye: Point3, look: Point3, up: Vec3) -> Result<Mat4, LoadError> {
+    let dir = (look - eye).normalized();
+    let right = up.normalized().cross(dir);
+    if right.near_zero() {
+        return Err(LoadError::Parse(
+            "LookAt up vector is parallel to view direction".to_string(),
+        ));
+    }
+    let right = right.normalized();
+    let new_up = dir.cross(right);
+    let world_from_camera = Mat4::new([
+        [right.x(), new_up.x(), dir.x(), eye.x()],
+        [right.y(), new_up.y(), dir.y(), eye.y()],
+        [right.z(), new_up.z(), dir.z(), eye.z()],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+    world_from_camera
+        .inverse()
+        .ok_or_else(|| LoadError::Parse("singular LookAt transform".to_string()))
+}
+
+pub fn parse_pbrt(text: &str, base_dir: &Path) -> Result<PbrtScene, LoadError> {
+    let mut parser = Parser::new(tokenize(text)?, base_dir.to_path_buf());
+    parser.parse()?;
+    parser.finish()
+}
+
+pub fn load_pbrt<P: AsRef<Path>>(path: P) -> Result<PbrtScene, LoadError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    parse_pbrt(&text, path.parent().unwrap_or_else(|| Path::new(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraModel;
+    use crate::testing::first_hit;
+
+    fn parse(text: &str) -> PbrtScene {
+        parse_pbrt(text, Path::new(".")).unwrap()
+    }
+
+    #[test]
+    fn image_is_not_mirrored() {
+        // In pbrt this sphere is on the right of the image.
+        let scene = parse(
+            r#"LookAt 0 0 -5  0 0 0  0 1 0
+            Camera "perspective" "float fov" [90]
+            Film "rgb" "integer xresolution" [200] "integer yresolution" [100]
+            WorldBegin
+            AttributeBegin
+              Translate 2 0 0
+              Shape "sphere" "float radius" [1]
+            AttributeEnd"#,
+        );
+        assert_eq!((scene.width, scene.height), (200, 100));
+        let hits = |u: f32| {
+            let ray = scene.camera.ray(u, 0.5).unwrap();
+            scene.world.hit(&ray, 0.001, f32::INFINITY).is_some()
+        };
+        assert!(hits(0.6));
+        assert!(!hits(0.4));
+    }
+
+    #[test]
+    fn transform_matrices_are_column_major() {
+        let scene = parse(
+            r#"WorldBegin
+            Transform [1 0 0 0  0 1 0 0  0 0 1 0  0 3 0 1]
+            Shape "sphere" "float radius" [1]"#,
+        );
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let p = first_hit(scene.world.as_ref(), Point3::new(0.0, 10.0, 0.0), down);
+        assert!((p.unwrap().y() - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn transforms_apply_innermost_first() {
+        // The scale happens in the translated frame, so the triangle stays at z = 5.
+        let scene = parse(
+            r#"WorldBegin
+            Translate 0 0 5
+            Scale 2 2 2
+            Shape "trianglemesh" "point3 P" [-1 -1 0  1 -1 0  0 1 0] "integer indices" [0 1 2]"#,
+        );
+        let world = scene.world.as_ref();
+        let back = Vec3::new(0.0, 0.0, -1.0);
+        let p = first_hit(world, Point3::new(0.0, 0.0, 20.0), back);
+        assert!((p.unwrap().z() - 5.0).abs() < 1e-4);
+        assert!(first_hit(world, Point3::new(0.0, 1.5, 20.0), back).is_some());
+        assert!(first_hit(world, Point3::new(0.0, 2.5, 20.0), back).is_none());
+    }
+
+    #[test]
+    fn unsupported_shapes_warn_and_bad_meshes_fail() {
+        let scene = parse(
+            r#"WorldBegin
+            Shape "cylinder" "float radius" [1]
+            Shape "sphere" "float radius" [1]"#,
+        );
+        assert_eq!(scene.warnings.len(), 1);
+
+        for indices in ["0 1 3", "0 -1 2", "0 1.5 2"] {
+            let bad = format!(
+                r#"WorldBegin
+                Shape "trianglemesh" "point3 P" [0 0 0  1 0 0  0 1 0] "integer indices" [{}]"#,
+                indices
+            );
+            assert!(
+                matches!(parse_pbrt(&bad, Path::new(".")), Err(LoadError::Parse(_))),
+                "indices [{}] should not load",
+                indices
+            );
+        }
+    }
+
+    #[test]
+    fn squashed_spheres_keep_their_shape() {
+        let scene = parse(
+            r#"WorldBegin
+            Scale 1 3 1
+            Shape "sphere" "float radius" [1]"#,
+        );
+        let world = scene.world.as_ref();
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let top = first_hit(world, Point3::new(0.0, 10.0, 0.0), down);
+        assert!((top.unwrap().y() - 3.0).abs() < 1e-4);
+        let side = first_hit(
+            world,
+            Point3::new(0.0, 0.0, 10.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        assert!((side.unwrap().z() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn singular_transforms_warn_instead_of_panicking() {
+        let scene = parse(
+            r#"WorldBegin
+            AttributeBegin
+            Scale 0 1 1
+            Shape "sphere" "float radius" [1]
+            Shape "sphere" "float radius" [1] "float zmax" [0.5]
+            AttributeEnd
+            Shape "sphere" "float radius" [1]"#,
+        );
+        assert_eq!(scene.warnings.len(), 2, "{:?}", scene.warnings);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert!(first_hit(scene.world.as_ref(), Point3::new(0.0, 10.0, 0.0), down).is_some());
+    }
+
+    #[test]
+    fn includes_load_once_each_and_cycles_fail() {
+        let dir = std::env::temp_dir().join(format!("pbrt-include-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, text: &str| fs::write(dir.join(name), text).unwrap();
+        write("ball.pbrt", r#"Shape "sphere" "float radius" [1]"#);
+        write("a.pbrt", r#"Include "b.pbrt""#);
+        write("b.pbrt", r#"Include "./a.pbrt""#);
+
+        // The same file twice in a row is not a cycle.
+        let scene = parse_pbrt(
+            r#"WorldBegin
+            Include "ball.pbrt"
+            Translate 0 0 5
+            Include "ball.pbrt""#,
+            &dir,
+        )
+        .unwrap();
+        let back = Vec3::new(0.0, 0.0, -1.0);
+        let p = first_hit(scene.world.as_ref(), Point3::new(0.0, 0.0, 20.0), back);
+        assert!((p.unwrap().z() - 6.0).abs() < 1e-4);
+
+        let cycle = parse_pbrt(r#"WorldBegin Include "a.pbrt""#, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(cycle, Err(LoadError::Parse(_))));
+    }
+}
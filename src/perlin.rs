@@ -3,7 +3,7 @@ use rand::Rng;
 
 fn permute(p: &mut [usize], n: usize) {
     let mut rng = rand::thread_rng();
-    for i in (0..n as usize).rev() {
+    for i in (0..n).rev() {
         let target = rng.gen_range(0..(i + 1));
         p.swap(i, target);
     }
@@ -18,6 +18,7 @@ fn perlin_generate_perm() -> Vec<usize> {
     p
 }
 
+#[allow(clippy::needless_range_loop)]
 fn trilinear_interp(c: &[[[Vec3; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
     let uu = u * u * (3.0 - 2.0 * u);
     let vv = v * v * (3.0 - 2.0 * v);
@@ -63,6 +64,7 @@ impl Perlin {
         }
     }
 
+    #[allow(clippy::needless_range_loop)]
     pub fn noise(&self, p: Vec3) -> f32 {
         let u = p.x() - p.x().floor();
         let v = p.y() - p.y().floor();
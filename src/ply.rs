@@ -0,0 +1,508 @@
+use super::mesh::{LoadError, TriangleMesh};
+use super::vec3::{Color, Point3, Vec3};
+
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, LoadError> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::Int8),
+            "uchar" | "uint8" => Ok(ScalarType::UInt8),
+            "short" | "int16" => Ok(ScalarType::Int16),
+            "ushort" | "uint16" => Ok(ScalarType::UInt16),
+            "int" | "int32" => Ok(ScalarType::Int32),
+            "uint" | "uint32" => Ok(ScalarType::UInt32),
+            "float" | "float32" => Ok(ScalarType::Float32),
+            "double" | "float64" => Ok(ScalarType::Float64),
+            _ => Err(LoadError::Parse(format!("unknown ply type '{}'", name))),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    // Scale applied when a color channel is stored as an integer.
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1.0 / 255.0,
+            ScalarType::Int16 | ScalarType::UInt16 => 1.0 / 65535.0,
+            ScalarType::Int32 | ScalarType::UInt32 => 1.0 / 4294967295.0,
+            ScalarType::Float32 | ScalarType::Float64 => 1.0,
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, ScalarType),
+    List(String, ScalarType, ScalarType),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Reader<'a> {
+    format: Format,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn next_token(&mut self) -> Result<&'a str, LoadError> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(LoadError::Parse("unexpected end of ply data".to_string()));
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .map_err(|_| LoadError::Parse("invalid ascii ply data".to_string()))
+    }
+
+    fn next_bytes<const N: usize>(&mut self) -> Result<[u8; N], LoadError> {
+        if self.pos + N > self.data.len() {
+            return Err(LoadError::Parse("unexpected end of ply data".to_string()));
+        }
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.data[self.pos..self.pos + N]);
+        self.pos += N;
+        if self.format == Format::BinaryBigEndian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    fn read(&mut self, ty: ScalarType) -> Result<f64, LoadError> {
+        if self.format == Format::Ascii {
+            let token = self.next_token()?;
+            return token
+                .parse::<f64>()
+                .map_err(|_| LoadError::Parse(format!("invalid ply value '{}'", token)));
+        }
+        // Bytes are normalized to little endian by `next_bytes`.
+        Ok(match ty {
+            ScalarType::Int8 => i8::from_le_bytes(self.next_bytes()?) as f64,
+            ScalarType::UInt8 => u8::from_le_bytes(self.next_bytes()?) as f64,
+            ScalarType::Int16 => i16::from_le_bytes(self.next_bytes()?) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes(self.next_bytes()?) as f64,
+            ScalarType::Int32 => i32::from_le_bytes(self.next_bytes()?) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes(self.next_bytes()?) as f64,
+            ScalarType::Float32 => f32::from_le_bytes(self.next_bytes()?) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(self.next_bytes()?),
+        })
+    }
+
+    // A list length or vertex index, which must be a whole number that is not negative.
+    fn read_index(&mut self, ty: ScalarType) -> Result<usize, LoadError> {
+        let value = self.read(ty)?;
+        if value < 0.0 || value.fract() != 0.0 || !value.is_finite() {
+            return Err(LoadError::Parse(format!("invalid ply index {}", value)));
+        }
+        Ok(value as usize)
+    }
+
+    fn skip(&mut self, ty: ScalarType) -> Result<(), LoadError> {
+        if self.format == Format::Ascii {
+            self.next_token()?;
+        } else {
+            if self.pos + ty.size() > self.data.len() {
+                return Err(LoadError::Parse("unexpected end of ply data".to_string()));
+            }
+            self.pos += ty.size();
+        }
+        Ok(())
+    }
+
+    fn skip_property(&mut self, property: &Property) -> Result<(), LoadError> {
+        match property {
+            Property::Scalar(_, ty) => self.skip(*ty),
+            Property::List(_, count_ty, item_ty) => {
+                let count = self.read_index(*count_ty)?;
+                for _ in 0..count {
+                    self.skip(*item_ty)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_header(data: &[u8]) -> Result<(Format, Vec<Element>, usize), LoadError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut pos = 0;
+    let mut first = true;
+
+    loop {
+        let end = data[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| LoadError::Parse("missing end_header".to_string()))?;
+        let line = std::str::from_utf8(&data[pos..pos + end])
+            .map_err(|_| LoadError::Parse("invalid ply header".to_string()))?
+            .trim();
+        pos += end + 1;
+
+        if first {
+            if line != "ply" {
+                return Err(LoadError::Parse("not a ply file".to_string()));
+            }
+            first = false;
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", fmt, _] => {
+                format = Some(match *fmt {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(LoadError::Parse(format!("unknown ply format '{}'", fmt))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| LoadError::Parse(format!("invalid element count '{}'", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| LoadError::Parse("property before element".to_string()))?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    ScalarType::parse(count_ty)?,
+                    ScalarType::parse(item_ty)?,
+                )),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| LoadError::Parse("property before element".to_string()))?
+                .properties
+                .push(Property::Scalar(name.to_string(), ScalarType::parse(ty)?)),
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => {
+                return Err(LoadError::Parse(format!(
+                    "unexpected header line '{}'",
+                    line
+                )))
+            }
+        }
+    }
+
+    let format = format.ok_or_else(|| LoadError::Parse("missing ply format".to_string()))?;
+    Ok((format, elements, pos))
+}
+
+pub fn parse_ply(data: &[u8]) -> Result<TriangleMesh, LoadError> {
+    let (format, elements, body) = parse_header(data)?;
+    let mut reader = Reader {
+        format,
+        data,
+        pos: body,
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    let mut has_normals = false;
+    let mut has_uvs = false;
+    let mut has_colors = false;
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let has = |names: &[&str]| {
+                    element.properties.iter().any(|p| match p {
+                        Property::Scalar(name, _) => names.contains(&name.as_str()),
+                        _ => false,
+                    })
+                };
+                has_normals = has(&["nx"]) && has(&["ny"]) && has(&["nz"]);
+                has_uvs = has(&["u", "s", "texture_u", "texture_s"])
+                    && has(&["v", "t", "texture_v", "texture_t"]);
+                has_colors = has(&["red", "r"]) && has(&["green", "g"]) && has(&["blue", "b"]);
+
+                for _ in 0..element.count {
+                    let mut p = Point3::new(0.0, 0.0, 0.0);
+                    let mut n = Vec3::new(0.0, 0.0, 0.0);
+                    let mut uv = (0.0, 0.0);
+                    let mut c = Color::new(1.0, 1.0, 1.0);
+                    for property in &element.properties {
+                        match property {
+                            Property::Scalar(name, ty) => {
+                                let value = reader.read(*ty)?;
+                                match name.as_str() {
+                                    "x" => p[0] = value as f32,
+                                    "y" => p[1] = value as f32,
+                                    "z" => p[2] = value as f32,
+                                    "nx" => n[0] = value as f32,
+                                    "ny" => n[1] = value as f32,
+                                    "nz" => n[2] = value as f32,
+                                    "u" | "s" | "texture_u" | "texture_s" => uv.0 = value as f32,
+                                    "v" | "t" | "texture_v" | "texture_t" => uv.1 = value as f32,
+                                    "red" | "r" => c[0] = (value * ty.color_scale()) as f32,
+                                    "green" | "g" => c[1] = (value * ty.color_scale()) as f32,
+                                    "blue" | "b" => c[2] = (value * ty.color_scale()) as f32,
+                                    _ => {}
+                                }
+                            }
+                            _ => reader.skip_property(property)?,
+                        }
+                    }
+                    positions.push(p);
+                    normals.push(n);
+                    uvs.push(uv);
+                    colors.push(c);
+                }
+            }
+            "face" => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match property {
+                            Property::List(name, count_ty, item_ty)
+                                if name == "vertex_indices" || name == "vertex_index" =>
+                            {
+                                let count = reader.read_index(*count_ty)?;
+                                let mut face = Vec::with_capacity(count);
+                                for _ in 0..count {
+                                    face.push(reader.read_index(*item_ty)?);
+                                }
+                                // Fan-triangulate polygons.
+                                for i in 1..count.saturating_sub(1) {
+                                    indices.push([face[0], face[i], face[i + 1]]);
+                                }
+                            }
+                            _ => reader.skip_property(property)?,
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        reader.skip_property(property)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(bad) = indices.iter().flatten().find(|&&i| i >= positions.len()) {
+        return Err(LoadError::Parse(format!(
+            "face references vertex {} but only {} vertices exist",
+            bad,
+            positions.len()
+        )));
+    }
+
+    let mut mesh = TriangleMesh::new(positions, indices);
+    if has_normals {
+        mesh.normals = Some(normals);
+    }
+    if has_uvs {
+        mesh.uvs = Some(uvs);
+    }
+    if has_colors {
+        mesh.colors = Some(colors);
+    }
+    Ok(mesh)
+}
+
+pub fn load_ply<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, LoadError> {
+    parse_ply(&fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_point(p: Point3, x: f32, y: f32, z: f32) {
+        assert!(
+            (p - Point3::new(x, y, z)).length() < 1e-6,
+            "expected ({}, {}, {}), got ({}, {}, {})",
+            x,
+            y,
+            z,
+            p.x(),
+            p.y(),
+            p.z()
+        );
+    }
+
+    #[test]
+    fn ascii_quad_is_fanned_into_triangles() {
+        let data = b"ply
+format ascii 1.0
+comment a unit square
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0.5 255 255 255
+4 0 1 2 3
+";
+        let mesh = parse_ply(data).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_point(mesh.positions[3], 0.0, 1.0, 0.5);
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        let colors = mesh.colors.unwrap();
+        assert_point(colors[1], 0.0, 1.0, 0.0);
+        assert!(mesh.normals.is_none() && mesh.uvs.is_none());
+    }
+
+    #[test]
+    fn binary_big_endian() {
+        let mut data = b"ply
+format binary_big_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property double nx
+element face 1
+property list uchar uint vertex_indices
+end_header
+"
+        .to_vec();
+        let vertices = [[1.5f32, -2.0, 3.25], [4.0, 5.0, 6.0], [-7.0, 8.0, 0.125]];
+        for v in vertices.iter() {
+            for c in v {
+                data.extend_from_slice(&c.to_be_bytes());
+            }
+            // A property the loader skips, to check it stays in step.
+            data.extend_from_slice(&9.0f64.to_be_bytes());
+        }
+        data.push(3);
+        for i in [2u32, 0, 1] {
+            data.extend_from_slice(&i.to_be_bytes());
+        }
+
+        let mesh = parse_ply(&data).unwrap();
+        assert_eq!(mesh.positions.len(), 3);
+        assert_point(mesh.positions[0], 1.5, -2.0, 3.25);
+        assert_point(mesh.positions[2], -7.0, 8.0, 0.125);
+        assert_eq!(mesh.indices, vec![[2, 0, 1]]);
+    }
+
+    #[test]
+    fn binary_little_endian_with_uvs() {
+        let mut data = b"ply
+format binary_little_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property float u
+property float v
+element face 1
+property list uchar ushort vertex_indices
+end_header
+"
+        .to_vec();
+        for v in [
+            [0.0f32, 0.0, 0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0, 1.0, 0.0],
+            [0.0, 2.0, 0.0, 0.0, 1.0],
+        ] {
+            for c in v {
+                data.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        data.push(3);
+        for i in [0u16, 1, 2] {
+            data.extend_from_slice(&i.to_le_bytes());
+        }
+
+        let mesh = parse_ply(&data).unwrap();
+        assert_point(mesh.positions[1], 2.0, 0.0, 0.0);
+        assert_eq!(mesh.uvs.unwrap()[2], (0.0, 1.0));
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn truncated_binary_data_is_an_error() {
+        let mut data = b"ply
+format binary_little_endian 1.0
+element vertex 2
+property float x
+property float y
+property float z
+end_header
+"
+        .to_vec();
+        data.extend_from_slice(&1.0f32.to_le_bytes());
+        assert!(matches!(parse_ply(&data), Err(LoadError::Parse(_))));
+    }
+
+    #[test]
+    fn bad_indices_are_rejected() {
+        let header = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+";
+        for face in ["3 0 -1 2", "3 0 1 3", "3 0 1.5 2"] {
+            let data = format!("{}{}\n", header, face);
+            assert!(
+                matches!(parse_ply(data.as_bytes()), Err(LoadError::Parse(_))),
+                "face '{}' should not load",
+                face
+            );
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use super::camera::{look_at_basis, sample_time, CameraModel};
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+
+use std::f32::consts::PI;
+
+// Parallel rays through a `height` tall window centered on `lookfrom`.
+pub struct OrthographicCamera {
+    origin: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    direction: Vec3,
+    time0: f32,
+    time1: f32,
+}
+
+impl OrthographicCamera {
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        height: f32,
+        aspect_ratio: f32,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        Self {
+            origin: lookfrom,
+            horizontal: aspect_ratio * height * u,
+            vertical: height * v,
+            direction: -1.0 * w,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl CameraModel for OrthographicCamera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        Some(Ray::new(
+            self.origin + (u - 0.5) * self.horizontal + (v - 0.5) * self.vertical,
+            self.direction,
+            sample_time(self.time0, self.time1),
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum FisheyeMapping {
+    // Image radius proportional to the angle off axis.
+    Equidistant,
+    // Image radius proportional to sin(angle / 2), preserving solid angle.
+    Equisolid,
+}
+
+// A circular fisheye whose image circle, spanning `fov` degrees, fits the shorter side.
+pub struct FisheyeCamera {
+    origin: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    fov: f32,
+    aspect_ratio: f32,
+    mapping: FisheyeMapping,
+    time0: f32,
+    time1: f32,
+}
+
+impl FisheyeCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        fov: f32,
+        aspect_ratio: f32,
+        mapping: FisheyeMapping,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            fov: fov.to_radians().min(2.0 * PI),
+            aspect_ratio,
+            mapping,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl CameraModel for FisheyeCamera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let (mut x, mut y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        if self.aspect_ratio >= 1.0 {
+            x *= self.aspect_ratio;
+        } else {
+            y /= self.aspect_ratio;
+        }
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * 0.5 * self.fov,
+            FisheyeMapping::Equisolid => 2.0 * (r * (0.25 * self.fov).sin()).asin(),
+        };
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let radial = if r > 0.0 {
+            (x / r) * self.u + (y / r) * self.v
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        };
+        Some(Ray::new(
+            self.origin,
+            sin_theta * radial - cos_theta * self.w,
+            sample_time(self.time0, self.time1),
+        ))
+    }
+}
+
+// Full 360 by 180 degree latitude-longitude panorama centered on the view direction;
+// use a 2:1 image.
+pub struct EquirectangularCamera {
+    origin: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    time0: f32,
+    time1: f32,
+}
+
+impl EquirectangularCamera {
+    pub fn new(lookfrom: Point3, lookat: Point3, vup: Vec3, time0: f32, time1: f32) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl CameraModel for EquirectangularCamera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let phi = 2.0 * PI * (u - 0.5);
+        let latitude = PI * (v - 0.5);
+        let direction =
+            latitude.cos() * (phi.sin() * self.u - phi.cos() * self.w) + latitude.sin() * self.v;
+        Some(Ray::new(
+            self.origin,
+            direction,
+            sample_time(self.time0, self.time1),
+        ))
+    }
+}
+
+// Six 90 degree faces in a 3x2 grid: +X, -X, +Y on the top row and -Y, +Z, -Z below,
+// with axes following the camera (X right, Y up, Z backward); use a 3:2 image.
+pub struct CubemapCamera {
+    origin: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    time0: f32,
+    time1: f32,
+}
+
+impl CubemapCamera {
+    pub fn new(lookfrom: Point3, lookat: Point3, vup: Vec3, time0: f32, time1: f32) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl CameraModel for CubemapCamera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let column = ((3.0 * u) as usize).min(2);
+        let row = if v >= 0.5 { 0 } else { 1 };
+        let a = 2.0 * (3.0 * u - column as f32) - 1.0;
+        let b = 2.0 * (2.0 * v - (1 - row) as f32) - 1.0;
+
+        // (face normal, face right, face up), as seen from inside the cube.
+        let (x, y, z) = (self.u, self.v, self.w);
+        let (forward, right, up) = match row * 3 + column {
+            0 => (x, z, y),
+            1 => (-1.0 * x, -1.0 * z, y),
+            2 => (y, x, z),
+            3 => (-1.0 * y, x, -1.0 * z),
+            4 => (z, -1.0 * x, y),
+            _ => (-1.0 * z, x, y),
+        };
+        Some(Ray::new(
+            self.origin,
+            forward + a * right + b * up,
+            sample_time(self.time0, self.time1),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cameras at the origin looking down -z with y up, so u, v, w are x, y, z.
+    fn view() -> (Point3, Point3, Vec3) {
+        (
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    fn assert_direction(ray: Ray, expected: Vec3) {
+        let d = ray.direction().normalized();
+        assert!((d - expected.normalized()).length() < 1e-4);
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let (from, at, up) = view();
+        let camera = OrthographicCamera::new(from, at, up, 2.0, 2.0, 0.0, 0.0);
+        let corner = camera.ray(0.0, 1.0).unwrap();
+        assert!((corner.origin() - Point3::new(-2.0, 1.0, 0.0)).length() < 1e-5);
+        assert_direction(corner, Vec3::new(0.0, 0.0, -1.0));
+        assert_direction(camera.ray(0.8, 0.1).unwrap(), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn fisheye_angle_grows_with_image_radius() {
+        let (from, at, up) = view();
+        let camera = FisheyeCamera::new(
+            from,
+            at,
+            up,
+            180.0,
+            1.0,
+            FisheyeMapping::Equidistant,
+            0.0,
+            0.0,
+        );
+        assert_direction(camera.ray(0.5, 0.5).unwrap(), Vec3::new(0.0, 0.0, -1.0));
+        // The rim of a 180 degree image circle looks sideways, halfway in at 45 degrees.
+        assert_direction(camera.ray(1.0, 0.5).unwrap(), Vec3::new(1.0, 0.0, 0.0));
+        assert_direction(camera.ray(0.5, 0.75).unwrap(), Vec3::new(0.0, 1.0, -1.0));
+        assert!(camera.ray(0.95, 0.95).is_none());
+    }
+
+    #[test]
+    fn equisolid_fisheye_matches_at_the_rim() {
+        let (from, at, up) = view();
+        let camera = FisheyeCamera::new(
+            from,
+            at,
+            up,
+            120.0,
+            2.0,
+            FisheyeMapping::Equisolid,
+            0.0,
+            0.0,
+        );
+        // The circle fits the shorter, vertical side.
+        let angle = 60f32.to_radians();
+        assert_direction(
+            camera.ray(0.5, 1.0).unwrap(),
+            Vec3::new(0.0, angle.sin(), -angle.cos()),
+        );
+        assert!(camera.ray(1.0, 0.5).is_none());
+    }
+
+    #[test]
+    fn equirectangular_wraps_around_the_viewer() {
+        let (from, at, up) = view();
+        let camera = EquirectangularCamera::new(from, at, up, 0.0, 0.0);
+        assert_direction(camera.ray(0.5, 0.5).unwrap(), Vec3::new(0.0, 0.0, -1.0));
+        assert_direction(camera.ray(0.75, 0.5).unwrap(), Vec3::new(1.0, 0.0, 0.0));
+        assert_direction(camera.ray(0.0, 0.5).unwrap(), Vec3::new(0.0, 0.0, 1.0));
+        assert_direction(camera.ray(0.3, 1.0).unwrap(), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn cubemap_faces_are_laid_out_in_order() {
+        let (from, at, up) = view();
+        let camera = CubemapCamera::new(from, at, up, 0.0, 0.0);
+        let centers = [
+            (1.0 / 6.0, 0.75, Vec3::new(1.0, 0.0, 0.0)),
+            (0.5, 0.75, Vec3::new(-1.0, 0.0, 0.0)),
+            (5.0 / 6.0, 0.75, Vec3::new(0.0, 1.0, 0.0)),
+            (1.0 / 6.0, 0.25, Vec3::new(0.0, -1.0, 0.0)),
+            (0.5, 0.25, Vec3::new(0.0, 0.0, 1.0)),
+            (5.0 / 6.0, 0.25, Vec3::new(0.0, 0.0, -1.0)),
+        ];
+        for (u, v, expected) in centers {
+            assert_direction(camera.ray(u, v).unwrap(), expected);
+        }
+        // The -Z face is what the camera looks at, so it keeps the image orientation.
+        assert_direction(camera.ray(1.0, 0.5).unwrap(), Vec3::new(1.0, 1.0, -1.0));
+    }
+}
@@ -0,0 +1,202 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::material::Scatter;
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+
+use std::sync::Arc;
+
+#[derive(Clone, Copy)]
+pub enum Planar {
+    // The parallelogram spanned by the two edges from the corner.
+    Parallelogram,
+    // The triangle with the corner and the ends of both edges as vertices.
+    Triangle,
+    // The ellipse centered on the corner with the edges as semi-axes.
+    Ellipse,
+}
+
+// A flat shape in any orientation, given by a point `q` and two edge vectors `u` and `v`.
+// The front face is on the side of u x v.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    // Scaled normal that turns a point in the plane into planar coordinates.
+    w: Vec3,
+    normal: Vec3,
+    d: f32,
+    shape: Planar,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Scatter>) -> Self {
+        Self::with_shape(Planar::Parallelogram, q, u, v, mat)
+    }
+
+    pub fn triangle(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Scatter>) -> Self {
+        Self::with_shape(Planar::Triangle, q, u, v, mat)
+    }
+
+    pub fn ellipse(center: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Scatter>) -> Self {
+        Self::with_shape(Planar::Ellipse, center, u, v, mat)
+    }
+
+    pub fn with_shape(shape: Planar, q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Scatter>) -> Self {
+        let n = u.cross(v);
+        let normal = n.normalized();
+        Self {
+            q,
+            u,
+            v,
+            w: n / n.dot(n),
+            normal,
+            d: normal.dot(q),
+            shape,
+            mat,
+        }
+    }
+
+    // Texture coordinates of planar coordinates (alpha, beta) if they fall inside the shape.
+    fn interior(&self, alpha: f32, beta: f32) -> Option<(f32, f32)> {
+        let inside = match self.shape {
+            Planar::Parallelogram => (0.0..=1.0).contains(&alpha) && (0.0..=1.0).contains(&beta),
+            Planar::Triangle => alpha >= 0.0 && beta >= 0.0 && alpha + beta <= 1.0,
+            Planar::Ellipse => alpha * alpha + beta * beta <= 1.0,
+        };
+        match (inside, self.shape) {
+            (false, _) => None,
+            (true, Planar::Ellipse) => Some((0.5 * (alpha + 1.0), 0.5 * (beta + 1.0))),
+            (true, _) => Some((alpha, beta)),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denominator = self.normal.dot(r.direction());
+        // Parallel rays never cross the plane.
+        if denominator.abs() < 1.0e-8 {
+            return None;
+        }
+        let t = (self.d - self.normal.dot(r.origin())) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        let (u, v) = self.interior(alpha, beta)?;
+
+        let mut rec = HitRecord {
+            t,
+            p,
+            normal: self.normal,
+            u,
+            v,
+            mat: self.mat.clone(),
+            front_face: false,
+            color: None,
+            tangent: None,
+        };
+        rec.set_face_normal(r, self.normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        let (min, max) = match self.shape {
+            Planar::Parallelogram => {
+                let corners = [self.q + self.u, self.q + self.v, self.q + self.u + self.v];
+                corners
+                    .iter()
+                    .fold((self.q, self.q), |(min, max), &c| (min.min(c), max.max(c)))
+            }
+            Planar::Triangle => {
+                let (a, b) = (self.q + self.u, self.q + self.v);
+                (self.q.min(a).min(b), self.q.max(a).max(b))
+            }
+            Planar::Ellipse => {
+                let radius = |i: usize| (self.u[i] * self.u[i] + self.v[i] * self.v[i]).sqrt();
+                let extent = Vec3::new(radius(0), radius(1), radius(2));
+                (self.q - extent, self.q + extent)
+            }
+        };
+        // Pad like `Rect` so axis-aligned shapes don't get flat boxes.
+        let padding = Vec3::new(0.0001, 0.0001, 0.0001);
+        Some(AABB::new(min - padding, max + padding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::gray;
+
+    // Shoots at the point with planar coordinates (alpha, beta) from the front side.
+    fn shoot(quad: &Quad, alpha: f32, beta: f32) -> Option<HitRecord> {
+        let target = quad.q + alpha * quad.u + beta * quad.v;
+        let origin = target + 3.0 * quad.normal + Vec3::new(0.2, -0.1, 0.3);
+        let ray = Ray::new(origin, target - origin, 0.0);
+        quad.hit(&ray, 0.001, f32::INFINITY)
+    }
+
+    fn skewed(shape: Planar) -> Quad {
+        Quad::with_shape(
+            shape,
+            Point3::new(1.0, 2.0, 3.0),
+            Vec3::new(2.0, 0.0, 1.0),
+            Vec3::new(0.5, 1.0, -1.0),
+            gray(),
+        )
+    }
+
+    #[test]
+    fn parallelogram_maps_edges_to_texture_coordinates() {
+        let quad = skewed(Planar::Parallelogram);
+        let rec = shoot(&quad, 0.25, 0.75).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-4);
+        assert!((rec.u - 0.25).abs() < 1e-4 && (rec.v - 0.75).abs() < 1e-4);
+        assert!(rec.front_face);
+        assert!(shoot(&quad, 1.1, 0.5).is_none());
+        assert!(shoot(&quad, 0.5, -0.1).is_none());
+    }
+
+    #[test]
+    fn triangle_and_ellipse_cut_the_parallelogram() {
+        let triangle = skewed(Planar::Triangle);
+        assert!(shoot(&triangle, 0.4, 0.5).is_some());
+        assert!(shoot(&triangle, 0.6, 0.5).is_none());
+
+        let ellipse = skewed(Planar::Ellipse);
+        let rec = shoot(&ellipse, -0.6, 0.6).unwrap();
+        assert!((rec.u - 0.2).abs() < 1e-4 && (rec.v - 0.8).abs() < 1e-4);
+        assert!(shoot(&ellipse, 0.75, 0.75).is_none());
+    }
+
+    #[test]
+    fn bounding_boxes_enclose_the_shape() {
+        // Corners of the parallelogram and triangle, and points around the ellipse.
+        let mut points = vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+        points.extend((0..16).map(|i| {
+            let angle = i as f32 / 16.0 * 2.0 * std::f32::consts::PI;
+            (angle.cos(), angle.sin())
+        }));
+        for shape in [Planar::Parallelogram, Planar::Triangle, Planar::Ellipse] {
+            let quad = skewed(shape);
+            let bbox = quad.bounding_box(0.0, 0.0).unwrap();
+            for &(alpha, beta) in &points {
+                if quad.interior(alpha, beta).is_none() {
+                    continue;
+                }
+                let p = quad.q + alpha * quad.u + beta * quad.v;
+                for k in 0..3 {
+                    assert!(p[k] >= bbox.min()[k] && p[k] <= bbox.max()[k]);
+                }
+            }
+        }
+    }
+}
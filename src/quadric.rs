@@ -0,0 +1,776 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::material::Scatter;
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// Shapes other than the capsule are built around the +y axis through `center`; wrap them
+// in a `Transform` to orient them. Angles around the axis (phi) start at +x and turn
+// toward -z, the same way `get_sphere_uv` measures longitude.
+
+fn phi_of(x: f32, z: f32) -> f32 {
+    let phi = (-z).atan2(x);
+    if phi < 0.0 {
+        phi + 2.0 * PI
+    } else {
+        phi
+    }
+}
+
+// Roots of a*t^2 + b*t + c in increasing order.
+fn solve_quadratic(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    if a == 0.0 {
+        return if b == 0.0 {
+            None
+        } else {
+            Some((-c / b, -c / b))
+        };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    // Avoids cancellation between -b and the square root.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+// Nearest root in (t_min, t_max) whose point survives clipping.
+fn nearest_root(
+    roots: &[f32],
+    t_min: f32,
+    t_max: f32,
+    accept: impl Fn(f32) -> bool,
+) -> Option<f32> {
+    roots
+        .iter()
+        .copied()
+        .filter(|&t| t > t_min && t < t_max && accept(t))
+        .fold(None, |best, t| match best {
+            Some(b) if b <= t => Some(b),
+            _ => Some(t),
+        })
+}
+
+// Extent in x and z of the annular sector between two radii swept from phi 0 to `phi_max`.
+pub fn sector_extent(outer: f32, inner: f32, phi_max: f32) -> (f32, f32, f32, f32) {
+    let mut angles = vec![0.0, phi_max];
+    for k in 1..4 {
+        let a = k as f32 * 0.5 * PI;
+        if a < phi_max {
+            angles.push(a);
+        }
+    }
+    let mut extent = (
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+    );
+    let mut include = |x: f32, z: f32| {
+        extent.0 = extent.0.min(x);
+        extent.1 = extent.1.max(x);
+        extent.2 = extent.2.min(z);
+        extent.3 = extent.3.max(z);
+    };
+    for &a in &angles {
+        include(outer * a.cos(), -outer * a.sin());
+    }
+    include(inner * phi_max.cos(), -inner * phi_max.sin());
+    include(inner, 0.0);
+    extent
+}
+
+fn sector_box(center: Point3, outer: f32, inner: f32, phi_max: f32, y0: f32, y1: f32) -> AABB {
+    let (x0, x1, z0, z1) = sector_extent(outer, inner, phi_max);
+    AABB::new(
+        center + Vec3::new(x0, y0, z0),
+        center + Vec3::new(x1, y1, z1),
+    )
+}
+
+fn record(
+    r: &Ray,
+    t: f32,
+    outward_normal: Vec3,
+    u: f32,
+    v: f32,
+    mat: &Arc<dyn Scatter>,
+) -> HitRecord {
+    let mut rec = HitRecord {
+        t,
+        p: r.at(t),
+        normal: outward_normal,
+        u,
+        v,
+        mat: mat.clone(),
+        front_face: false,
+        color: None,
+        tangent: None,
+    };
+    rec.set_face_normal(r, outward_normal);
+    rec
+}
+
+// An open tube between heights `y_min` and `y_max` relative to `center`.
+pub struct Cylinder {
+    center: Point3,
+    radius: f32,
+    y_min: f32,
+    y_max: f32,
+    phi_max: f32,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Cylinder {
+    pub fn new(center: Point3, radius: f32, y_min: f32, y_max: f32, mat: Arc<dyn Scatter>) -> Self {
+        Self {
+            center,
+            radius,
+            y_min: y_min.min(y_max),
+            y_max: y_min.max(y_max),
+            phi_max: 2.0 * PI,
+            mat,
+        }
+    }
+
+    // Sweeps only `degrees` around the axis.
+    pub fn with_phi_max(mut self, degrees: f32) -> Self {
+        self.phi_max = degrees.clamp(0.0, 360.0).to_radians();
+        self
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = r.origin() - self.center;
+        let d = r.direction();
+        let a = d.x() * d.x() + d.z() * d.z();
+        let b = 2.0 * (o.x() * d.x() + o.z() * d.z());
+        let c = o.x() * o.x() + o.z() * o.z() - self.radius * self.radius;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        let t = nearest_root(&[t0, t1], t_min, t_max, |t| {
+            let p = o + t * d;
+            p.y() >= self.y_min && p.y() <= self.y_max && phi_of(p.x(), p.z()) <= self.phi_max
+        })?;
+
+        let p = o + t * d;
+        let normal = Vec3::new(p.x(), 0.0, p.z()) / self.radius;
+        let u = phi_of(p.x(), p.z()) / self.phi_max;
+        let v = (p.y() - self.y_min) / (self.y_max - self.y_min);
+        Some(record(r, t, normal, u, v, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        Some(sector_box(
+            self.center,
+            self.radius,
+            self.radius,
+            self.phi_max,
+            self.y_min,
+            self.y_max,
+        ))
+    }
+}
+
+// A cone with its base of `radius` at `center` and its apex `height` above it, optionally
+// cut off to a frustum between two heights.
+pub struct Cone {
+    center: Point3,
+    radius: f32,
+    height: f32,
+    y_min: f32,
+    y_max: f32,
+    phi_max: f32,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Cone {
+    pub fn new(center: Point3, radius: f32, height: f32, mat: Arc<dyn Scatter>) -> Self {
+        Self {
+            center,
+            radius,
+            height,
+            y_min: 0.0,
+            y_max: height,
+            phi_max: 2.0 * PI,
+            mat,
+        }
+    }
+
+    pub fn with_phi_max(mut self, degrees: f32) -> Self {
+        self.phi_max = degrees.clamp(0.0, 360.0).to_radians();
+        self
+    }
+
+    // Keeps only the part between heights `y_min` and `y_max` above the base.
+    pub fn with_height_range(mut self, y_min: f32, y_max: f32) -> Self {
+        self.y_min = y_min.min(y_max).max(0.0);
+        self.y_max = y_min.max(y_max).min(self.height);
+        self
+    }
+
+    fn radius_at(&self, y: f32) -> f32 {
+        self.radius * (1.0 - y / self.height)
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = r.origin() - self.center;
+        let d = r.direction();
+        let k = (self.radius / self.height) * (self.radius / self.height);
+        let h = self.height - o.y();
+        let a = d.x() * d.x() + d.z() * d.z() - k * d.y() * d.y();
+        let b = 2.0 * (o.x() * d.x() + o.z() * d.z() + k * d.y() * h);
+        let c = o.x() * o.x() + o.z() * o.z() - k * h * h;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        let t = nearest_root(&[t0, t1], t_min, t_max, |t| {
+            let p = o + t * d;
+            p.y() >= self.y_min && p.y() <= self.y_max && phi_of(p.x(), p.z()) <= self.phi_max
+        })?;
+
+        let p = o + t * d;
+        let rho = (p.x() * p.x() + p.z() * p.z()).sqrt().max(f32::EPSILON);
+        let normal = Vec3::new(p.x() / rho, self.radius / self.height, p.z() / rho).normalized();
+        let u = phi_of(p.x(), p.z()) / self.phi_max;
+        let v = (p.y() - self.y_min) / (self.y_max - self.y_min);
+        Some(record(r, t, normal, u, v, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        Some(sector_box(
+            self.center,
+            self.radius_at(self.y_min),
+            self.radius_at(self.y_max),
+            self.phi_max,
+            self.y_min,
+            self.y_max,
+        ))
+    }
+}
+
+// A flat disk facing +y, optionally with a hole of `inner_radius` to make an annulus.
+pub struct Disk {
+    center: Point3,
+    radius: f32,
+    inner_radius: f32,
+    phi_max: f32,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Disk {
+    pub fn new(center: Point3, radius: f32, mat: Arc<dyn Scatter>) -> Self {
+        Self {
+            center,
+            radius,
+            inner_radius: 0.0,
+            phi_max: 2.0 * PI,
+            mat,
+        }
+    }
+
+    pub fn with_inner_radius(mut self, inner_radius: f32) -> Self {
+        self.inner_radius = inner_radius.clamp(0.0, self.radius);
+        self
+    }
+
+    pub fn with_phi_max(mut self, degrees: f32) -> Self {
+        self.phi_max = degrees.clamp(0.0, 360.0).to_radians();
+        self
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = r.origin() - self.center;
+        let d = r.direction();
+        if d.y() == 0.0 {
+            return None;
+        }
+        let t = -o.y() / d.y();
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+        let p = o + t * d;
+        let dist = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        if dist > self.radius || dist < self.inner_radius {
+            return None;
+        }
+        let phi = phi_of(p.x(), p.z());
+        if phi > self.phi_max {
+            return None;
+        }
+
+        let u = phi / self.phi_max;
+        let v = (self.radius - dist) / (self.radius - self.inner_radius);
+        Some(record(r, t, Vec3::new(0.0, 1.0, 0.0), u, v, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        // Padded like `Rect` so the box never has zero thickness.
+        Some(sector_box(
+            self.center,
+            self.radius,
+            self.inner_radius,
+            self.phi_max,
+            -0.0001,
+            0.0001,
+        ))
+    }
+}
+
+// Real roots of x^3 + a*x^2 + b*x + c.
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    let shift = a / 3.0;
+    if r * r < q * q * q {
+        let theta = (r / (q * q * q).sqrt()).clamp(-1.0, 1.0).acos();
+        let s = -2.0 * q.sqrt();
+        (0..3)
+            .map(|k| s * ((theta + 2.0 * std::f64::consts::PI * k as f64) / 3.0).cos() - shift)
+            .collect()
+    } else {
+        let big_a = -r.signum() * (r.abs() + (r * r - q * q * q).sqrt()).cbrt();
+        let big_b = if big_a == 0.0 { 0.0 } else { q / big_a };
+        vec![big_a + big_b - shift]
+    }
+}
+
+// Real roots of x^4 + a*x^3 + b*x^2 + c*x + d by Ferrari's method, polished with Newton
+// steps since the closed form loses precision near double roots.
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Depressed quartic y^4 + p*y^2 + q*y + r with x = y - a/4.
+    let shift = a / 4.0;
+    let p = b - 6.0 * shift * shift;
+    let q = c - 2.0 * b * shift + 8.0 * shift * shift * shift;
+    let r = d - c * shift + b * shift * shift - 3.0 * shift * shift * shift * shift;
+
+    let mut roots = Vec::with_capacity(4);
+    if q.abs() < 1.0e-12 {
+        // Biquadratic.
+        let disc = p * p - 4.0 * r;
+        if disc >= 0.0 {
+            for z in [0.5 * (-p + disc.sqrt()), 0.5 * (-p - disc.sqrt())] {
+                if z >= 0.0 {
+                    roots.push(z.sqrt());
+                    roots.push(-z.sqrt());
+                }
+            }
+        }
+    } else {
+        // Pick a root m > 0 of the resolvent cubic so the quartic splits into two
+        // quadratics y^2 +- sqrt(2m)*y + (p/2 + m -+ q/(2 sqrt(2m))).
+        let m = solve_cubic(p, 0.25 * p * p - r, -0.125 * q * q)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m > 0.0 {
+            let s = (2.0 * m).sqrt();
+            for (sign, k) in [
+                (1.0, 0.5 * p + m - 0.5 * q / s),
+                (-1.0, 0.5 * p + m + 0.5 * q / s),
+            ] {
+                let disc = s * s - 4.0 * k;
+                if disc >= 0.0 {
+                    roots.push(0.5 * (-sign * s + disc.sqrt()));
+                    roots.push(0.5 * (-sign * s - disc.sqrt()));
+                }
+            }
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|y| {
+            let mut x = y - shift;
+            for _ in 0..2 {
+                let f = (((x + a) * x + b) * x + c) * x + d;
+                let df = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+                if df != 0.0 {
+                    x -= f / df;
+                }
+            }
+            x
+        })
+        .collect()
+}
+
+// A ring of tube `minor_radius` around a circle of `major_radius` in the xz plane.
+pub struct Torus {
+    center: Point3,
+    major_radius: f32,
+    minor_radius: f32,
+    phi_max: f32,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3,
+        major_radius: f32,
+        minor_radius: f32,
+        mat: Arc<dyn Scatter>,
+    ) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            phi_max: 2.0 * PI,
+            mat,
+        }
+    }
+
+    pub fn with_phi_max(mut self, degrees: f32) -> Self {
+        self.phi_max = degrees.clamp(0.0, 360.0).to_radians();
+        self
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let length = r.direction().length();
+        let d = r.direction() / length;
+        let mut o = r.origin() - self.center;
+
+        // Start from the bounding sphere so the quartic isn't solved far from the torus.
+        let bound = self.major_radius + self.minor_radius;
+        let half_b = o.dot(d);
+        let c = o.dot(o) - bound * bound;
+        let disc = half_b * half_b - c;
+        if disc < 0.0 {
+            return None;
+        }
+        let start = (-half_b - disc.sqrt()).max(0.0);
+        o += start * d;
+
+        let (ox, oy, oz) = (o.x() as f64, o.y() as f64, o.z() as f64);
+        let (dx, dy, dz) = (d.x() as f64, d.y() as f64, d.z() as f64);
+        let big_r2 = (self.major_radius * self.major_radius) as f64;
+        let small_r2 = (self.minor_radius * self.minor_radius) as f64;
+        let od = ox * dx + oy * dy + oz * dz;
+        let sum = ox * ox + oy * oy + oz * oz - small_r2 - big_r2;
+        let roots = solve_quartic(
+            4.0 * od,
+            2.0 * sum + 4.0 * od * od + 4.0 * big_r2 * dy * dy,
+            4.0 * sum * od + 8.0 * big_r2 * oy * dy,
+            sum * sum - 4.0 * big_r2 * (small_r2 - oy * oy),
+        );
+        let roots: Vec<f32> = roots
+            .into_iter()
+            .map(|s| (s as f32 + start) / length)
+            .collect();
+        let t = nearest_root(&roots, t_min, t_max, |t| {
+            let p = r.origin() - self.center + t * r.direction();
+            phi_of(p.x(), p.z()) <= self.phi_max
+        })?;
+
+        let p = r.origin() - self.center + t * r.direction();
+        let phi = phi_of(p.x(), p.z());
+        let ring = self.major_radius * Vec3::new(phi.cos(), 0.0, -phi.sin());
+        let normal = (p - ring).normalized();
+        // Around the tube, starting at the outer equator and heading up.
+        let rho = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        let mut theta = p.y().atan2(rho - self.major_radius);
+        if theta < 0.0 {
+            theta += 2.0 * PI;
+        }
+        let u = phi / self.phi_max;
+        let v = theta / (2.0 * PI);
+        Some(record(r, t, normal, u, v, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        Some(sector_box(
+            self.center,
+            self.major_radius + self.minor_radius,
+            (self.major_radius - self.minor_radius).max(0.0),
+            self.phi_max,
+            -self.minor_radius,
+            self.minor_radius,
+        ))
+    }
+}
+
+// A cylinder between two arbitrary end points closed off by hemispheres.
+pub struct Capsule {
+    p0: Point3,
+    axis: Vec3,
+    length: f32,
+    radius: f32,
+    // Directions of phi = 0 and phi = 90 degrees around the axis.
+    tangent: Vec3,
+    bitangent: Vec3,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Capsule {
+    pub fn new(p0: Point3, p1: Point3, radius: f32, mat: Arc<dyn Scatter>) -> Self {
+        let length = (p1 - p0).length();
+        let axis = if length > 0.0 {
+            (p1 - p0) / length
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        let helper = if axis.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let tangent = axis.cross(helper).cross(axis).normalized();
+        let bitangent = tangent.cross(axis);
+        Self {
+            p0,
+            axis,
+            length,
+            radius,
+            tangent,
+            bitangent,
+            mat,
+        }
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = r.origin() - self.p0;
+        let d = r.direction();
+        let height = |t: f32| (o + t * d).dot(self.axis);
+
+        // Side: the distance to the axis equals the radius.
+        let o_perp = o - o.dot(self.axis) * self.axis;
+        let d_perp = d - d.dot(self.axis) * self.axis;
+        let side = solve_quadratic(
+            d_perp.dot(d_perp),
+            2.0 * o_perp.dot(d_perp),
+            o_perp.dot(o_perp) - self.radius * self.radius,
+        )
+        .and_then(|(t0, t1)| {
+            nearest_root(&[t0, t1], t_min, t_max, |t| {
+                (0.0..=self.length).contains(&height(t))
+            })
+        });
+
+        // Caps: each sphere only counts beyond its end of the side.
+        let cap = |center: Vec3, below: bool| {
+            let oc = o - center;
+            let (t0, t1) = solve_quadratic(
+                d.dot(d),
+                2.0 * oc.dot(d),
+                oc.dot(oc) - self.radius * self.radius,
+            )?;
+            nearest_root(&[t0, t1], t_min, t_max, |t| {
+                if below {
+                    height(t) < 0.0
+                } else {
+                    height(t) > self.length
+                }
+            })
+        };
+        let bottom = cap(Vec3::new(0.0, 0.0, 0.0), true);
+        let top = cap(self.length * self.axis, false);
+
+        let t = [side, bottom, top]
+            .into_iter()
+            .flatten()
+            .fold(f32::INFINITY, f32::min);
+        if !t.is_finite() {
+            return None;
+        }
+
+        let p = o + t * d;
+        let h = p.dot(self.axis);
+        let closest = h.clamp(0.0, self.length) * self.axis;
+        let normal = (p - closest) / self.radius;
+        let mut phi = normal.dot(self.bitangent).atan2(normal.dot(self.tangent));
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        // v runs by arc length from the bottom pole over the side to the top pole.
+        let quarter = 0.5 * PI * self.radius;
+        let s = if h < 0.0 {
+            quarter - self.radius * (-h / self.radius).clamp(-1.0, 1.0).asin()
+        } else if h > self.length {
+            quarter
+                + self.length
+                + self.radius * ((h - self.length) / self.radius).clamp(-1.0, 1.0).asin()
+        } else {
+            quarter + h
+        };
+        let u = phi / (2.0 * PI);
+        let v = s / (2.0 * quarter + self.length);
+        Some(record(r, t, normal, u, v, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        let p1 = self.p0 + self.length * self.axis;
+        Some(AABB::new(
+            self.p0.min(p1) - extent,
+            self.p0.max(p1) + extent,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::gray;
+
+    fn hit_t(shape: &dyn Hittable, origin: Point3, direction: Vec3) -> Option<f32> {
+        let ray = Ray::new(origin, direction, 0.0);
+        shape.hit(&ray, 0.001, f32::INFINITY).map(|rec| rec.t)
+    }
+
+    fn assert_near(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("expected a hit");
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{} vs {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn quartic_finds_all_real_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let mut roots = solve_quartic(-10.0, 35.0, -50.0, 24.0);
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+        // x^4 + 1 has none.
+        assert!(solve_quartic(0.0, 0.0, 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn torus_hits_the_near_side_of_the_tube() {
+        let torus = Torus::new(Point3::new(0.0, 0.0, 0.0), 2.0, 0.5, gray());
+        assert_near(
+            hit_t(
+                &torus,
+                Point3::new(-5.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+            ),
+            1.25,
+        );
+        assert_near(
+            hit_t(
+                &torus,
+                Point3::new(0.0, 5.0, -2.0),
+                Vec3::new(0.0, -1.0, 0.0),
+            ),
+            4.5,
+        );
+        // Straight through the hole.
+        assert!(hit_t(
+            &torus,
+            Point3::new(0.0, 5.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0)
+        )
+        .is_none());
+        // A grazing ray along the top of the tube.
+        assert_near(
+            hit_t(
+                &torus,
+                Point3::new(-5.0, 0.5, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+            ),
+            3.0,
+        );
+    }
+
+    #[test]
+    fn cylinder_and_cone_clip_to_their_height_and_sweep() {
+        let cylinder = Cylinder::new(Point3::new(0.0, 0.0, 0.0), 1.0, 0.0, 2.0, gray());
+        assert_near(
+            hit_t(
+                &cylinder,
+                Point3::new(-5.0, 1.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+            ),
+            4.0,
+        );
+        assert!(hit_t(
+            &cylinder,
+            Point3::new(-5.0, 3.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0)
+        )
+        .is_none());
+        // Half a sweep keeps the -z side, so a ray from +z only meets the far wall.
+        let half =
+            Cylinder::new(Point3::new(0.0, 0.0, 0.0), 1.0, 0.0, 2.0, gray()).with_phi_max(180.0);
+        assert_near(
+            hit_t(&half, Point3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 0.0, -1.0)),
+            6.0,
+        );
+
+        let cone = Cone::new(Point3::new(0.0, 0.0, 0.0), 1.0, 2.0, gray());
+        assert_near(
+            hit_t(&cone, Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            4.5,
+        );
+        let frustum =
+            Cone::new(Point3::new(0.0, 0.0, 0.0), 1.0, 2.0, gray()).with_height_range(0.0, 0.5);
+        assert!(hit_t(
+            &frustum,
+            Point3::new(-5.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn annulus_lets_rays_through_its_hole() {
+        let disk = Disk::new(Point3::new(0.0, 1.0, 0.0), 1.0, gray()).with_inner_radius(0.5);
+        assert_near(
+            hit_t(&disk, Point3::new(0.7, 3.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+            2.0,
+        );
+        assert!(hit_t(&disk, Point3::new(0.3, 3.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).is_none());
+        assert!(hit_t(&disk, Point3::new(1.2, 3.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn capsule_caps_are_hemispheres() {
+        let capsule = Capsule::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+            0.5,
+            gray(),
+        );
+        assert_near(
+            hit_t(
+                &capsule,
+                Point3::new(0.0, 5.0, 0.0),
+                Vec3::new(0.0, -1.0, 0.0),
+            ),
+            2.5,
+        );
+        assert_near(
+            hit_t(
+                &capsule,
+                Point3::new(-5.0, 1.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+            ),
+            4.5,
+        );
+        // Just below the bottom end the cap has curved inward.
+        let below: f32 = -0.3;
+        let expected = 5.0 - (0.25 - below * below).sqrt();
+        assert_near(
+            hit_t(
+                &capsule,
+                Point3::new(-5.0, below, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+            ),
+            expected,
+        );
+    }
+}
@@ -75,6 +75,8 @@ impl Hittable for Rect {
                     v,
                     mat: self.mat.clone(),
                     front_face: false,
+                    color: None,
+                    tangent: None,
                 };
                 rec.set_face_normal(r, normal);
 
@@ -101,3 +103,28 @@ impl Hittable for Rect {
         Some(AABB::new(min, max))
     }
 }
+
+// Reverses which side of a surface counts as its front, e.g. to make a `Rect` on the
+// low side of a box face outward.
+pub struct FlipFace<H: Hittable> {
+    hitable: H,
+}
+
+impl<H: Hittable> FlipFace<H> {
+    pub fn new(hitable: H) -> Self {
+        Self { hitable }
+    }
+}
+
+impl<H: Hittable> Hittable for FlipFace<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hitable.hit(r, t_min, t_max).map(|mut rec| {
+            rec.front_face = !rec.front_face;
+            rec
+        })
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
+        self.hitable.bounding_box(time0, time1)
+    }
+}
@@ -95,6 +95,8 @@ impl Hittable for Rotate {
             -self.sin_theta * r.direction()[a_axis] + self.cos_theta * r.direction()[b_axis];
         let rotated_ray = Ray::new(origin, direction, r.time());
         self.hitable.hit(&rotated_ray, t_min, t_max).map(|mut hit| {
+            // A rotation keeps which side the ray is on, so the child's `front_face` stands
+            // and its normal, already facing the ray, only needs turning.
             let mut p = hit.p;
             let mut normal = hit.normal;
             p[a_axis] = self.cos_theta * hit.p[a_axis] - self.sin_theta * hit.p[b_axis];
@@ -104,12 +106,18 @@ impl Hittable for Rotate {
             normal[b_axis] =
                 self.sin_theta * hit.normal[a_axis] + self.cos_theta * hit.normal[b_axis];
             hit.p = p;
-            hit.set_face_normal(&rotated_ray, normal);
+            hit.tangent = hit.tangent.map(|t| {
+                let mut tangent = t;
+                tangent[a_axis] = self.cos_theta * t[a_axis] - self.sin_theta * t[b_axis];
+                tangent[b_axis] = self.sin_theta * t[a_axis] + self.cos_theta * t[b_axis];
+                tangent
+            });
+            hit.normal = normal;
             hit
         })
     }
 
     fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
-        self.bbox.clone()
+        self.bbox
     }
 }
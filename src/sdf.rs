@@ -0,0 +1,418 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::material::Scatter;
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+
+use std::sync::Arc;
+
+// Signed distance to a surface, negative inside. Sphere tracing needs it to never
+// overestimate the true distance; closures can be used directly as fields.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: Point3) -> f32;
+}
+
+impl<F: Fn(Point3) -> f32 + Send + Sync> Sdf for F {
+    fn distance(&self, p: Point3) -> f32 {
+        self(p)
+    }
+}
+
+pub struct SdfSphere {
+    center: Point3,
+    radius: f32,
+}
+
+impl SdfSphere {
+    pub fn new(center: Point3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Point3) -> f32 {
+        (p - self.center).length() - self.radius
+    }
+}
+
+// An axis-aligned box of the given half extents whose edges are rounded off by `radius`.
+pub struct SdfBox {
+    center: Point3,
+    half_extent: Vec3,
+    radius: f32,
+}
+
+impl SdfBox {
+    pub fn new(center: Point3, half_extent: Vec3, radius: f32) -> Self {
+        Self {
+            center,
+            half_extent,
+            radius,
+        }
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: Point3) -> f32 {
+        let rounding = Vec3::new(self.radius, self.radius, self.radius);
+        let p = p - self.center;
+        let q = Vec3::new(p.x().abs(), p.y().abs(), p.z().abs()) - self.half_extent + rounding;
+        let outside = q.max(Vec3::new(0.0, 0.0, 0.0)).length();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside - self.radius
+    }
+}
+
+// A ring in the xz plane, like `quadric::Torus`.
+pub struct SdfTorus {
+    center: Point3,
+    major_radius: f32,
+    minor_radius: f32,
+}
+
+impl SdfTorus {
+    pub fn new(center: Point3, major_radius: f32, minor_radius: f32) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: Point3) -> f32 {
+        let p = p - self.center;
+        let ring = (p.x() * p.x() + p.z() * p.z()).sqrt() - self.major_radius;
+        (ring * ring + p.y() * p.y()).sqrt() - self.minor_radius
+    }
+}
+
+pub struct SdfCapsule {
+    a: Point3,
+    b: Point3,
+    radius: f32,
+}
+
+impl SdfCapsule {
+    pub fn new(a: Point3, b: Point3, radius: f32) -> Self {
+        Self { a, b, radius }
+    }
+}
+
+impl Sdf for SdfCapsule {
+    fn distance(&self, p: Point3) -> f32 {
+        let pa = p - self.a;
+        let ba = self.b - self.a;
+        let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+        (pa - h * ba).length() - self.radius
+    }
+}
+
+// Union whose seams are filleted over a width of about `k`.
+pub struct SmoothUnion {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f32,
+}
+
+impl SmoothUnion {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f32) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: Point3) -> f32 {
+        let (da, db) = (self.a.distance(p), self.b.distance(p));
+        if self.k <= 0.0 {
+            return da.min(db);
+        }
+        // Polynomial smooth minimum.
+        let h = (0.5 + 0.5 * (db - da) / self.k).clamp(0.0, 1.0);
+        db + (da - db) * h - self.k * h * (1.0 - h)
+    }
+}
+
+// Tiles space with copies of a field, one per cell of size `period` centered on the
+// origin. The field should fit inside a cell, and the `SdfShape` bounds limit how many
+// copies appear.
+pub struct Repeat {
+    sdf: Arc<dyn Sdf>,
+    period: Vec3,
+}
+
+impl Repeat {
+    pub fn new(sdf: Arc<dyn Sdf>, period: Vec3) -> Self {
+        Self { sdf, period }
+    }
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: Point3) -> f32 {
+        let wrap = |x: f32, period: f32| {
+            if period > 0.0 {
+                x - period * (x / period).round()
+            } else {
+                x
+            }
+        };
+        self.sdf.distance(Point3::new(
+            wrap(p.x(), self.period.x()),
+            wrap(p.y(), self.period.y()),
+            wrap(p.z(), self.period.z()),
+        ))
+    }
+}
+
+// The power-n Mandelbulb fractal scaled to fit a sphere of about `scale`. Its distance
+// is an estimate, so shapes using it usually need a reduced step scale.
+pub struct Mandelbulb {
+    center: Point3,
+    scale: f32,
+    power: f32,
+    iterations: u32,
+}
+
+impl Mandelbulb {
+    pub fn new(center: Point3, scale: f32, power: f32, iterations: u32) -> Self {
+        Self {
+            center,
+            scale,
+            power,
+            iterations,
+        }
+    }
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: Point3) -> f32 {
+        let c = (p - self.center) / self.scale;
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.length();
+        for _ in 0..self.iterations {
+            if r > 2.0 {
+                break;
+            }
+            let theta = (z.y() / r).clamp(-1.0, 1.0).acos() * self.power;
+            let phi = z.z().atan2(z.x()) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+            let zr = r.powf(self.power);
+            z =
+                zr * Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ) + c;
+            r = z.length();
+        }
+        if r < 1.0e-6 {
+            return 0.0;
+        }
+        self.scale * 0.5 * r.ln() * r / dr
+    }
+}
+
+// Renders a distance field by sphere tracing. `bounds` must contain the whole surface;
+// only the part of a ray inside it is marched, and it is what the `BVH` sees.
+pub struct SdfShape {
+    sdf: Arc<dyn Sdf>,
+    bounds: AABB,
+    mat: Arc<dyn Scatter>,
+    max_steps: u32,
+    epsilon: f32,
+    step_scale: f32,
+}
+
+impl SdfShape {
+    pub fn new(sdf: Arc<dyn Sdf>, bounds: AABB, mat: Arc<dyn Scatter>) -> Self {
+        Self {
+            sdf,
+            bounds,
+            mat,
+            max_steps: 256,
+            epsilon: 1.0e-4,
+            step_scale: 1.0,
+        }
+    }
+
+    // Distance below which the surface counts as hit, also used to estimate normals.
+    pub fn with_epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    // Fraction of the distance bound taken each step, below 1 for fields that overestimate.
+    pub fn with_step_scale(mut self, step_scale: f32, max_steps: u32) -> Self {
+        self.step_scale = step_scale;
+        self.max_steps = max_steps;
+        self
+    }
+
+    // Parameter range where the ray is inside `bounds`.
+    fn clip(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let (mut t0, mut t1) = (t_min, t_max);
+        for a in 0..3 {
+            let inv_d = 1.0 / r.direction()[a];
+            let mut near = (self.bounds.min()[a] - r.origin()[a]) * inv_d;
+            let mut far = (self.bounds.max()[a] - r.origin()[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+            if t1 < t0 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+
+    // Gradient by the tetrahedron technique, four evaluations instead of six.
+    fn normal(&self, p: Point3) -> Vec3 {
+        let h = self.epsilon;
+        let k = [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ];
+        let n = k.iter().fold(Vec3::new(0.0, 0.0, 0.0), |n, &k| {
+            n + self.sdf.distance(p + h * k) * k
+        });
+        if n.near_zero() {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            n.normalized()
+        }
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (mut t, t_end) = self.clip(r, t_min, t_max)?;
+        let speed = r.direction().length();
+        // March along whichever side of the surface the ray starts on. A ray that starts
+        // on the surface, such as one leaving an earlier hit, first steps off it so it
+        // doesn't hit its own starting point again.
+        let mut side = 0.0;
+        for _ in 0..self.max_steps {
+            let p = r.at(t);
+            let distance = self.sdf.distance(p);
+            if side == 0.0 {
+                if distance.abs() < self.epsilon {
+                    t += self.epsilon / speed;
+                    if t > t_end {
+                        return None;
+                    }
+                    continue;
+                }
+                side = distance.signum();
+            }
+            let d = side * distance;
+            if d < self.epsilon {
+                let outward_normal = self.normal(p);
+                let mut rec = HitRecord {
+                    t,
+                    p,
+                    normal: outward_normal,
+                    u: 0.0,
+                    v: 0.0,
+                    mat: self.mat.clone(),
+                    front_face: false,
+                    color: None,
+                    tangent: None,
+                };
+                rec.set_face_normal(r, outward_normal);
+                return Some(rec);
+            }
+            t += self.step_scale * d / speed;
+            if t > t_end {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::gray;
+
+    fn shape(sdf: impl Sdf + 'static) -> SdfShape {
+        let bounds = AABB::new(Point3::new(-3.0, -3.0, -3.0), Point3::new(3.0, 3.0, 3.0));
+        SdfShape::new(Arc::new(sdf), bounds, gray())
+    }
+
+    fn hit(shape: &SdfShape, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        shape.hit(&Ray::new(origin, direction, 0.0), 0.001, f32::INFINITY)
+    }
+
+    #[test]
+    fn sphere_tracing_finds_the_surface() {
+        let ball = shape(SdfSphere::new(Point3::new(0.0, 0.0, 0.0), 1.0));
+        let rec = hit(&ball, Point3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-3);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-2);
+        // From inside the ray marches to the far wall.
+        let rec = hit(&ball, Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-3);
+        assert!(!rec.front_face);
+        assert!(hit(&ball, Point3::new(-5.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn primitive_distances() {
+        let rounded = SdfBox::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 1.0), 0.5);
+        assert!((rounded.distance(Point3::new(3.0, 0.0, 0.0)) - 2.0).abs() < 1e-5);
+        assert!((rounded.distance(Point3::new(0.0, 0.0, 0.0)) + 1.0).abs() < 1e-5);
+        // Corners are rounded off by the radius.
+        let corner = Point3::new(0.5, 1.5, 0.5) + Vec3::new(1.0, 1.0, 1.0).normalized();
+        assert!((rounded.distance(corner) - 0.5).abs() < 1e-5);
+
+        let torus = SdfTorus::new(Point3::new(0.0, 0.0, 0.0), 2.0, 0.5);
+        assert!((torus.distance(Point3::new(0.0, 0.0, 0.0)) - 1.5).abs() < 1e-5);
+        let capsule = SdfCapsule::new(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 2.0, 0.0), 0.5);
+        assert!((capsule.distance(Point3::new(0.0, 4.0, 0.0)) - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn combinators_reshape_fields() {
+        let a: Arc<dyn Sdf> = Arc::new(SdfSphere::new(Point3::new(-1.0, 0.0, 0.0), 1.0));
+        let b: Arc<dyn Sdf> = Arc::new(SdfSphere::new(Point3::new(1.0, 0.0, 0.0), 1.0));
+        let sharp = SmoothUnion::new(a.clone(), b.clone(), 0.0);
+        let smooth = SmoothUnion::new(a, b, 0.5);
+        let seam = Point3::new(0.0, 0.5, 0.0);
+        // The fillet fills in the crease between the two balls.
+        assert!(smooth.distance(seam) < sharp.distance(seam));
+        let far = Point3::new(-3.0, 0.0, 0.0);
+        assert!((smooth.distance(far) - sharp.distance(far)).abs() < 1e-5);
+
+        let tiled = Repeat::new(
+            Arc::new(SdfSphere::new(Point3::new(0.0, 0.0, 0.0), 0.25)),
+            Vec3::new(1.0, 0.0, 1.0),
+        );
+        // Every whole x and z holds a copy, but y is left alone.
+        assert!((tiled.distance(Point3::new(3.0, 0.0, -2.0)) + 0.25).abs() < 1e-5);
+        assert!((tiled.distance(Point3::new(3.0, 1.0, -2.0)) - 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rays_leaving_the_surface_do_not_hit_it_again() {
+        let ball = shape(SdfSphere::new(Point3::new(0.0, 0.0, 0.0), 1.0)).with_epsilon(1.0e-3);
+        let on_surface = Point3::new(0.0, 1.0, 0.0);
+        let leave =
+            |direction: Vec3| ball.hit(&Ray::new(on_surface, direction, 0.0), 0.0, f32::INFINITY);
+        assert!(leave(Vec3::new(1.0, 1.0, 0.0)).is_none());
+        // Heading inward, the next crossing is the far wall.
+        let rec = leave(Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-2 && !rec.front_face);
+    }
+}
@@ -0,0 +1,153 @@
+use rand::Rng;
+
+enum Curve {
+    Box,
+    Trapezoid { opening: f32, closing: f32 },
+    // Running sum of equal-width bins, normalized to end at 1.
+    Tabulated(Vec<f32>),
+}
+
+// How the shutter distributes exposure over [time0, time1]. A rolling shutter reads the
+// sensor out row by row from the top, so each row sees a window shifted later in time.
+pub struct Shutter {
+    curve: Curve,
+    readout: f32,
+}
+
+impl Default for Shutter {
+    // An ideal global shutter: fully open for the whole interval.
+    fn default() -> Self {
+        Self {
+            curve: Curve::Box,
+            readout: 0.0,
+        }
+    }
+}
+
+impl Shutter {
+    // Spends the first `opening` and last `closing` fractions of the exposure ramping
+    // linearly between closed and fully open, like a mechanical leaf shutter.
+    pub fn trapezoid(opening: f32, closing: f32) -> Self {
+        assert!(
+            opening >= 0.0 && closing >= 0.0 && opening + closing <= 1.0,
+            "shutter ramps must fit in the exposure"
+        );
+        Self {
+            curve: Curve::Trapezoid { opening, closing },
+            ..Self::default()
+        }
+    }
+
+    // Relative openness over the exposure, as evenly spaced steps.
+    pub fn custom(weights: &[f32]) -> Self {
+        let mut cdf = Vec::with_capacity(weights.len());
+        let mut sum = 0.0;
+        for w in weights {
+            sum += w.max(0.0);
+            cdf.push(sum);
+        }
+        assert!(sum > 0.0, "shutter curve is never open");
+        cdf.iter_mut().for_each(|c| *c /= sum);
+        Self {
+            curve: Curve::Tabulated(cdf),
+            ..Self::default()
+        }
+    }
+
+    // Makes the shutter roll: reading out the whole frame takes `readout` of the
+    // interval, leaving each row exposed for the rest. Times stay in [time0, time1] so
+    // bounding volumes built for that interval remain valid.
+    pub fn rolling(mut self, readout: f32) -> Self {
+        self.readout = readout.clamp(0.0, 1.0);
+        self
+    }
+
+    // Fraction of the exposure window in [0, 1] drawn from the shutter curve.
+    fn sample_curve(&self, s: f32) -> f32 {
+        match &self.curve {
+            Curve::Box => s,
+            Curve::Trapezoid { opening, closing } => {
+                let (a, b) = (*opening, *closing);
+                let area = 1.0 - 0.5 * (a + b);
+                let s = s * area;
+                if s < 0.5 * a {
+                    a * (s / (0.5 * a)).sqrt()
+                } else if s < area - 0.5 * b {
+                    s + 0.5 * a
+                } else {
+                    1.0 - b * ((area - s) / (0.5 * b)).sqrt()
+                }
+            }
+            Curve::Tabulated(cdf) => {
+                let i = cdf.partition_point(|&c| c <= s).min(cdf.len() - 1);
+                let lo = if i == 0 { 0.0 } else { cdf[i - 1] };
+                let within = if cdf[i] > lo {
+                    (s - lo) / (cdf[i] - lo)
+                } else {
+                    0.0
+                };
+                (i as f32 + within) / cdf.len() as f32
+            }
+        }
+    }
+
+    // Time for a sample through image row `v` (0 at the bottom, 1 at the top).
+    pub fn sample(&self, time0: f32, time1: f32, v: f32) -> f32 {
+        // A zero-length shutter freezes the scene at `time0`.
+        if time1 <= time0 {
+            return time0;
+        }
+        let length = time1 - time0;
+        let start = time0 + self.readout * (1.0 - v.clamp(0.0, 1.0)) * length;
+        let window = (1.0 - self.readout) * length;
+        start + window * self.sample_curve(rand::thread_rng().gen())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trapezoid_inverts_its_cdf() {
+        let (a, b) = (0.2, 0.4);
+        let shutter = Shutter::trapezoid(a, b);
+        let area = 1.0 - 0.5 * (a + b);
+        let cdf = |x: f32| {
+            if x < a {
+                0.5 * x * x / a / area
+            } else if x < 1.0 - b {
+                (x - 0.5 * a) / area
+            } else {
+                1.0 - 0.5 * (1.0 - x) * (1.0 - x) / b / area
+            }
+        };
+        for i in 0..=20 {
+            let x = i as f32 / 20.0;
+            assert!((shutter.sample_curve(cdf(x)) - x).abs() < 1e-4, "{}", x);
+        }
+    }
+
+    #[test]
+    fn tabulated_curves_skip_closed_steps() {
+        let shutter = Shutter::custom(&[0.0, 1.0, 0.0, 3.0]);
+        assert!((shutter.sample_curve(0.1) - 0.35).abs() < 1e-6);
+        assert!((shutter.sample_curve(0.5) - (3.0 + 1.0 / 3.0) / 4.0).abs() < 1e-6);
+        for i in 0..100 {
+            let x = shutter.sample_curve(i as f32 / 100.0);
+            assert!((0.25..0.5).contains(&x) || (0.75..=1.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn rolling_shutter_exposes_lower_rows_later() {
+        let shutter = Shutter::default().rolling(0.5);
+        for _ in 0..100 {
+            let top = shutter.sample(2.0, 4.0, 1.0);
+            let bottom = shutter.sample(2.0, 4.0, 0.0);
+            assert!((2.0..=3.0).contains(&top));
+            assert!((3.0..=4.0).contains(&bottom));
+        }
+        assert_eq!(shutter.sample(1.0, 1.0, 0.3), 1.0);
+    }
+}
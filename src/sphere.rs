@@ -1,13 +1,16 @@
 use super::aabb::AABB;
 use super::hittable::{HitRecord, Hittable};
 use super::material::Scatter;
+use super::quadric::sector_extent;
 use super::ray::Ray;
 use super::vec3::{Point3, Vec3};
 use std::f32;
 
 use std::sync::Arc;
 
-fn get_sphere_uv(p: Vec3) -> (f32, f32) {
+// Longitude from the -x direction and latitude from the south pole, each mapped to
+// [0, 1], for a point on the unit sphere.
+pub fn get_sphere_uv(p: Vec3) -> (f32, f32) {
     // let phi = (-p.z()).atan2(p.x()) + f32::consts::PI;
     let phi = f32::atan2(-p.z(), p.x()) + f32::consts::PI;
     let theta = (-p.y()).acos();
@@ -22,10 +25,25 @@ fn get_sphere_uv(p: Vec3) -> (f32, f32) {
     (u, v)
 }
 
+#[derive(Clone, Copy)]
+pub enum SphereUv {
+    // Textures read correctly from outside, as on a globe.
+    Outside,
+    // Mirrored in u so textures read correctly from inside, as on a sky dome.
+    Inside,
+}
+
+// Full spheres by default. Like pbrt's zmin/zmax/phimax, a sphere can be cut to the
+// slab `y_min..y_max` relative to its center and to longitudes up to `phi_max`, measured
+// as in `get_sphere_uv`; texture coordinates then stretch over the remaining part.
 #[derive(Clone)]
 pub struct Sphere {
     center: Point3,
     radius: f32,
+    y_min: f32,
+    y_max: f32,
+    phi_max: f32,
+    uv: SphereUv,
     mat: Arc<dyn Scatter>,
 }
 
@@ -34,9 +52,59 @@ impl Sphere {
         Self {
             center: cen,
             radius: r,
+            y_min: -r,
+            y_max: r,
+            phi_max: 2.0 * f32::consts::PI,
+            uv: SphereUv::Outside,
             mat: m,
         }
     }
+
+    pub fn with_height_range(mut self, y_min: f32, y_max: f32) -> Self {
+        self.y_min = y_min.min(y_max).clamp(-self.radius, self.radius);
+        self.y_max = y_min.max(y_max).clamp(-self.radius, self.radius);
+        self
+    }
+
+    pub fn with_phi_max(mut self, degrees: f32) -> Self {
+        self.phi_max = degrees.clamp(0.0, 360.0).to_radians();
+        self
+    }
+
+    pub fn with_uv(mut self, uv: SphereUv) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    fn is_full(&self) -> bool {
+        self.y_min <= -self.radius
+            && self.y_max >= self.radius
+            && self.phi_max >= 2.0 * f32::consts::PI
+    }
+
+    // Texture coordinates of a point on the unit sphere, or `None` if it was cut away.
+    fn clipped_uv(&self, n: Vec3) -> Option<(f32, f32)> {
+        let (u, v) = get_sphere_uv(n);
+        let (u, v) = if self.is_full() {
+            (u, v)
+        } else {
+            let y = n.y() * self.radius;
+            let phi = u * 2.0 * f32::consts::PI;
+            if y < self.y_min || y > self.y_max || phi > self.phi_max {
+                return None;
+            }
+            let theta_min = (-self.y_min / self.radius).clamp(-1.0, 1.0).acos();
+            let theta_max = (-self.y_max / self.radius).clamp(-1.0, 1.0).acos();
+            (
+                phi / self.phi_max,
+                (v * f32::consts::PI - theta_min) / (theta_max - theta_min),
+            )
+        };
+        match self.uv {
+            SphereUv::Outside => Some((u, v)),
+            SphereUv::Inside => Some((1.0 - u, v)),
+        }
+    }
 }
 
 impl Hittable for Sphere {
@@ -52,41 +120,109 @@ impl Hittable for Sphere {
         }
         let sqrtd = discriminant.sqrt();
 
-        // Find the nearest root that lies in the acceptable range.
-        let mut root = (-half_b - sqrtd) / a;
-        if root < t_min || t_max < root {
-            root = (-half_b + sqrtd) / a;
+        // Find the nearest root that lies in the acceptable range and wasn't cut away.
+        for root in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
             if root < t_min || t_max < root {
-                return None;
+                continue;
             }
+            let p = r.at(root);
+            let outward_normal = (p - self.center) / self.radius;
+            let (u, v) = match self.clipped_uv(outward_normal) {
+                Some(uv) => uv,
+                None => continue,
+            };
+            let mut rec = HitRecord {
+                t: root,
+                p,
+                normal: Vec3::new(0.0, 0.0, 0.0),
+                u,
+                v,
+                mat: self.mat.clone(),
+                front_face: false,
+                color: None,
+                tangent: None,
+            };
+            rec.set_face_normal(r, outward_normal);
+            return Some(rec);
         }
 
-        let p = r.at(root);
-        let mut rec = HitRecord {
-            t: root,
-            p: p,
-            normal: Vec3::new(0.0, 0.0, 0.0),
-            u: 0.0,
-            v: 0.0,
-            mat: self.mat.clone(),
-            front_face: false,
-        };
-
-        let outward_normal = (rec.p - self.center) / self.radius;
-        rec.set_face_normal(r, outward_normal);
-        let (u, v) = get_sphere_uv(outward_normal);
-        rec.u = u;
-        rec.v = v;
-
-        Some(rec)
+        None
     }
 
     fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        // Radii of the circles where the slab cuts the sphere, and the widest circle kept.
+        let ring = |y: f32| (self.radius * self.radius - y * y).max(0.0).sqrt();
+        let (r0, r1) = (ring(self.y_min), ring(self.y_max));
+        let outer = if self.y_min <= 0.0 && self.y_max >= 0.0 {
+            self.radius
+        } else {
+            r0.max(r1)
+        };
+        // Longitude here starts at -x, half a turn from where `sector_extent` starts.
+        let (x0, x1, z0, z1) = sector_extent(outer, r0.min(r1), self.phi_max);
         let output_box = AABB::new(
-            self.center - Vec3::new(self.radius, self.radius, self.radius),
-            self.center + Vec3::new(self.radius, self.radius, self.radius),
+            self.center + Vec3::new(-x1, self.y_min, -z1),
+            self.center + Vec3::new(-x0, self.y_max, -z0),
         );
 
         Some(output_box)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::gray;
+
+    fn hit(sphere: &Sphere, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        sphere.hit(&Ray::new(origin, direction, 0.0), 0.001, f32::INFINITY)
+    }
+
+    fn unit() -> Sphere {
+        Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, gray())
+    }
+
+    #[test]
+    fn cut_away_parts_let_rays_reach_the_far_side() {
+        let dome = unit().with_height_range(0.0, 1.0);
+        let rec = hit(&dome, Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-4);
+        assert!(!rec.front_face);
+        // The texture spans the dome from its rim to the pole.
+        assert!((rec.v - 1.0).abs() < 1e-4);
+
+        // Half the longitudes keep the +z side.
+        let half = unit().with_phi_max(180.0);
+        let front = hit(&half, Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!((front.t - 4.0).abs() < 1e-4 && front.front_face);
+        assert!((front.u - 0.5).abs() < 1e-4);
+        let back = hit(&half, Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!((back.t - 6.0).abs() < 1e-4 && !back.front_face);
+    }
+
+    #[test]
+    fn inside_uvs_mirror_longitude() {
+        let globe = unit();
+        let sky = unit().with_uv(SphereUv::Inside);
+        let origin = Point3::new(0.0, 0.0, 5.0);
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        let outside = hit(&globe, origin, down).unwrap();
+        let inside = hit(&sky, origin, down).unwrap();
+        assert!((outside.u - 0.25).abs() < 1e-4);
+        assert!((inside.u - 0.75).abs() < 1e-4);
+        assert!((inside.v - outside.v).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bounding_box_shrinks_with_the_cut() {
+        let cap = Sphere::new(Point3::new(0.0, 1.0, 0.0), 2.0, gray()).with_height_range(1.0, 2.0);
+        let bbox = cap.bounding_box(0.0, 0.0).unwrap();
+        let rim = 3f32.sqrt();
+        assert!((bbox.min().y() - 2.0).abs() < 1e-4 && (bbox.max().y() - 3.0).abs() < 1e-4);
+        assert!((bbox.max().x() - rim).abs() < 1e-4 && (bbox.min().z() + rim).abs() < 1e-4);
+
+        let quarter = unit().with_phi_max(90.0);
+        let bbox = quarter.bounding_box(0.0, 0.0).unwrap();
+        assert!(bbox.max().x() < 1e-4 && bbox.min().z() > -1e-4);
+    }
+}
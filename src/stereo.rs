@@ -0,0 +1,228 @@
+use super::camera::{look_at_basis, sample_time, Camera, CameraModel};
+use super::ray::Ray;
+use super::vec3::{Point3, Vec3};
+
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy)]
+pub enum StereoLayout {
+    // Left eye in the left half of the image.
+    SideBySide,
+    // Left eye in the top half of the image.
+    OverUnder,
+}
+
+impl StereoLayout {
+    // Picks the eye for an image point and remaps the point into that eye's [0, 1]^2.
+    fn split(self, u: f32, v: f32) -> (bool, f32, f32) {
+        match self {
+            StereoLayout::SideBySide if u < 0.5 => (true, 2.0 * u, v),
+            StereoLayout::SideBySide => (false, 2.0 * u - 1.0, v),
+            StereoLayout::OverUnder if v >= 0.5 => (true, u, 2.0 * v - 1.0),
+            StereoLayout::OverUnder => (false, u, 2.0 * v),
+        }
+    }
+}
+
+// A pair of parallel perspective cameras `ipd` apart. With a finite `convergence`
+// distance each eye's frustum is shifted off-axis so the views meet there, avoiding the
+// vertical parallax of toed-in eyes; pass `f32::INFINITY` for no convergence.
+pub struct StereoCamera {
+    left: Camera,
+    right: Camera,
+    layout: StereoLayout,
+}
+
+impl StereoCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        vfov: f32,
+        eye_aspect_ratio: f32,
+        aperture: f32,
+        focus_dist: f32,
+        ipd: f32,
+        convergence: f32,
+        layout: StereoLayout,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        let (u, _, w) = look_at_basis(lookfrom, lookat, vup);
+        let half_width = eye_aspect_ratio * (0.5 * vfov.to_radians()).tan();
+        let eye = |side: f32| {
+            let from = lookfrom + side * 0.5 * ipd * u;
+            let shift = if convergence.is_finite() {
+                -side * 0.25 * ipd / (convergence * half_width)
+            } else {
+                0.0
+            };
+            Camera::new(
+                from,
+                from - w,
+                vup,
+                vfov,
+                eye_aspect_ratio,
+                aperture,
+                focus_dist,
+                time0,
+                time1,
+            )
+            .with_shift(shift, 0.0)
+        };
+        Self {
+            left: eye(-1.0),
+            right: eye(1.0),
+            layout,
+        }
+    }
+}
+
+impl CameraModel for StereoCamera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let (left, u, v) = self.layout.split(u, v);
+        let eye = if left { &self.left } else { &self.right };
+        eye.ray(u, v)
+    }
+}
+
+// Omni-directional stereo: one equirectangular panorama per eye, where every column is
+// seen from eyes on a circle of diameter `ipd` facing that column's direction. Each eye
+// needs a 2:1 region, so over-under images are square.
+pub struct OdsCamera {
+    origin: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    ipd: f32,
+    convergence: f32,
+    layout: StereoLayout,
+    time0: f32,
+    time1: f32,
+}
+
+impl OdsCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        ipd: f32,
+        convergence: f32,
+        layout: StereoLayout,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            ipd,
+            convergence,
+            layout,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl CameraModel for OdsCamera {
+    fn ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let (left, u, v) = self.layout.split(u, v);
+        let phi = 2.0 * PI * (u - 0.5);
+        let latitude = PI * (v - 0.5);
+        let heading = phi.sin() * self.u - phi.cos() * self.w;
+        let right = phi.cos() * self.u + phi.sin() * self.w;
+        let direction = latitude.cos() * heading + latitude.sin() * self.v;
+
+        let side = if left { -1.0 } else { 1.0 };
+        let offset = side * 0.5 * self.ipd * right;
+        let direction = if self.convergence.is_finite() {
+            self.convergence * direction - offset
+        } else {
+            direction
+        };
+        Some(Ray::new(
+            self.origin + offset,
+            direction,
+            sample_time(self.time0, self.time1),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Where `ray` reaches `distance` along the -z view axis.
+    fn at_depth(ray: &Ray, distance: f32) -> Point3 {
+        let t = (-distance - ray.origin().z()) / ray.direction().z();
+        ray.at(t)
+    }
+
+    fn stereo(convergence: f32, layout: StereoLayout) -> StereoCamera {
+        StereoCamera::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+            0.064,
+            convergence,
+            layout,
+            0.0,
+            0.0,
+        )
+    }
+
+    #[test]
+    fn eye_views_meet_at_the_convergence_distance() {
+        let camera = stereo(2.0, StereoLayout::SideBySide);
+        let left = camera.ray(0.25, 0.5).unwrap();
+        let right = camera.ray(0.75, 0.5).unwrap();
+        assert!((left.origin() - Point3::new(-0.032, 0.0, 0.0)).length() < 1e-6);
+        assert!((right.origin() - Point3::new(0.032, 0.0, 0.0)).length() < 1e-6);
+        assert!((at_depth(&left, 2.0) - Point3::new(0.0, 0.0, -2.0)).length() < 1e-5);
+        assert!((at_depth(&right, 2.0) - Point3::new(0.0, 0.0, -2.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn parallel_eyes_stay_apart() {
+        // Over-under puts the left eye on top.
+        let camera = stereo(f32::INFINITY, StereoLayout::OverUnder);
+        let left = camera.ray(0.5, 0.75).unwrap();
+        let right = camera.ray(0.5, 0.25).unwrap();
+        let gap = at_depth(&right, 100.0) - at_depth(&left, 100.0);
+        assert!((gap - Vec3::new(0.064, 0.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn ods_eyes_sit_across_each_heading() {
+        let camera = OdsCamera::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.064,
+            3.0,
+            StereoLayout::SideBySide,
+            0.0,
+            0.0,
+        );
+        for u in [0.1, 0.3, 0.45] {
+            let left = camera.ray(u, 0.5).unwrap();
+            let right = camera.ray(u + 0.5, 0.5).unwrap();
+            let baseline = right.origin() - left.origin();
+            assert!((baseline.length() - 0.064).abs() < 1e-5);
+            assert!(baseline.dot(left.direction()).abs() < 0.01 * left.direction().length());
+            // Both eyes converge on the same point 3 units out.
+            let meet = left.at(1.0);
+            assert!((meet - right.at(1.0)).length() < 1e-5);
+            assert!((meet.length() - 3.0).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use super::mesh::{LoadError, TriangleMesh};
+use super::vec3::Point3;
+
+use std::fs;
+use std::path::Path;
+
+fn parse_binary(data: &[u8]) -> Result<TriangleMesh, LoadError> {
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    let mut positions = Vec::with_capacity(3 * count);
+    let mut indices = Vec::with_capacity(count);
+
+    let read_f32 =
+        |at: usize| f32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+    for i in 0..count {
+        // 12 bytes of facet normal, 3 vertices of 12 bytes, 2 bytes of attributes.
+        let facet = 84 + 50 * i + 12;
+        for v in 0..3 {
+            let at = facet + 12 * v;
+            positions.push(Point3::new(
+                read_f32(at),
+                read_f32(at + 4),
+                read_f32(at + 8),
+            ));
+        }
+        indices.push([3 * i, 3 * i + 1, 3 * i + 2]);
+    }
+
+    Ok(TriangleMesh::new(positions, indices))
+}
+
+fn parse_ascii(text: &str) -> Result<TriangleMesh, LoadError> {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    let mut facet = Vec::with_capacity(3);
+
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["vertex", x, y, z] => {
+                let parse = |s: &str| {
+                    s.parse::<f32>()
+                        .map_err(|_| LoadError::Parse(format!("invalid stl vertex '{}'", line)))
+                };
+                facet.push(Point3::new(parse(x)?, parse(y)?, parse(z)?));
+            }
+            ["endfacet"] => {
+                if facet.len() != 3 {
+                    return Err(LoadError::Parse(format!(
+                        "stl facet has {} vertices",
+                        facet.len()
+                    )));
+                }
+                let base = positions.len();
+                positions.append(&mut facet);
+                indices.push([base, base + 1, base + 2]);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(TriangleMesh::new(positions, indices))
+}
+
+pub fn parse_stl(data: &[u8]) -> Result<TriangleMesh, LoadError> {
+    // Binary files may also start with "solid", so trust the size field when it matches.
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == 84 + 50 * count {
+            return parse_binary(data);
+        }
+    }
+
+    if data.starts_with(b"solid") {
+        let text = std::str::from_utf8(data)
+            .map_err(|_| LoadError::Parse("invalid ascii stl data".to_string()))?;
+        parse_ascii(text)
+    } else {
+        Err(LoadError::Parse("not an stl file".to_string()))
+    }
+}
+
+pub fn load_stl<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, LoadError> {
+    parse_stl(&fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(triangles: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        // Starting the header with "solid" must not make it look like ascii.
+        let mut data = b"solid but actually binary".to_vec();
+        data.resize(80, 0);
+        data.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+        for tri in triangles {
+            data.extend_from_slice(&[0; 12]);
+            for v in tri {
+                for c in v {
+                    data.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            data.extend_from_slice(&[0; 2]);
+        }
+        data
+    }
+
+    #[test]
+    fn ascii_facets() {
+        let text = "solid test
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 1 0 0
+      vertex 1 1 0
+      vertex 0 1 -2.5e-1
+    endloop
+  endfacet
+endsolid test
+";
+        let mesh = parse_stl(text.as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 6);
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [3, 4, 5]]);
+        let p = mesh.positions[5];
+        assert_eq!((p.x(), p.y(), p.z()), (0.0, 1.0, -0.25));
+    }
+
+    #[test]
+    fn binary_facets() {
+        let data = binary(&[
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.5]],
+        ]);
+        let mesh = parse_stl(&data).unwrap();
+        assert_eq!(mesh.positions.len(), 6);
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [3, 4, 5]]);
+        let p = mesh.positions[5];
+        assert_eq!((p.x(), p.y(), p.z()), (7.0, 8.0, 9.5));
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        let short_facet = "solid test
+facet normal 0 0 1
+outer loop
+vertex 0 0 0
+vertex 1 0 0
+endloop
+endfacet
+endsolid test
+";
+        assert!(matches!(
+            parse_stl(short_facet.as_bytes()),
+            Err(LoadError::Parse(_))
+        ));
+        assert!(matches!(parse_stl(b"not an stl"), Err(LoadError::Parse(_))));
+    }
+}
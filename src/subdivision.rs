@@ -0,0 +1,555 @@
+use super::mesh::{LoadError, TriangleMesh};
+use super::vec3::{Point3, Vec3};
+
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Boundary {
+    // Boundary edges are infinitely sharp creases that every boundary vertex slides along.
+    EdgesOnly,
+    // As above, and vertices at the corner of a single face also stay where they are.
+    EdgesAndCorners,
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+// Edge and vertex adjacency of a polygon mesh, numbered in the order faces list them.
+struct Topology {
+    edges: Vec<(usize, usize)>,
+    edge_index: HashMap<(usize, usize), usize>,
+    edge_faces: Vec<Vec<usize>>,
+    vertex_edges: Vec<Vec<usize>>,
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    fn new(vertex_count: usize, faces: &[Vec<usize>]) -> Self {
+        let mut edges = Vec::new();
+        let mut edge_index = HashMap::new();
+        let mut edge_faces: Vec<Vec<usize>> = Vec::new();
+        let mut vertex_edges = vec![Vec::new(); vertex_count];
+        let mut vertex_faces = vec![Vec::new(); vertex_count];
+        for (f, face) in faces.iter().enumerate() {
+            for (i, &a) in face.iter().enumerate() {
+                vertex_faces[a].push(f);
+                let key = edge_key(a, face[(i + 1) % face.len()]);
+                let e = *edge_index.entry(key).or_insert_with(|| {
+                    edges.push(key);
+                    edge_faces.push(Vec::new());
+                    vertex_edges[key.0].push(edges.len() - 1);
+                    vertex_edges[key.1].push(edges.len() - 1);
+                    edges.len() - 1
+                });
+                edge_faces[e].push(f);
+            }
+        }
+        Self {
+            edges,
+            edge_index,
+            edge_faces,
+            vertex_edges,
+            vertex_faces,
+        }
+    }
+
+    fn other(&self, e: usize, v: usize) -> usize {
+        let (a, b) = self.edges[e];
+        if a == v {
+            b
+        } else {
+            a
+        }
+    }
+}
+
+// A polygon control cage that is refined into a smooth surface by Catmull-Clark (any
+// polygons) or Loop (triangles) subdivision. Edges can be tagged as creases of a given
+// sharpness: each level of subdivision treats a crease as sharp and lowers its
+// sharpness by one, so a sharpness of 2.5 rounds off after a few levels and
+// `f32::INFINITY` stays sharp in the limit.
+pub struct SubdivisionMesh {
+    positions: Vec<Point3>,
+    faces: Vec<Vec<usize>>,
+    creases: HashMap<(usize, usize), f32>,
+    corners: HashSet<usize>,
+    boundary: Boundary,
+}
+
+impl SubdivisionMesh {
+    // Faces list vertex indices in counter-clockwise order seen from the front. Each
+    // needs at least three distinct vertices.
+    pub fn new(positions: Vec<Point3>, faces: Vec<Vec<usize>>) -> Result<Self, LoadError> {
+        for face in faces.iter() {
+            if face.len() < 3 {
+                return Err(LoadError::Parse(
+                    "subdivision faces need at least 3 vertices".to_string(),
+                ));
+            }
+            if face.iter().any(|&v| v >= positions.len()) {
+                return Err(LoadError::Parse(
+                    "subdivision face index out of range".to_string(),
+                ));
+            }
+            if face
+                .iter()
+                .enumerate()
+                .any(|(i, v)| face[i + 1..].contains(v))
+            {
+                return Err(LoadError::Parse(
+                    "subdivision faces cannot repeat a vertex".to_string(),
+                ));
+            }
+        }
+        Ok(Self {
+            positions,
+            faces,
+            creases: HashMap::new(),
+            corners: HashSet::new(),
+            boundary: Boundary::EdgesOnly,
+        })
+    }
+
+    pub fn with_crease(mut self, a: usize, b: usize, sharpness: f32) -> Self {
+        self.creases.insert(edge_key(a, b), sharpness);
+        self
+    }
+
+    // Pins a vertex so the surface passes through it with a point.
+    pub fn with_corner(mut self, v: usize) -> Self {
+        self.corners.insert(v);
+        self
+    }
+
+    pub fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    pub fn catmull_clark(&self, levels: u32) -> TriangleMesh {
+        self.refine(|m| m.catmull_clark_step(), levels)
+            .to_triangles()
+    }
+
+    // Polygons other than triangles are split into fans first.
+    pub fn loop_subdivision(&self, levels: u32) -> TriangleMesh {
+        let triangles = self
+            .faces
+            .iter()
+            .flat_map(|face| (1..face.len() - 1).map(move |i| vec![face[0], face[i], face[i + 1]]))
+            .collect();
+        let mut cage = self.clone_level(triangles);
+        // Found on the polygons, as fanning can give a corner more than one face.
+        if self.boundary == Boundary::EdgesAndCorners {
+            let topo = Topology::new(self.positions.len(), &self.faces);
+            cage.corners
+                .extend((0..self.positions.len()).filter(|&v| topo.vertex_faces[v].len() == 1));
+        }
+        cage.refine(|m| m.loop_step(), levels).to_triangles()
+    }
+
+    fn refine<F: Fn(&Self) -> Self>(&self, step: F, levels: u32) -> Self {
+        let mut mesh = self.clone_level(self.faces.clone());
+        for _ in 0..levels {
+            mesh = step(&mesh);
+        }
+        mesh
+    }
+
+    // A mesh with the same vertices, creases and rules but new faces.
+    fn clone_level(&self, faces: Vec<Vec<usize>>) -> Self {
+        Self {
+            positions: self.positions.clone(),
+            faces,
+            creases: self.creases.clone(),
+            corners: self.corners.clone(),
+            boundary: self.boundary,
+        }
+    }
+
+    fn sharpness(&self, topo: &Topology, e: usize) -> f32 {
+        if topo.edge_faces[e].len() != 2 {
+            return f32::INFINITY;
+        }
+        self.creases.get(&topo.edges[e]).copied().unwrap_or(0.0)
+    }
+
+    // Blends an edge's smooth point toward its midpoint by its sharpness.
+    fn edge_point<F: Fn() -> Point3>(&self, topo: &Topology, e: usize, smooth: F) -> Point3 {
+        let (a, b) = topo.edges[e];
+        let midpoint = 0.5 * (self.positions[a] + self.positions[b]);
+        let s = self.sharpness(topo, e);
+        if s >= 1.0 {
+            midpoint
+        } else {
+            let smooth = smooth();
+            smooth + s * (midpoint - smooth)
+        }
+    }
+
+    // The crease and corner rules shared by both schemes: a vertex on two sharp edges
+    // moves along them, one on more (or a corner) stays put, and the rest are smooth.
+    fn vertex_point(&self, topo: &Topology, v: usize, smooth: Point3) -> Point3 {
+        let p = self.positions[v];
+        let sharp: Vec<usize> = topo.vertex_edges[v]
+            .iter()
+            .copied()
+            .filter(|&e| self.sharpness(topo, e) > 0.0)
+            .collect();
+        let corner = self.corners.contains(&v)
+            || (self.boundary == Boundary::EdgesAndCorners
+                && topo.vertex_faces[v].len() == 1
+                && sharp.len() == 2);
+        let sharp_point = if corner || sharp.len() > 2 {
+            p
+        } else if sharp.len() == 2 {
+            let (a, b) = (topo.other(sharp[0], v), topo.other(sharp[1], v));
+            0.125 * (self.positions[a] + self.positions[b]) + 0.75 * p
+        } else {
+            return smooth;
+        };
+        // Semi-sharp features fade into the smooth rule as their sharpness runs out.
+        let s = if corner {
+            1.0
+        } else {
+            sharp.iter().map(|&e| self.sharpness(topo, e)).sum::<f32>() / sharp.len() as f32
+        };
+        if s >= 1.0 {
+            sharp_point
+        } else {
+            smooth + s * (sharp_point - smooth)
+        }
+    }
+
+    // Creases of the next level, where vertex v keeps index v and the point of edge e
+    // has index `edge_offset + e`.
+    fn child_creases(&self, topo: &Topology, edge_offset: usize) -> HashMap<(usize, usize), f32> {
+        let mut creases = HashMap::new();
+        for (&(a, b), &s) in self.creases.iter() {
+            if s <= 1.0 {
+                continue;
+            }
+            if let Some(&e) = topo.edge_index.get(&(a, b)) {
+                creases.insert(edge_key(a, edge_offset + e), s - 1.0);
+                creases.insert(edge_key(edge_offset + e, b), s - 1.0);
+            }
+        }
+        creases
+    }
+
+    fn catmull_clark_step(&self) -> Self {
+        let topo = Topology::new(self.positions.len(), &self.faces);
+        let (vertex_count, edge_count) = (self.positions.len(), topo.edges.len());
+
+        let face_points: Vec<Point3> = self
+            .faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .fold(Vec3::new(0.0, 0.0, 0.0), |sum, &v| sum + self.positions[v])
+                    / face.len() as f32
+            })
+            .collect();
+
+        let mut positions = Vec::with_capacity(vertex_count + edge_count + self.faces.len());
+        for v in 0..vertex_count {
+            let n = topo.vertex_edges[v].len();
+            let faces = &topo.vertex_faces[v];
+            if n == 0 || faces.is_empty() {
+                positions.push(self.positions[v]);
+                continue;
+            }
+            let q = faces
+                .iter()
+                .fold(Vec3::new(0.0, 0.0, 0.0), |sum, &f| sum + face_points[f])
+                / faces.len() as f32;
+            let r = topo.vertex_edges[v]
+                .iter()
+                .fold(Vec3::new(0.0, 0.0, 0.0), |sum, &e| {
+                    sum + 0.5 * (self.positions[v] + self.positions[topo.other(e, v)])
+                })
+                / n as f32;
+            let smooth = (q + 2.0 * r + (n as f32 - 3.0) * self.positions[v]) / n as f32;
+            positions.push(self.vertex_point(&topo, v, smooth));
+        }
+        for e in 0..edge_count {
+            positions.push(self.edge_point(&topo, e, || {
+                let (a, b) = topo.edges[e];
+                let (f0, f1) = (topo.edge_faces[e][0], topo.edge_faces[e][1]);
+                0.25 * (self.positions[a] + self.positions[b] + face_points[f0] + face_points[f1])
+            }));
+        }
+        positions.extend(face_points.iter().copied());
+
+        // One quad per corner of every face, keeping the winding.
+        let edge_point = |a: usize, b: usize| vertex_count + topo.edge_index[&edge_key(a, b)];
+        let mut faces = Vec::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            let k = face.len();
+            for i in 0..k {
+                let (prev, v, next) = (face[(i + k - 1) % k], face[i], face[(i + 1) % k]);
+                faces.push(vec![
+                    v,
+                    edge_point(v, next),
+                    vertex_count + edge_count + f,
+                    edge_point(prev, v),
+                ]);
+            }
+        }
+
+        Self {
+            positions,
+            faces,
+            creases: self.child_creases(&topo, vertex_count),
+            corners: self.corners.clone(),
+            boundary: self.boundary,
+        }
+    }
+
+    fn loop_step(&self) -> Self {
+        let topo = Topology::new(self.positions.len(), &self.faces);
+        let vertex_count = self.positions.len();
+
+        let mut positions = Vec::with_capacity(vertex_count + topo.edges.len());
+        for v in 0..vertex_count {
+            let n = topo.vertex_edges[v].len();
+            if n == 0 {
+                positions.push(self.positions[v]);
+                continue;
+            }
+            let ring = topo.vertex_edges[v]
+                .iter()
+                .fold(Vec3::new(0.0, 0.0, 0.0), |sum, &e| {
+                    sum + self.positions[topo.other(e, v)]
+                });
+            let c = 0.375 + 0.25 * (2.0 * PI / n as f32).cos();
+            let beta = (0.625 - c * c) / n as f32;
+            let smooth = (1.0 - n as f32 * beta) * self.positions[v] + beta * ring;
+            positions.push(self.vertex_point(&topo, v, smooth));
+        }
+        for e in 0..topo.edges.len() {
+            positions.push(self.edge_point(&topo, e, || {
+                let (a, b) = topo.edges[e];
+                let opposite = |f: usize| {
+                    let face = &self.faces[f];
+                    self.positions[*face.iter().find(|&&v| v != a && v != b).unwrap()]
+                };
+                let (f0, f1) = (topo.edge_faces[e][0], topo.edge_faces[e][1]);
+                0.375 * (self.positions[a] + self.positions[b])
+                    + 0.125 * (opposite(f0) + opposite(f1))
+            }));
+        }
+
+        // Four triangles per triangle, keeping the winding.
+        let edge_point = |a: usize, b: usize| vertex_count + topo.edge_index[&edge_key(a, b)];
+        let mut faces = Vec::with_capacity(4 * self.faces.len());
+        for face in self.faces.iter() {
+            let (a, b, c) = (face[0], face[1], face[2]);
+            let (ab, bc, ca) = (edge_point(a, b), edge_point(b, c), edge_point(c, a));
+            faces.push(vec![a, ab, ca]);
+            faces.push(vec![ab, b, bc]);
+            faces.push(vec![ca, bc, c]);
+            faces.push(vec![ab, bc, ca]);
+        }
+
+        Self {
+            positions,
+            faces,
+            creases: self.child_creases(&topo, vertex_count),
+            corners: self.corners.clone(),
+            boundary: self.boundary,
+        }
+    }
+
+    // Fans every face into triangles. Normals are averaged over the faces around each
+    // vertex, except across boundaries and creases that are still sharp, where the
+    // vertex is split so the crease stays crisp.
+    fn to_triangles(&self) -> TriangleMesh {
+        let topo = Topology::new(self.positions.len(), &self.faces);
+
+        // Union-find over face corners, joined across smooth edges.
+        let mut first_corner = Vec::with_capacity(self.faces.len());
+        let mut corner_count = 0;
+        for face in self.faces.iter() {
+            first_corner.push(corner_count);
+            corner_count += face.len();
+        }
+        let corner = |f: usize, v: usize| {
+            first_corner[f] + self.faces[f].iter().position(|&w| w == v).unwrap()
+        };
+        let mut parent: Vec<usize> = (0..corner_count).collect();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        for e in 0..topo.edges.len() {
+            if topo.edge_faces[e].len() != 2 || self.sharpness(&topo, e) >= 1.0 {
+                continue;
+            }
+            let (f0, f1) = (topo.edge_faces[e][0], topo.edge_faces[e][1]);
+            let (a, b) = topo.edges[e];
+            for v in [a, b] {
+                let (r0, r1) = (
+                    find(&mut parent, corner(f0, v)),
+                    find(&mut parent, corner(f1, v)),
+                );
+                parent[r0] = r1;
+            }
+        }
+
+        let mut vertex_of_root = HashMap::new();
+        let mut positions = Vec::new();
+        let mut normals: Vec<Vec3> = Vec::new();
+        let mut indices = Vec::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            let p0 = self.positions[face[0]];
+            // Area-weighted, so big faces count for more.
+            let face_normal = (1..face.len() - 1).fold(Vec3::new(0.0, 0.0, 0.0), |n, i| {
+                n + (self.positions[face[i]] - p0).cross(self.positions[face[i + 1]] - p0)
+            });
+            let mut ids = Vec::with_capacity(face.len());
+            for (i, &v) in face.iter().enumerate() {
+                let root = find(&mut parent, first_corner[f] + i);
+                let id = *vertex_of_root.entry(root).or_insert_with(|| {
+                    positions.push(self.positions[v]);
+                    normals.push(Vec3::new(0.0, 0.0, 0.0));
+                    positions.len() - 1
+                });
+                normals[id] += face_normal;
+                ids.push(id);
+            }
+            for i in 1..ids.len() - 1 {
+                indices.push([ids[0], ids[i], ids[i + 1]]);
+            }
+        }
+
+        let mut mesh = TriangleMesh::new(positions, indices);
+        mesh.normals = Some(
+            normals
+                .into_iter()
+                .map(|n| {
+                    if n.near_zero() {
+                        Vec3::new(0.0, 1.0, 0.0)
+                    } else {
+                        n.normalized()
+                    }
+                })
+                .collect(),
+        );
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The [-1, 1]^3 cube with faces wound counter-clockwise from outside.
+    fn cube() -> SubdivisionMesh {
+        let positions = (0..8)
+            .map(|i| {
+                let s = |bit: usize| if i & bit != 0 { 1.0 } else { -1.0 };
+                Point3::new(s(1), s(2), s(4))
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+        ];
+        SubdivisionMesh::new(positions, faces).unwrap()
+    }
+
+    fn has_point(mesh: &TriangleMesh, p: Point3) -> bool {
+        mesh.positions.iter().any(|&q| (q - p).length() < 1e-5)
+    }
+
+    #[test]
+    fn subdivided_cube_shrinks_inside_its_cage() {
+        let mesh = cube().catmull_clark(3);
+        // 6 faces become 6 * 4^3 quads, each fanned into two triangles.
+        assert_eq!(mesh.indices.len(), 2 * 6 * 64);
+        for p in mesh.positions.iter() {
+            assert!(p.x().abs() <= 1.0 && p.y().abs() <= 1.0 && p.z().abs() <= 1.0);
+            assert!(p.length() > 0.5);
+        }
+        assert!(!has_point(&mesh, Point3::new(1.0, 1.0, 1.0)));
+        // Normals point away from the center.
+        let normals = mesh.normals.as_ref().unwrap();
+        for (p, n) in mesh.positions.iter().zip(normals.iter()) {
+            assert!(p.dot(*n) > 0.0);
+        }
+    }
+
+    #[test]
+    fn sharp_creases_keep_the_cube() {
+        let mut cage = cube();
+        // Cube edges join corners whose indices differ in one bit.
+        for v in 0..8 {
+            for bit in [1, 2, 4] {
+                if v & bit == 0 {
+                    cage = cage.with_crease(v, v | bit, f32::INFINITY);
+                }
+            }
+        }
+        let mesh = cage.catmull_clark(2);
+        // Corners on three sharp edges stay put and edges stay straight.
+        assert!(has_point(&mesh, Point3::new(1.0, 1.0, 1.0)));
+        assert!(has_point(&mesh, Point3::new(-1.0, 1.0, 0.0)));
+        // Each face is split where the crease runs, so a corner has a vertex per face.
+        let corners = mesh
+            .positions
+            .iter()
+            .filter(|&&q| (q - Point3::new(-1.0, -1.0, -1.0)).length() < 1e-5)
+            .count();
+        assert_eq!(corners, 3);
+    }
+
+    #[test]
+    fn loop_keeps_pinned_corners_and_open_boundaries() {
+        // Two triangles making an open square.
+        let square = SubdivisionMesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, -1.0),
+                Point3::new(0.0, 0.0, -1.0),
+            ],
+            vec![vec![0, 1, 2], vec![0, 2, 3]],
+        )
+        .unwrap();
+        let mesh = square.loop_subdivision(2);
+        assert_eq!(mesh.indices.len(), 2 * 16);
+        // Boundary vertices slide along the boundary, pulling single-face corners in.
+        assert!(!has_point(&mesh, Point3::new(1.0, 0.0, 0.0)));
+        assert!(mesh.positions.iter().all(|p| p.y().abs() < 1e-6));
+
+        let pinned = square
+            .with_boundary(Boundary::EdgesAndCorners)
+            .loop_subdivision(2);
+        assert!(has_point(&pinned, Point3::new(1.0, 0.0, 0.0)));
+        assert!(has_point(&pinned, Point3::new(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn bad_faces_are_errors() {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        for face in [vec![0, 1], vec![0, 1, 3], vec![0, 1, 1, 2]] {
+            let cage = SubdivisionMesh::new(positions.clone(), vec![face]);
+            assert!(matches!(cage, Err(LoadError::Parse(_))));
+        }
+        assert!(SubdivisionMesh::new(positions, vec![vec![0, 1, 2]]).is_ok());
+    }
+}
@@ -0,0 +1,19 @@
+use super::hittable::Hittable;
+use super::material::{Lambertian, Scatter};
+use super::ray::Ray;
+use super::texture::ConstantTexture;
+use super::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// Any material, for tests that only look at geometry.
+pub fn gray() -> Arc<dyn Scatter> {
+    Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.5, 0.5, 0.5,
+    ))))
+}
+
+// Where a ray from `origin` along `direction` first meets `world`, if it does.
+pub fn first_hit(world: &dyn Hittable, origin: Point3, direction: Vec3) -> Option<Point3> {
+    let ray = Ray::new(origin, direction, 0.0);
+    world.hit(&ray, 0.001, f32::INFINITY).map(|rec| rec.p)
+}
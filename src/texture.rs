@@ -1,8 +1,26 @@
+use super::hittable::HitRecord;
 use super::perlin::Perlin;
-use super::vec3::Vec3;
+use super::vec3::{Vec3, VectorConst};
+
+use std::sync::Arc;
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3;
+
+    // Lets a texture look at per-hit data beyond (u, v, p), such as vertex colors.
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        self.value(rec.u, rec.v, rec.p)
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        (**self).value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        (**self).value_at(rec)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -45,6 +63,37 @@ impl<T: Texture, U: Texture> Texture for CheckerTexture<T, U> {
     }
 }
 
+// Checkerboard in texture space rather than in world space.
+#[derive(Copy, Clone)]
+pub struct UvCheckerTexture<T: Texture, U: Texture> {
+    odd: T,
+    even: U,
+    uscale: f32,
+    vscale: f32,
+}
+
+impl<T: Texture, U: Texture> UvCheckerTexture<T, U> {
+    pub fn new(odd: T, even: U, uscale: f32, vscale: f32) -> Self {
+        Self {
+            odd,
+            even,
+            uscale,
+            vscale,
+        }
+    }
+}
+
+impl<T: Texture, U: Texture> Texture for UvCheckerTexture<T, U> {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        let cell = (u * self.uscale).floor() as i64 + (v * self.vscale).floor() as i64;
+        if cell.rem_euclid(2) == 0 {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct NoiseTexture {
     noise: Perlin,
@@ -101,3 +150,48 @@ impl Texture for ImageTexture {
         Vec3::new(r, g, b)
     }
 }
+
+#[derive(Clone)]
+pub struct ScaledTexture<T: Texture> {
+    texture: T,
+    scale: f32,
+}
+
+impl<T: Texture> ScaledTexture<T> {
+    pub fn new(texture: T, scale: f32) -> Self {
+        Self { texture, scale }
+    }
+}
+
+impl<T: Texture> Texture for ScaledTexture<T> {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        self.scale * self.texture.value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        self.scale * self.texture.value_at(rec)
+    }
+}
+
+// Modulates a base texture by the interpolated vertex color of the hit surface,
+// falling back to the base texture alone where no vertex colors are present.
+#[derive(Clone)]
+pub struct VertexColorTexture<T: Texture> {
+    base: T,
+}
+
+impl<T: Texture> VertexColorTexture<T> {
+    pub fn new(base: T) -> Self {
+        Self { base }
+    }
+}
+
+impl<T: Texture> Texture for VertexColorTexture<T> {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        self.base.value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        rec.color.unwrap_or(Vec3::ONE) * self.base.value_at(rec)
+    }
+}
@@ -0,0 +1,362 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::material::Scatter;
+use super::matrix::{Mat4, Quat};
+use super::ray::Ray;
+use super::sphere::Sphere;
+use super::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+fn hit_transformed(
+    hitable: &dyn Hittable,
+    matrix: &Mat4,
+    inverse: &Mat4,
+    r: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<HitRecord> {
+    // The direction is left unnormalized so `t` means the same thing in both spaces.
+    let local_ray = Ray::new(
+        inverse.transform_point(r.origin()),
+        inverse.transform_vector(r.direction()),
+        r.time(),
+    );
+    hitable.hit(&local_ray, t_min, t_max).map(|mut hit| {
+        let outward = if hit.front_face {
+            hit.normal
+        } else {
+            -1.0 * hit.normal
+        };
+        hit.p = matrix.transform_point(hit.p);
+        hit.tangent = hit.tangent.map(|t| matrix.transform_vector(t).normalized());
+        hit.set_face_normal(r, Mat4::transform_normal(inverse, outward).normalized());
+        hit
+    })
+}
+
+fn transform_box(matrix: &Mat4, b: &AABB) -> AABB {
+    let mut min = Point3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = Point3::new(-f32::MAX, -f32::MAX, -f32::MAX);
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { b.min().x() } else { b.max().x() },
+            if i & 2 == 0 { b.min().y() } else { b.max().y() },
+            if i & 4 == 0 { b.min().z() } else { b.max().z() },
+        );
+        let p = matrix.transform_point(corner);
+        min = min.min(p);
+        max = max.max(p);
+    }
+    AABB::new(min, max)
+}
+
+// Places a shared prototype in the world through an arbitrary invertible affine matrix.
+pub struct Transform {
+    hitable: Arc<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+}
+
+impl Transform {
+    // `None` when the matrix can't be inverted, as with a zero scale. Importers use this
+    // so a bad file can't panic.
+    pub fn try_new(hitable: Arc<dyn Hittable>, matrix: Mat4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+        Some(Self {
+            hitable,
+            matrix,
+            inverse,
+        })
+    }
+
+    // For hand-built scenes, where a singular matrix is a bug.
+    pub fn new(hitable: Arc<dyn Hittable>, matrix: Mat4) -> Self {
+        Self::try_new(hitable, matrix).expect("transform matrix must be invertible")
+    }
+}
+
+// A sphere of `radius` about `center`, placed by `matrix`. It stays a plain sphere when
+// the matrix keeps it round and is transformed as a whole when it would squash it;
+// `None` when the matrix flattens it completely.
+pub fn place_sphere(
+    center: Point3,
+    radius: f32,
+    matrix: Mat4,
+    mat: Arc<dyn Scatter>,
+) -> Option<Box<dyn Hittable>> {
+    match matrix.uniform_scale() {
+        Some(scale) => Some(Box::new(Sphere::new(
+            matrix.transform_point(center),
+            radius * scale,
+            mat,
+        ))),
+        None => Transform::try_new(Arc::new(Sphere::new(center, radius, mat)), matrix)
+            .map(|t| Box::new(t) as Box<dyn Hittable>),
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_transformed(
+            self.hitable.as_ref(),
+            &self.matrix,
+            &self.inverse,
+            r,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
+        self.hitable
+            .bounding_box(time0, time1)
+            .map(|b| transform_box(&self.matrix, &b))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    fn matrix(&self) -> Mat4 {
+        Mat4::translate(self.translation) * self.rotation.to_matrix() * Mat4::scale(self.scale)
+    }
+
+    fn lerp(&self, other: &Self, time: f32) -> Self {
+        let t = (time - self.time) / (other.time - self.time);
+        Self {
+            time,
+            translation: (1.0 - t) * self.translation + t * other.translation,
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: (1.0 - t) * self.scale + t * other.scale,
+        }
+    }
+}
+
+// Rigid motion plus scale over a sequence of keyframes; the pose holds still outside
+// the first and last keyframe.
+pub struct AnimatedTransform {
+    hitable: Arc<dyn Hittable>,
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    pub fn new(hitable: Arc<dyn Hittable>, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "animated transform needs a keyframe");
+        assert!(
+            keyframes.iter().all(|k| k.time.is_finite()),
+            "keyframe times must be finite"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { hitable, keyframes }
+    }
+
+    fn pose(&self, time: f32) -> Keyframe {
+        let first = &self.keyframes[0];
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return *first;
+        }
+        if time >= last.time {
+            return *last;
+        }
+        let i = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes[i - 1].lerp(&self.keyframes[i], time)
+    }
+
+    pub fn matrix(&self, time: f32) -> Mat4 {
+        self.pose(time).matrix()
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let matrix = self.matrix(r.time());
+        let inverse = matrix.inverse()?;
+        hit_transformed(self.hitable.as_ref(), &matrix, &inverse, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
+        const STEPS: usize = 16;
+
+        let b = self.hitable.bounding_box(time0, time1)?;
+        let radius = (0..8)
+            .map(|i| {
+                Vec3::new(
+                    if i & 1 == 0 { b.min().x() } else { b.max().x() },
+                    if i & 2 == 0 { b.min().y() } else { b.max().y() },
+                    if i & 4 == 0 { b.min().z() } else { b.max().z() },
+                )
+                .length()
+            })
+            .fold(0.0, f32::max);
+
+        // Sample every keyframe segment overlapping the interval. Corners sweep arcs under
+        // rotation, so each sampled box is padded by the arc's maximum deviation from its
+        // chord between samples.
+        let mut times = vec![time0];
+        times.extend(
+            self.keyframes
+                .iter()
+                .map(|k| k.time)
+                .filter(|&t| t > time0 && t < time1),
+        );
+        times.push(time1);
+
+        let mut bbox: Option<AABB> = None;
+        for pair in times.windows(2) {
+            let (start, end) = (self.pose(pair[0]), self.pose(pair[1]));
+            let scale = [start.scale, end.scale]
+                .iter()
+                .map(|s| s.x().abs().max(s.y().abs()).max(s.z().abs()))
+                .fold(0.0, f32::max);
+            let step_angle = start.rotation.angle_to(end.rotation) / STEPS as f32;
+            let pad = radius * scale * (1.0 - (0.5 * step_angle).cos());
+            let pad = Vec3::new(pad, pad, pad);
+
+            for s in 0..=STEPS {
+                let time = pair[0] + (pair[1] - pair[0]) * s as f32 / STEPS as f32;
+                let sample = transform_box(&self.matrix(time), &b);
+                let sample = AABB::new(sample.min() - pad, sample.max() + pad);
+                bbox = Some(match bbox {
+                    Some(bbox) => AABB::surrounding_box(&bbox, &sample),
+                    None => sample,
+                });
+            }
+        }
+        bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::gray;
+
+    fn ellipsoid() -> Transform {
+        let sphere = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, gray()));
+        Transform::new(sphere, Mat4::scale(Vec3::new(1.0, 2.0, 1.0)))
+    }
+
+    #[test]
+    fn hits_and_normals_follow_a_non_uniform_scale() {
+        let shape = ellipsoid();
+        let ray = Ray::new(Point3::new(0.5, 10.0, 0.0), Vec3::new(0.0, -2.0, 0.0), 0.0);
+        let hit = shape.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        // x^2 + y^2 / 4 = 1, with t measured along the unnormalized direction.
+        let y = 2.0 * 0.75f32.sqrt();
+        assert!((hit.p.y() - y).abs() < 1e-4);
+        assert!((hit.t - (10.0 - y) / 2.0).abs() < 1e-4);
+        let expected = Vec3::new(0.5, y / 4.0, 0.0).normalized();
+        assert!((hit.normal - expected).length() < 1e-4);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn hits_from_inside_are_back_faces() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let hit = ellipsoid().hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.p.y() - 2.0).abs() < 1e-4);
+        assert!(!hit.front_face);
+        assert!((hit.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn bounding_box_covers_the_transformed_prototype() {
+        let sphere = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, gray()));
+        let m = Mat4::translate(Vec3::new(5.0, 0.0, 0.0))
+            * Mat4::rotate(45.0, Vec3::new(0.0, 0.0, 1.0));
+        let bbox = Transform::new(sphere, m).bounding_box(0.0, 1.0).unwrap();
+        let half = 2.0f32.sqrt();
+        assert!((bbox.min() - Point3::new(5.0 - half, -half, -1.0)).length() < 1e-4);
+        assert!((bbox.max() - Point3::new(5.0 + half, half, 1.0)).length() < 1e-4);
+    }
+
+    fn spinning_sphere() -> AnimatedTransform {
+        // An off-center sphere swung a third of a turn around the y axis.
+        let sphere = Arc::new(Sphere::new(Point3::new(2.0, 0.0, 0.0), 0.5, gray()));
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let one = Vec3::new(1.0, 1.0, 1.0);
+        AnimatedTransform::new(
+            sphere,
+            vec![
+                Keyframe::new(
+                    1.0,
+                    Vec3::new(0.0, 0.0, 0.0),
+                    Quat::from_axis_angle(120.0, y),
+                    one,
+                ),
+                Keyframe::new(0.0, Vec3::new(0.0, 0.0, 0.0), Quat::IDENTITY, one),
+            ],
+        )
+    }
+
+    #[test]
+    fn poses_interpolate_between_sorted_keyframes() {
+        let shape = spinning_sphere();
+        let center = |time: f32| {
+            shape
+                .matrix(time)
+                .transform_point(Point3::new(2.0, 0.0, 0.0))
+        };
+        assert!((center(0.0) - Point3::new(2.0, 0.0, 0.0)).length() < 1e-4);
+        // Turning about y carries +x toward -z.
+        let s = 3f32.sqrt();
+        assert!((center(0.5) - Point3::new(1.0, 0.0, -s)).length() < 1e-4);
+        assert!((center(1.0) - Point3::new(-1.0, 0.0, -s)).length() < 1e-4);
+        // Outside the keyframes the pose holds still.
+        assert!((center(3.0) - center(1.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn motion_bounds_cover_the_whole_sweep() {
+        let shape = spinning_sphere();
+        let bbox = shape.bounding_box(0.0, 1.0).unwrap();
+        for i in 0..=32 {
+            let time = i as f32 / 32.0;
+            let c = shape
+                .matrix(time)
+                .transform_point(Point3::new(2.0, 0.0, 0.0));
+            for k in 0..3 {
+                assert!(c[k] - 0.5 >= bbox.min()[k] - 1e-4 && c[k] + 0.5 <= bbox.max()[k] + 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn singular_matrices_have_no_transform() {
+        let flat = Mat4::scale(Vec3::new(1.0, 0.0, 1.0));
+        let sphere = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, gray()));
+        assert!(Transform::try_new(sphere, flat).is_none());
+        assert!(place_sphere(Point3::new(0.0, 0.0, 0.0), 1.0, flat, gray()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "keyframe times must be finite")]
+    fn nan_keyframe_times_are_rejected() {
+        let sphere = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, gray()));
+        let still = |time| {
+            Keyframe::new(
+                time,
+                Vec3::new(0.0, 0.0, 0.0),
+                Quat::IDENTITY,
+                Vec3::new(1.0, 1.0, 1.0),
+            )
+        };
+        AnimatedTransform::new(sphere, vec![still(0.0), still(f32::NAN)]);
+    }
+}
@@ -0,0 +1,119 @@
+use super::aabb::AABB;
+use super::hittable::{HitRecord, Hittable};
+use super::material::Scatter;
+use super::mesh::TriangleMesh;
+use super::ray::Ray;
+use super::vec3::Vec3;
+
+use std::sync::Arc;
+
+pub struct Triangle {
+    mesh: Arc<TriangleMesh>,
+    index: usize,
+    mat: Arc<dyn Scatter>,
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<TriangleMesh>, index: usize, mat: Arc<dyn Scatter>) -> Self {
+        Self { mesh, index, mat }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let p0 = self.mesh.position(i0, r.time());
+        let p1 = self.mesh.position(i1, r.time());
+        let p2 = self.mesh.position(i2, r.time());
+
+        // Moller-Trumbore
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+        let pvec = r.direction().cross(e2);
+        let det = e1.dot(pvec);
+        if det.abs() < 1.0e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = r.origin() - p0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qvec = tvec.cross(e1);
+        let b2 = r.direction().dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = e2.dot(qvec) * inv_det;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        let b0 = 1.0 - b1 - b2;
+
+        let (u, v) = match &self.mesh.uvs {
+            Some(uvs) => (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            ),
+            None => (b1, b2),
+        };
+
+        let geometric_normal = e1.cross(e2).normalized();
+        let outward_normal = match &self.mesh.normals {
+            Some(normals) => {
+                let n = b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2];
+                if n.near_zero() {
+                    geometric_normal
+                } else {
+                    n.normalized()
+                }
+            }
+            None => geometric_normal,
+        };
+
+        let color = self
+            .mesh
+            .colors
+            .as_ref()
+            .map(|colors| b0 * colors[i0] + b1 * colors[i1] + b2 * colors[i2]);
+
+        let mut rec = HitRecord {
+            t,
+            p: r.at(t),
+            normal: outward_normal,
+            u,
+            v,
+            mat: self.mat.clone(),
+            front_face: false,
+            color,
+            tangent: None,
+        };
+        rec.set_face_normal(r, outward_normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let mut min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vec3::new(-f32::MAX, -f32::MAX, -f32::MAX);
+        let times = if self.mesh.motion.len() < 2 {
+            vec![time0]
+        } else {
+            self.mesh.motion_times(time0, time1)
+        };
+        for time in times {
+            for i in [i0, i1, i2] {
+                let p = self.mesh.position(i, time);
+                min = min.min(p);
+                max = max.max(p);
+            }
+        }
+
+        // Pad so that axis-aligned triangles still get a box with volume.
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+        Some(AABB::new(min - pad, max + pad))
+    }
+}
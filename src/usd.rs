@@ -0,0 +1,1316 @@
+use super::bvh::BVH;
+use super::camera::Camera;
+use super::hittable::Hittable;
+use super::material::{Dielectric, DiffuseLight, Lambertian, Metal, Scatter};
+use super::matrix::Mat4;
+use super::mesh::{LoadError, TriangleMesh};
+use super::sphere::Sphere;
+use super::texture::{ConstantTexture, ImageTexture, Texture, VertexColorTexture};
+use super::transform::place_sphere;
+use super::vec3::{Color, Point3, Vec3};
+use super::world::HitableList;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Asset(String),
+    Path(String),
+    Num(f64),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, LoadError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let unterminated = |what: &str| LoadError::Parse(format!("unterminated {}", what));
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '"' || c == '\'' {
+            let triple = i + 2 < chars.len() && chars[i + 1] == c && chars[i + 2] == c;
+            let mut s = String::new();
+            if triple {
+                i += 3;
+                while i + 2 < chars.len()
+                    && !(chars[i] == c && chars[i + 1] == c && chars[i + 2] == c)
+                {
+                    s.push(chars[i]);
+                    i += 1;
+                }
+                if i + 2 >= chars.len() {
+                    return Err(unterminated("string"));
+                }
+                i += 3;
+            } else {
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        i += 1;
+                    }
+                    s.push(chars[i]);
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(unterminated("string"));
+                }
+                i += 1;
+            }
+            tokens.push(Token::Str(s));
+        } else if c == '@' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '@' {
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(unterminated("asset path"));
+            }
+            tokens.push(Token::Asset(chars[start..i].iter().collect()));
+            i += 1;
+        } else if c == '<' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '>' {
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(unterminated("path"));
+            }
+            tokens.push(Token::Path(chars[start..i].iter().collect()));
+            i += 1;
+        } else if "()[]{}=,;:".contains(c) {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        } else {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || "_.:-+!".contains(chars[i])) {
+                // A ':' directly followed by whitespace separates time samples from values.
+                if chars[i] == ':' && (i + 1 >= chars.len() || chars[i + 1].is_whitespace()) {
+                    break;
+                }
+                i += 1;
+            }
+            if start == i {
+                return Err(LoadError::Parse(format!("unexpected character '{}'", c)));
+            }
+            let word: String = chars[start..i].iter().collect();
+            match word.parse::<f64>() {
+                Ok(n) if !word.starts_with(|c: char| c.is_alphabetic()) => {
+                    tokens.push(Token::Num(n))
+                }
+                _ => tokens.push(Token::Ident(word)),
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Clone)]
+enum Value {
+    Num(f64),
+    Str(String),
+    Asset(String),
+    Path(String),
+    Ident(String),
+    List(Vec<Value>),
+    None,
+}
+
+impl Value {
+    fn num(&self) -> Option<f32> {
+        match self {
+            Value::Num(n) => Some(*n as f32),
+            Value::Ident(s) if s == "true" => Some(1.0),
+            Value::Ident(s) if s == "false" => Some(0.0),
+            _ => None,
+        }
+    }
+
+    fn nums(&self) -> Vec<f32> {
+        let mut out = Vec::new();
+        self.flatten_into(&mut out);
+        out
+    }
+
+    fn flatten_into(&self, out: &mut Vec<f32>) {
+        match self {
+            Value::List(items) => items.iter().for_each(|v| v.flatten_into(out)),
+            v => out.extend(v.num()),
+        }
+    }
+
+    fn vec3(&self) -> Option<Vec3> {
+        let n = self.nums();
+        (n.len() == 3).then(|| Vec3::new(n[0], n[1], n[2]))
+    }
+
+    fn vec3s(&self) -> Vec<Vec3> {
+        self.nums()
+            .chunks_exact(3)
+            .map(|c| Vec3::new(c[0], c[1], c[2]))
+            .collect()
+    }
+
+    fn text(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) | Value::Asset(s) | Value::Ident(s) | Value::Path(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn paths(&self) -> Vec<String> {
+        match self {
+            Value::Path(p) => vec![p.clone()],
+            Value::List(items) => items.iter().flat_map(|v| v.paths()).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Attribute {
+    value: Value,
+    interpolation: Option<String>,
+}
+
+#[derive(Clone, Default)]
+struct Prim {
+    specifier: String,
+    type_name: String,
+    name: String,
+    attributes: HashMap<String, Attribute>,
+    references: Vec<String>,
+    children: Vec<Prim>,
+}
+
+impl Prim {
+    fn get(&self, name: &str) -> Option<&Value> {
+        self.attributes.get(name).map(|a| &a.value)
+    }
+
+    fn float(&self, names: &[&str], default: f32) -> f32 {
+        names
+            .iter()
+            .find_map(|n| self.get(n).and_then(|v| v.num()))
+            .unwrap_or(default)
+    }
+
+    fn color(&self, names: &[&str]) -> Option<Color> {
+        names
+            .iter()
+            .find_map(|n| self.get(n).and_then(|v| v.vec3()))
+    }
+
+    fn text(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(|v| v.text())
+    }
+
+    fn child(&self, name: &str) -> Option<&Prim> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    warnings: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, LoadError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| LoadError::Parse("unexpected end of usda file".to_string()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), LoadError> {
+        match self.next()? {
+            Token::Punct(p) if p == c => Ok(()),
+            _ => Err(LoadError::Parse(format!("expected '{}'", c))),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, LoadError> {
+        match self.next()? {
+            Token::Ident(s) => Ok(s),
+            _ => Err(LoadError::Parse("expected an identifier".to_string())),
+        }
+    }
+
+    fn skip_balanced(&mut self) -> Result<(), LoadError> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+                Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, LoadError> {
+        match self.peek().cloned() {
+            Some(Token::Punct(open)) if open == '(' || open == '[' => {
+                let close = if open == '(' { ')' } else { ']' };
+                self.next()?;
+                let mut items = Vec::new();
+                while !self.is_punct(close) {
+                    items.push(self.value()?);
+                    if self.is_punct(',') {
+                        self.next()?;
+                    }
+                }
+                self.next()?;
+                Ok(Value::List(items))
+            }
+            Some(Token::Punct('{')) => {
+                // Time samples keep the earliest value; other dictionaries are skipped.
+                let is_samples = matches!(self.tokens.get(self.pos + 1), Some(Token::Num(_)))
+                    && self.tokens.get(self.pos + 2) == Some(&Token::Punct(':'));
+                if !is_samples {
+                    self.skip_balanced()?;
+                    return Ok(Value::None);
+                }
+                self.next()?;
+                let mut first: Option<(f64, Value)> = None;
+                while !self.is_punct('}') {
+                    let time = match self.next()? {
+                        Token::Num(t) => t,
+                        _ => return Err(LoadError::Parse("expected a sample time".to_string())),
+                    };
+                    self.expect(':')?;
+                    let value = self.value()?;
+                    if first.as_ref().is_none_or(|(t, _)| time < *t) {
+                        first = Some((time, value));
+                    }
+                    if self.is_punct(',') {
+                        self.next()?;
+                    }
+                }
+                self.next()?;
+                Ok(first.map(|(_, v)| v).unwrap_or(Value::None))
+            }
+            _ => Ok(match self.next()? {
+                Token::Num(n) => Value::Num(n),
+                Token::Str(s) => Value::Str(s),
+                Token::Asset(s) => Value::Asset(s),
+                Token::Path(s) => Value::Path(s),
+                Token::Ident(s) if s == "None" => Value::None,
+                Token::Ident(s) => Value::Ident(s),
+                Token::Punct(c) => return Err(LoadError::Parse(format!("unexpected '{}'", c))),
+            }),
+        }
+    }
+
+    // Parses a `( ... )` metadata block into key/value pairs; list-editing keywords are
+    // treated as plain assignments.
+    fn metadata(&mut self) -> Result<Vec<(String, Value)>, LoadError> {
+        let mut entries = Vec::new();
+        if !self.is_punct('(') {
+            return Ok(entries);
+        }
+        self.next()?;
+        while !self.is_punct(')') {
+            match self.next()? {
+                Token::Str(_) | Token::Punct(';') | Token::Punct(',') => {}
+                Token::Ident(mut key) => {
+                    if ["prepend", "append", "add", "delete", "reorder"].contains(&key.as_str()) {
+                        key = self.ident()?;
+                    }
+                    if self.is_punct('=') {
+                        self.next()?;
+                        let value = self.value()?;
+                        entries.push((key, value));
+                    }
+                }
+                _ => return Err(LoadError::Parse("malformed metadata".to_string())),
+            }
+        }
+        self.next()?;
+        Ok(entries)
+    }
+
+    fn prim(&mut self, specifier: String) -> Result<Prim, LoadError> {
+        let mut prim = Prim {
+            specifier,
+            ..Prim::default()
+        };
+        if let Some(Token::Ident(_)) = self.peek() {
+            prim.type_name = self.ident()?;
+        }
+        prim.name = match self.next()? {
+            Token::Str(s) => s,
+            _ => return Err(LoadError::Parse("expected a prim name".to_string())),
+        };
+        for (key, value) in self.metadata()? {
+            match key.as_str() {
+                "references" | "inherits" | "specializes" => {
+                    if let Value::Asset(file) = &value {
+                        self.warnings
+                            .push(format!("external reference '{}' not supported", file));
+                    }
+                    if let Value::List(items) = &value {
+                        if items.iter().any(|v| matches!(v, Value::Asset(_))) {
+                            self.warnings
+                                .push("external references not supported".to_string());
+                        }
+                    }
+                    prim.references.extend(value.paths());
+                }
+                "payload" => self.warnings.push("payloads not supported".to_string()),
+                _ => {}
+            }
+        }
+        self.expect('{')?;
+        self.body(&mut prim)?;
+        Ok(prim)
+    }
+
+    fn body(&mut self, prim: &mut Prim) -> Result<(), LoadError> {
+        loop {
+            let token = self.next()?;
+            let word = match token {
+                Token::Punct('}') => return Ok(()),
+                Token::Punct(';') => continue,
+                Token::Ident(w) => w,
+                _ => {
+                    return Err(LoadError::Parse(
+                        "unexpected token in prim body".to_string(),
+                    ))
+                }
+            };
+
+            match word.as_str() {
+                "def" | "over" | "class" => {
+                    let child = self.prim(word)?;
+                    prim.children.push(child);
+                }
+                "variantSet" => {
+                    self.warnings.push("variant sets not supported".to_string());
+                    self.next()?;
+                    self.expect('=')?;
+                    self.skip_balanced()?;
+                }
+                "reorder" => {
+                    self.ident()?;
+                    self.expect('=')?;
+                    self.value()?;
+                }
+                "rel" => {
+                    let name = self.ident()?;
+                    if self.is_punct('=') {
+                        self.next()?;
+                        let value = self.value()?;
+                        prim.attributes.insert(
+                            name,
+                            Attribute {
+                                value,
+                                interpolation: None,
+                            },
+                        );
+                    }
+                    self.metadata()?;
+                }
+                _ => {
+                    // [custom] [uniform] type[[]] name [= value] [(metadata)]
+                    let mut ty = word;
+                    while ty == "custom" || ty == "uniform" || ty == "varying" || ty == "prepend" {
+                        ty = self.ident()?;
+                    }
+                    if self.is_punct('[') {
+                        self.next()?;
+                        self.expect(']')?;
+                    }
+                    let mut name = self.ident()?;
+                    let value = if self.is_punct('=') {
+                        self.next()?;
+                        Some(self.value()?)
+                    } else {
+                        None
+                    };
+                    let metadata = self.metadata()?;
+                    if let Some(stripped) = name.strip_suffix(".timeSamples") {
+                        name = stripped.to_string();
+                    }
+                    if let Some(value) = value {
+                        let interpolation = metadata
+                            .iter()
+                            .find(|(k, _)| k == "interpolation")
+                            .and_then(|(_, v)| v.text().map(|s| s.to_string()));
+                        prim.attributes.insert(
+                            name,
+                            Attribute {
+                                value,
+                                interpolation,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn layer(&mut self) -> Result<(Prim, Vec<(String, Value)>), LoadError> {
+        let metadata = self.metadata()?;
+        let mut root = Prim::default();
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Ident(w) if w == "def" || w == "over" || w == "class" => {
+                    self.next()?;
+                    let prim = self.prim(w)?;
+                    root.children.push(prim);
+                }
+                Token::Punct(';') => {
+                    self.next()?;
+                }
+                _ => return Err(LoadError::Parse("expected a prim definition".to_string())),
+            }
+        }
+        Ok((root, metadata))
+    }
+}
+
+fn find_prim<'a>(root: &'a Prim, path: &str) -> Option<&'a Prim> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .try_fold(root, |prim, name| prim.child(name))
+}
+
+// Flattens internal references: the referenced prim provides weaker opinions that local
+// attributes and children override. `expanding` holds the paths of the references being
+// followed, so a reference back to one of them is a cycle.
+fn compose(prim: &Prim, root: &Prim, expanding: &mut Vec<String>) -> Result<Prim, LoadError> {
+    let mut composed = Prim {
+        specifier: prim.specifier.clone(),
+        type_name: prim.type_name.clone(),
+        name: prim.name.clone(),
+        ..Prim::default()
+    };
+    for reference in &prim.references {
+        let target = reference.split('.').next().unwrap_or("");
+        if let Some(source) = find_prim(root, target) {
+            let path: Vec<&str> = target.split('/').filter(|s| !s.is_empty()).collect();
+            let path = path.join("/");
+            if expanding.contains(&path) {
+                return Err(LoadError::Parse(format!(
+                    "reference cycle through '/{}'",
+                    path
+                )));
+            }
+            expanding.push(path);
+            let source = compose(source, root, expanding)?;
+            expanding.pop();
+            if composed.type_name.is_empty() {
+                composed.type_name = source.type_name.clone();
+            }
+            merge(&mut composed, source);
+        }
+    }
+    let mut local = Prim {
+        attributes: prim.attributes.clone(),
+        ..Prim::default()
+    };
+    for child in &prim.children {
+        local.children.push(compose(child, root, expanding)?);
+    }
+    merge(&mut composed, local);
+    Ok(composed)
+}
+
+fn merge(into: &mut Prim, from: Prim) {
+    into.attributes.extend(from.attributes);
+    for child in from.children {
+        match into.children.iter_mut().find(|c| c.name == child.name) {
+            Some(existing) => {
+                if !child.type_name.is_empty() {
+                    existing.type_name = child.type_name.clone();
+                }
+                merge(existing, child);
+            }
+            None => into.children.push(child),
+        }
+    }
+}
+
+fn rotation(axes: &str, angles: &[f32]) -> Mat4 {
+    axes.chars()
+        .zip(angles)
+        .fold(Mat4::IDENTITY, |m, (axis, &angle)| {
+            let v = match axis {
+                'X' => Vec3::new(1.0, 0.0, 0.0),
+                'Y' => Vec3::new(0.0, 1.0, 0.0),
+                _ => Vec3::new(0.0, 0.0, 1.0),
+            };
+            // The first listed axis is applied first.
+            Mat4::rotate(angle, v) * m
+        })
+}
+
+// Local-to-parent transform from the prim's xformOpOrder; the first op is outermost.
+fn local_transform(prim: &Prim, warnings: &mut Vec<String>) -> (Mat4, bool) {
+    let order: Vec<String> = match prim.get("xformOpOrder") {
+        Some(Value::List(ops)) => ops
+            .iter()
+            .filter_map(|v| v.text().map(|s| s.to_string()))
+            .collect(),
+        _ => return (Mat4::IDENTITY, false),
+    };
+
+    let mut m = Mat4::IDENTITY;
+    let mut reset = false;
+    for op in order {
+        if op == "!resetXformStack!" {
+            reset = true;
+            m = Mat4::IDENTITY;
+            continue;
+        }
+        let (inverse, name) = match op.strip_prefix("!invert!") {
+            Some(name) => (true, name.to_string()),
+            None => (false, op.clone()),
+        };
+        let values = match prim.get(&name) {
+            Some(v) => v.nums(),
+            None => {
+                warnings.push(format!("missing transform op '{}'", name));
+                continue;
+            }
+        };
+        let kind = name
+            .strip_prefix("xformOp:")
+            .unwrap_or(&name)
+            .split(':')
+            .next()
+            .unwrap_or("");
+        let step = match (kind, values.len()) {
+            ("translate", 3) => Mat4::translate(Vec3::new(values[0], values[1], values[2])),
+            ("scale", 3) => Mat4::scale(Vec3::new(values[0], values[1], values[2])),
+            ("scale", 1) => Mat4::scale(Vec3::new(values[0], values[0], values[0])),
+            ("rotateX", 1) => Mat4::rotate(values[0], Vec3::new(1.0, 0.0, 0.0)),
+            ("rotateY", 1) => Mat4::rotate(values[0], Vec3::new(0.0, 1.0, 0.0)),
+            ("rotateZ", 1) => Mat4::rotate(values[0], Vec3::new(0.0, 0.0, 1.0)),
+            ("orient", 4) => Mat4::from_quaternion(values[0], values[1], values[2], values[3]),
+            ("transform", 16) => {
+                let mut rows = [[0.0; 4]; 4];
+                for (i, v) in values.iter().enumerate() {
+                    rows[i / 4][i % 4] = *v;
+                }
+                // USD matrices act on row vectors.
+                Mat4::new(rows).transpose()
+            }
+            (k, 3) if k.starts_with("rotate") && k.len() == 9 => rotation(&k[6..], &values),
+            _ => {
+                warnings.push(format!("unsupported transform op '{}'", name));
+                continue;
+            }
+        };
+        let step = if inverse {
+            step.inverse().unwrap_or(Mat4::IDENTITY)
+        } else {
+            step
+        };
+        m = m * step;
+    }
+    (m, reset)
+}
+
+// Keeps a per-vertex attribute only if every vertex received a value.
+fn complete<T>(values: Vec<Option<T>>, len: usize) -> Option<Vec<T>> {
+    if values.len() == len && values.iter().all(|v| v.is_some()) {
+        Some(values.into_iter().flatten().collect())
+    } else {
+        None
+    }
+}
+
+pub struct UsdScene {
+    pub world: Box<dyn Hittable>,
+    pub camera: Camera,
+    pub width: u32,
+    pub height: u32,
+    pub warnings: Vec<String>,
+}
+
+struct Importer<'a> {
+    root: &'a Prim,
+    base_dir: PathBuf,
+    objects: HitableList,
+    materials: HashMap<String, Arc<dyn Scatter>>,
+    dome: Option<Arc<dyn Texture>>,
+    camera: Option<(Mat4, &'a Prim)>,
+    resolution: Option<(u32, u32)>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, msg: String) {
+        self.warnings.push(msg);
+    }
+
+    fn shader_input(&self, shader: &Prim, input: &str) -> Option<&'a Prim> {
+        let connection = shader.get(&format!("inputs:{}.connect", input))?;
+        let path = connection.paths().into_iter().next()?;
+        find_prim(self.root, path.split('.').next().unwrap_or(""))
+    }
+
+    fn texture_input(&mut self, shader: &Prim, input: &str, default: Color) -> Arc<dyn Texture> {
+        if let Some(source) = self.shader_input(shader, input) {
+            if source.text("info:id") == Some("UsdUVTexture") {
+                if let Some(file) = source.text("inputs:file") {
+                    match image::open(self.base_dir.join(file)) {
+                        Ok(img) => {
+                            let img = img.to_rgb8();
+                            let (nx, ny) = img.dimensions();
+                            return Arc::new(ImageTexture::new(img.into_raw(), nx, ny));
+                        }
+                        Err(err) => {
+                            self.warn(format!("could not load texture '{}': {}", file, err))
+                        }
+                    }
+                }
+            } else {
+                self.warn(format!(
+                    "shader '{}' feeding inputs:{} not supported",
+                    source.name, input
+                ));
+            }
+        }
+        let color = shader
+            .color(&[&format!("inputs:{}", input)])
+            .unwrap_or(default);
+        Arc::new(ConstantTexture::new(color))
+    }
+
+    fn material(&mut self, path: &str) -> Option<Arc<dyn Scatter>> {
+        if let Some(m) = self.materials.get(path) {
+            return Some(m.clone());
+        }
+        let material = match find_prim(self.root, path) {
+            Some(m) => m,
+            None => {
+                self.warn(format!("material '{}' not found", path));
+                return None;
+            }
+        };
+        let shader = material
+            .get("outputs:surface.connect")
+            .and_then(|v| v.paths().into_iter().next())
+            .and_then(|p| find_prim(self.root, p.split('.').next().unwrap_or("")))
+            .or_else(|| {
+                material
+                    .children
+                    .iter()
+                    .find(|c| c.text("info:id") == Some("UsdPreviewSurface"))
+            });
+        let shader = match shader {
+            Some(s) if s.text("info:id") == Some("UsdPreviewSurface") => s,
+            _ => {
+                self.warn(format!("material '{}' has no UsdPreviewSurface", path));
+                return None;
+            }
+        };
+
+        let emissive = shader
+            .color(&["inputs:emissiveColor"])
+            .unwrap_or(Color::new(0.0, 0.0, 0.0));
+        let opacity = shader.float(&["inputs:opacity"], 1.0);
+        let metallic = shader.float(&["inputs:metallic"], 0.0);
+        let roughness = shader.float(&["inputs:roughness"], 0.5);
+
+        let result: Arc<dyn Scatter> = if !emissive.near_zero() {
+            Arc::new(DiffuseLight::new(ConstantTexture::new(emissive)))
+        } else if opacity < 1.0 {
+            Arc::new(Dielectric::new(shader.float(&["inputs:ior"], 1.5)))
+        } else if metallic >= 0.5 {
+            let albedo = shader
+                .color(&["inputs:diffuseColor"])
+                .unwrap_or(Color::new(0.18, 0.18, 0.18));
+            Arc::new(Metal::new(albedo, roughness))
+        } else {
+            let albedo = self.texture_input(shader, "diffuseColor", Color::new(0.18, 0.18, 0.18));
+            Arc::new(Lambertian::new(albedo))
+        };
+        self.materials.insert(path.to_string(), result.clone());
+        Some(result)
+    }
+
+    fn light_material(&self, prim: &Prim) -> Arc<dyn Scatter> {
+        let intensity = prim.float(&["inputs:intensity", "intensity"], 1.0);
+        let exposure = prim.float(&["inputs:exposure", "exposure"], 0.0);
+        let color = prim
+            .color(&["inputs:color", "color"])
+            .unwrap_or(Color::new(1.0, 1.0, 1.0));
+        Arc::new(DiffuseLight::new(ConstantTexture::new(
+            intensity * exposure.exp2() * color,
+        )))
+    }
+
+    fn mesh(&mut self, prim: &Prim) -> Option<TriangleMesh> {
+        let points = prim.get("points")?.vec3s();
+        let counts: Vec<usize> = prim
+            .get("faceVertexCounts")?
+            .nums()
+            .iter()
+            .map(|&n| n as usize)
+            .collect();
+        let face_indices: Vec<usize> = prim
+            .get("faceVertexIndices")?
+            .nums()
+            .iter()
+            .map(|&n| n as usize)
+            .collect();
+        if counts.iter().sum::<usize>() != face_indices.len()
+            || face_indices.iter().any(|&i| i >= points.len())
+        {
+            self.warn(format!("mesh '{}' has inconsistent topology", prim.name));
+            return None;
+        }
+        let left_handed = prim.text("orientation") == Some("leftHanded");
+
+        // Per face-vertex lookup honoring each primvar's interpolation.
+        let corner_value = |attr: &Attribute, data_len: usize, face: usize, corner: usize| {
+            let vertex = face_indices[corner];
+            let index = match attr.interpolation.as_deref() {
+                Some("constant") => 0,
+                Some("uniform") => face,
+                Some("faceVarying") => corner,
+                _ => vertex,
+            };
+            (index < data_len).then_some(index)
+        };
+
+        let normals_attr = prim
+            .attributes
+            .get("normals")
+            .or_else(|| prim.attributes.get("primvars:normals"));
+        let normals = normals_attr.map(|a| a.value.vec3s());
+        let st_name = ["primvars:st", "primvars:UVMap", "primvars:uv"]
+            .iter()
+            .find(|n| prim.attributes.contains_key(**n))
+            .copied();
+        let st_attr = st_name.and_then(|n| prim.attributes.get(n));
+        let st: Option<Vec<(f32, f32)>> = st_attr.map(|a| {
+            a.value
+                .nums()
+                .chunks_exact(2)
+                .map(|c| (c[0], c[1]))
+                .collect()
+        });
+        let st_indices: Option<Vec<usize>> = st_name
+            .and_then(|n| prim.get(&format!("{}:indices", n)))
+            .map(|v| v.nums().iter().map(|&i| i as usize).collect());
+        let color_attr = prim.attributes.get("primvars:displayColor");
+        let colors = color_attr.map(|a| a.value.vec3s());
+
+        let mut positions = Vec::new();
+        let mut out_normals = Vec::new();
+        let mut out_uvs = Vec::new();
+        let mut out_colors = Vec::new();
+        let mut indices = Vec::new();
+        let mut corner = 0;
+        for (face, &count) in counts.iter().enumerate() {
+            let base = positions.len();
+            for c in corner..corner + count {
+                positions.push(points[face_indices[c]]);
+                if let (Some(attr), Some(data)) = (normals_attr, &normals) {
+                    out_normals.push(corner_value(attr, data.len(), face, c).map(|i| data[i]));
+                }
+                if let (Some(attr), Some(data)) = (st_attr, &st) {
+                    let i = corner_value(attr, usize::MAX, face, c)
+                        .map(|i| {
+                            st_indices
+                                .as_ref()
+                                .map_or(Some(i), |idx| idx.get(i).copied())
+                        })
+                        .and_then(|i| i)
+                        .filter(|&i| i < data.len());
+                    out_uvs.push(i.map(|i| data[i]));
+                }
+                if let (Some(attr), Some(data)) = (color_attr, &colors) {
+                    let i = if data.len() == 1 {
+                        Some(0)
+                    } else {
+                        corner_value(attr, data.len(), face, c)
+                    };
+                    out_colors.push(i.map(|i| data[i]));
+                }
+            }
+            for k in 1..count.saturating_sub(1) {
+                if left_handed {
+                    indices.push([base, base + k + 1, base + k]);
+                } else {
+                    indices.push([base, base + k, base + k + 1]);
+                }
+            }
+            corner += count;
+        }
+
+        let n = positions.len();
+        let mut mesh = TriangleMesh::new(positions, indices);
+        mesh.normals = complete(out_normals, n);
+        mesh.uvs = complete(out_uvs, n);
+        mesh.colors = complete(out_colors, n);
+        Some(mesh)
+    }
+
+    fn visit(
+        &mut self,
+        prim: &'a Prim,
+        parent: Mat4,
+        binding: Option<String>,
+    ) -> Result<(), LoadError> {
+        if prim.specifier != "def" {
+            return Ok(());
+        }
+        let (local, reset) = local_transform(prim, &mut self.warnings);
+        let to_world = if reset { local } else { parent * local };
+        let binding = prim
+            .get("material:binding")
+            .and_then(|v| v.paths().into_iter().next())
+            .or(binding);
+
+        let surface = |importer: &mut Self| -> Arc<dyn Scatter> {
+            binding
+                .as_deref()
+                .and_then(|path| importer.material(path))
+                .unwrap_or_else(|| {
+                    let color = prim
+                        .get("primvars:displayColor")
+                        .map(|v| v.vec3s())
+                        .filter(|c| c.len() == 1)
+                        .map(|c| c[0])
+                        .unwrap_or(Color::new(0.18, 0.18, 0.18));
+                    Arc::new(Lambertian::new(VertexColorTexture::new(
+                        ConstantTexture::new(color),
+                    )))
+                })
+        };
+
+        match prim.type_name.as_str() {
+            "" | "Xform" | "Scope" | "Material" | "Shader" | "NodeGraph" | "GeomSubset" => {}
+            "Mesh" => {
+                let mat = surface(self);
+                if let Some(mut mesh) = self.mesh(prim) {
+                    // A constant displayColor is already folded into the fallback material.
+                    let constant = prim
+                        .get("primvars:displayColor")
+                        .is_some_and(|v| v.vec3s().len() == 1);
+                    if constant {
+                        mesh.colors = None;
+                    }
+                    mesh.transform(&to_world);
+                    self.objects.extend(mesh.triangles(mat));
+                }
+            }
+            "Sphere" => {
+                let mat = surface(self);
+                let radius = prim.float(&["radius"], 1.0);
+                let origin = Point3::new(0.0, 0.0, 0.0);
+                match place_sphere(origin, radius, to_world, mat) {
+                    Some(sphere) => self.objects.push(sphere),
+                    None => self.warn(format!(
+                        "{} '{}' has a singular transform",
+                        prim.type_name, prim.name
+                    )),
+                }
+            }
+            "Cube" => {
+                let mat = surface(self);
+                let half = 0.5 * prim.float(&["size"], 2.0);
+                let mut mesh = TriangleMesh::cube();
+                mesh.transform(&(to_world * Mat4::scale(Vec3::new(half, half, half))));
+                self.objects.extend(mesh.triangles(mat));
+            }
+            "RectLight" => {
+                let w = 0.5 * prim.float(&["inputs:width", "width"], 1.0);
+                let h = 0.5 * prim.float(&["inputs:height", "height"], 1.0);
+                let mut mesh = TriangleMesh::rectangle();
+                mesh.transform(&(to_world * Mat4::scale(Vec3::new(w, h, 1.0))));
+                let mat = self.light_material(prim);
+                self.objects.extend(mesh.triangles(mat));
+            }
+            "SphereLight" => {
+                let radius = prim.float(&["inputs:radius", "radius"], 0.5);
+                let origin = Point3::new(0.0, 0.0, 0.0);
+                let mat = self.light_material(prim);
+                match place_sphere(origin, radius, to_world, mat) {
+                    Some(sphere) => self.objects.push(sphere),
+                    None => self.warn(format!(
+                        "{} '{}' has a singular transform",
+                        prim.type_name, prim.name
+                    )),
+                }
+            }
+            "DomeLight" => {
+                let intensity = prim.float(&["inputs:intensity", "intensity"], 1.0)
+                    * prim.float(&["inputs:exposure", "exposure"], 0.0).exp2();
+                let color = prim
+                    .color(&["inputs:color", "color"])
+                    .unwrap_or(Color::new(1.0, 1.0, 1.0));
+                if prim.get("inputs:texture:file").is_some() {
+                    self.warn("DomeLight texture ignored".to_string());
+                }
+                self.dome = Some(Arc::new(ConstantTexture::new(intensity * color)));
+            }
+            "Camera" => {
+                if self.camera.is_none() {
+                    self.camera = Some((to_world, prim));
+                }
+            }
+            "RenderSettings" => {
+                let res = prim.get("resolution").map(|v| v.nums()).unwrap_or_default();
+                if res.len() == 2 {
+                    self.resolution = Some((res[0] as u32, res[1] as u32));
+                }
+            }
+            other => self.warn(format!("prim type '{}' not supported", other)),
+        }
+
+        for child in &prim.children {
+            self.visit(child, to_world, binding.clone())?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, up_axis: &str) -> Result<UsdScene, LoadError> {
+        if self.objects.is_empty() {
+            return Err(LoadError::Parse(
+                "stage contains no supported geometry".to_string(),
+            ));
+        }
+        let bounds = self
+            .objects
+            .bounding_box(0.0, 1.0)
+            .ok_or_else(|| LoadError::Parse("stage geometry is unbounded".to_string()))?;
+        let center = 0.5 * (bounds.min() + bounds.max());
+        let extent = (bounds.max() - bounds.min()).length().max(1.0);
+
+        let (camera, aspect_ratio) = match self.camera {
+            Some((to_world, prim)) => {
+                // Lens values are in tenths of a scene unit.
+                let focal = prim.float(&["focalLength"], 50.0);
+                let h_aperture = prim.float(&["horizontalAperture"], 20.955);
+                let v_aperture = prim.float(&["verticalAperture"], 15.2908);
+                let f_stop = prim.float(&["fStop"], 0.0);
+                let focus = prim.float(&["focusDistance"], 0.0);
+                let aspect_ratio = match self.resolution {
+                    Some((w, h)) => w as f32 / h as f32,
+                    None => h_aperture / v_aperture,
+                };
+                let vfov = 2.0 * (v_aperture / (2.0 * focal)).atan().to_degrees();
+                let (aperture, focus_dist) = if f_stop > 0.0 && focus > 0.0 {
+                    (focal / f_stop / 10.0, focus)
+                } else {
+                    (0.0, 1.0)
+                };
+                let lookfrom = to_world.transform_point(Point3::new(0.0, 0.0, 0.0));
+                let lookat = to_world.transform_point(Point3::new(0.0, 0.0, -1.0));
+                let vup = to_world.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+                (
+                    Camera::new(
+                        lookfrom,
+                        lookat,
+                        vup,
+                        vfov,
+                        aspect_ratio,
+                        aperture,
+                        focus_dist,
+                        0.0,
+                        1.0,
+                    ),
+                    aspect_ratio,
+                )
+            }
+            None => {
+                self.warn("no camera found, framing the whole stage".to_string());
+                let aspect_ratio = self
+                    .resolution
+                    .map_or(16.0 / 9.0, |(w, h)| w as f32 / h as f32);
+                let (offset, vup) = if up_axis == "Z" {
+                    (Vec3::new(0.0, -1.5 * extent, 0.0), Vec3::new(0.0, 0.0, 1.0))
+                } else {
+                    (Vec3::new(0.0, 0.0, 1.5 * extent), Vec3::new(0.0, 1.0, 0.0))
+                };
+                (
+                    Camera::new(
+                        center + offset,
+                        center,
+                        vup,
+                        40.0,
+                        aspect_ratio,
+                        0.0,
+                        1.0,
+                        0.0,
+                        1.0,
+                    ),
+                    aspect_ratio,
+                )
+            }
+        };
+
+        if let Some(dome) = self.dome.take() {
+            self.objects.push(Box::new(Sphere::new(
+                center,
+                100.0 * extent,
+                Arc::new(DiffuseLight::new(dome)),
+            )));
+        }
+
+        let (width, height) = self
+            .resolution
+            .unwrap_or((1280, (1280.0 / aspect_ratio) as u32));
+        Ok(UsdScene {
+            world: Box::new(BVH::new(self.objects, 0.0, 1.0)),
+            camera,
+            width,
+            height,
+            warnings: self.warnings,
+        })
+    }
+}
+
+pub fn parse_usda(text: &str, base_dir: &Path) -> Result<UsdScene, LoadError> {
+    if !text.starts_with("#usda") {
+        return Err(LoadError::Parse("missing #usda header".to_string()));
+    }
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+        warnings: Vec::new(),
+    };
+    let (layer, metadata) = parser.layer()?;
+    let up_axis = metadata
+        .iter()
+        .find(|(k, _)| k == "upAxis")
+        .and_then(|(_, v)| v.text())
+        .unwrap_or("Y")
+        .to_string();
+
+    let mut root = Prim::default();
+    for prim in &layer.children {
+        root.children.push(compose(prim, &layer, &mut Vec::new())?);
+    }
+
+    let mut importer = Importer {
+        root: &root,
+        base_dir: base_dir.to_path_buf(),
+        objects: HitableList::new(),
+        materials: HashMap::new(),
+        dome: None,
+        camera: None,
+        resolution: None,
+        warnings: parser.warnings,
+    };
+    for prim in &root.children {
+        importer.visit(prim, Mat4::IDENTITY, None)?;
+    }
+    importer.finish(&up_axis)
+}
+
+pub fn load_usda<P: AsRef<Path>>(path: P) -> Result<UsdScene, LoadError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    parse_usda(&text, path.parent().unwrap_or_else(|| Path::new(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::first_hit;
+
+    fn parse(text: &str) -> UsdScene {
+        parse_usda(text, Path::new(".")).unwrap()
+    }
+
+    #[test]
+    fn first_xform_op_is_outermost() {
+        // Scaled about its own origin, then moved, so the center is at z = 5.
+        let scene = parse(
+            r#"#usda 1.0
+            def Sphere "Ball"
+            {
+                double radius = 1
+                double3 xformOp:translate = (0, 0, 5)
+                float3 xformOp:scale = (2, 2, 2)
+                uniform token[] xformOpOrder = ["xformOp:translate", "xformOp:scale"]
+            }"#,
+        );
+        let p = first_hit(
+            scene.world.as_ref(),
+            Point3::new(0.0, 0.0, 20.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        assert!((p.unwrap().z() - 7.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn matrices_act_on_row_vectors_inside_parents() {
+        let scene = parse(
+            r#"#usda 1.0
+            def Xform "Parent"
+            {
+                double3 xformOp:translate = (1, 0, 0)
+                uniform token[] xformOpOrder = ["xformOp:translate"]
+
+                def Sphere "Child"
+                {
+                    matrix4d xformOp:transform = ((1, 0, 0, 0), (0, 1, 0, 0), (0, 0, 1, 0), (0, 3, 0, 1))
+                    uniform token[] xformOpOrder = ["xformOp:transform"]
+                }
+            }"#,
+        );
+        let p = first_hit(
+            scene.world.as_ref(),
+            Point3::new(1.0, 10.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+        );
+        assert!((p.unwrap().y() - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn polygon_meshes_are_fanned() {
+        let scene = parse(
+            r#"#usda 1.0
+            def Mesh "Quad"
+            {
+                int[] faceVertexCounts = [4]
+                int[] faceVertexIndices = [0, 1, 2, 3]
+                point3f[] points = [(-1, -1, 0), (1, -1, 0), (1, 1, 0), (-1, 1, 0)]
+            }"#,
+        );
+        for (x, y) in [(-0.9, -0.5), (0.9, 0.5), (0.5, -0.9), (-0.5, 0.9)] {
+            let p = first_hit(
+                scene.world.as_ref(),
+                Point3::new(x, y, 5.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            );
+            assert!(p.unwrap().z().abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn references_are_overridden_locally() {
+        let scene = parse(
+            r#"#usda 1.0
+            def Sphere "Proto"
+            {
+                double radius = 1
+            }
+            def "Instance" (
+                references = </Proto>
+            )
+            {
+                double radius = 2
+                double3 xformOp:translate = (10, 0, 0)
+                uniform token[] xformOpOrder = ["xformOp:translate"]
+            }"#,
+        );
+        let p = first_hit(
+            scene.world.as_ref(),
+            Point3::new(10.0, 10.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+        );
+        assert!((p.unwrap().y() - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn reference_cycles_fail() {
+        let cycle = r#"#usda 1.0
+            def Xform "A" (references = </B>) {}
+            def Xform "B" (references = </A>) {}"#;
+        assert!(matches!(
+            parse_usda(cycle, Path::new(".")),
+            Err(LoadError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn deep_hierarchies_load_and_reference_cycles_fail() {
+        let mut deep = String::from("#usda 1.0\n");
+        for i in 0..40 {
+            deep += &format!("def Xform \"Level{}\" {{\n", i);
+        }
+        deep += "def Sphere \"Leaf\" {}\n";
+        deep += &"}\n".repeat(40);
+        assert!(parse_usda(&deep, Path::new(".")).is_ok());
+
+        let cycle = r#"#usda 1.0
+            def Xform "A" (references = </B>) {}
+            def Xform "B" (references = </A>) {}"#;
+        assert!(matches!(
+            parse_usda(cycle, Path::new(".")),
+            Err(LoadError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn squashed_spheres_keep_their_shape() {
+        let scene = parse(
+            r#"#usda 1.0
+            def Sphere "Egg" {
+                double radius = 1
+                double3 xformOp:scale = (1, 3, 1)
+                uniform token[] xformOpOrder = ["xformOp:scale"]
+            }
+            def SphereLight "Bulb" {
+                float inputs:radius = 1
+                double3 xformOp:translate = (10, 0, 0)
+                double3 xformOp:scale = (3, 1, 1)
+                uniform token[] xformOpOrder = ["xformOp:translate", "xformOp:scale"]
+            }"#,
+        );
+        let world = scene.world.as_ref();
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let top = first_hit(world, Point3::new(0.0, 10.0, 0.0), down);
+        assert!((top.unwrap().y() - 3.0).abs() < 1e-4);
+        let bulb = first_hit(
+            world,
+            Point3::new(20.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+        );
+        assert!((bulb.unwrap().x() - 13.0).abs() < 1e-4);
+        let bulb_top = first_hit(world, Point3::new(10.0, 10.0, 0.0), down);
+        assert!((bulb_top.unwrap().y() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn singular_transforms_warn_instead_of_panicking() {
+        let scene = parse(
+            r#"#usda 1.0
+            def Sphere "Flat" {
+                double3 xformOp:scale = (1, 0, 1)
+                uniform token[] xformOpOrder = ["xformOp:scale"]
+            }
+            def Sphere "Round" {}"#,
+        );
+        assert!(scene.warnings.iter().any(|w| w.contains("singular")));
+    }
+}
@@ -97,6 +97,22 @@ impl Vec3 {
         self / self.length()
     }
 
+    pub fn min(self, other: Self) -> Self {
+        Self {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+            z: self.z.min(other.z),
+        }
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+            z: self.z.max(other.z),
+        }
+    }
+
     pub fn random(r: Range<f32>) -> Self {
         let mut rng = rand::thread_rng();
 
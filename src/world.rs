@@ -25,10 +25,9 @@ impl Hittable for World {
         match self.first() {
             Some(first) => match first.bounding_box(time0, time1) {
                 Some(bbox) => self.iter().skip(1).try_fold(bbox, |acc, hitable| {
-                    match hitable.bounding_box(time0, time1) {
-                        Some(bbox) => Some(AABB::surrounding_box(&acc, &bbox)),
-                        _ => None,
-                    }
+                    hitable
+                        .bounding_box(time0, time1)
+                        .map(|bbox| AABB::surrounding_box(&acc, &bbox))
                 }),
                 _ => None,
             },